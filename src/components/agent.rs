@@ -0,0 +1,94 @@
+//! 自動で遊ぶプログラム（エージェント）とのやり取りに使う型. 1行に1つのJSONでやり取りする.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{ball::BallType, input::InputAction};
+
+fn one_frame() -> u32 {
+    1
+}
+
+/// エージェントから受け取る命令
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum AgentCommand {
+    /// ステージを最初から始める. ステージを省略すると前回のステージ, 初回なら乱数で選んだステージを遊ぶ
+    Reset {
+        stage: Option<String>,
+    },
+    /// 次のリセットでステージを選ぶ乱数の種を設定する. ステージを指定しないリセットは, 今のステージがあってもこの種で選び直す.
+    /// ゲーム自体は乱数を使わない. 画面を出さない場合はシステムの実行順も決まっているので, 同じ操作なら同じ結果になる.
+    /// 画面を出す場合は描画などのシステムも一緒に動くため, 同じ結果になるとは限らない
+    Seed {
+        seed: u64,
+    },
+    /// 操作を指定したフレーム数だけ押し続けて進める. 0フレームなら進めない
+    Step {
+        #[serde(default)]
+        actions: Vec<InputAction>,
+        #[serde(default = "one_frame")]
+        frames: u32,
+    },
+    Close,
+}
+
+/// ボール一つの状態
+#[derive(Clone, Debug, Serialize)]
+pub struct BallObservation {
+    pub ball_type: BallType,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    /// ランチャーに番えられているか
+    pub nocking: bool,
+}
+
+/// 動かせる, または動くブロック一つの状態. 外枠の壁は含まない
+#[derive(Clone, Debug, Serialize)]
+pub struct BlockObservation {
+    pub pos: Vec2,
+    pub angle: f32,
+    pub control_group: u32,
+}
+
+/// ランチャー一つの状態
+#[derive(Clone, Debug, Serialize)]
+pub struct LauncherObservation {
+    pub pos: Vec2,
+    pub angle: f32,
+    pub nocking: bool,
+}
+
+/// 1フレームの観測
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Observation {
+    pub stage: String,
+    /// 経過時間（フレーム）
+    pub tick: u32,
+    /// 残り時間（フレーム）
+    pub remaining_time: u32,
+    pub remaining_balls: u32,
+    /// 残りのボール. 先頭から順に番える
+    pub magazine: Vec<BallType>,
+    pub score: u32,
+    pub strokes: u32,
+    pub operations: u32,
+    pub balls: Vec<BallObservation>,
+    pub blocks: Vec<BlockObservation>,
+    pub launchers: Vec<LauncherObservation>,
+    /// 前回の観測から増えた得点
+    pub reward: f32,
+    /// ゲームが終わったか
+    pub done: bool,
+    /// クリアしたか（終わったときのみtrueになりうる）
+    pub cleared: bool,
+}
+
+/// エージェントへの返答
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentResponse {
+    Observation(Observation),
+    Seed { seed: u64 },
+    Error { message: String },
+    Closed,
+}
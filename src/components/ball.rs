@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::events::ball::SetBallEvent;
 
@@ -6,7 +7,7 @@ use crate::events::ball::SetBallEvent;
 #[derive(Component, Clone, Copy, Debug)]
 pub struct MetalBall;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BallType {
     Normal,
     Metal,
@@ -50,6 +51,47 @@ impl SetBall for Vec<SetBallEvent> {
     }
 }
 
+/// この速さ未満が続いたら静止しているとみなす
+const REST_SPEED: f32 = 0.3;
+/// 低速がこのフレーム数続いたら静止とする
+const REST_FRAMES: u32 = 90;
+/// この半径の範囲内に留まり続けたら振動して抜け出せないとみなす
+const OSCILLATION_RADIUS: f32 = 30.0;
+/// 狭い範囲にこのフレーム数留まったら静止とする
+const OSCILLATION_FRAMES: u32 = 300;
+
+/// 発射されたボールが止まったり, 狭い範囲で跳ね続けたりしていないかを調べる
+#[derive(Component, Default)]
+pub struct BallRestDetector {
+    /// 低速が続いているフレーム数
+    slow_frames: u32,
+    /// 振動判定の基準位置
+    anchor: Vec2,
+    /// 基準位置の近くに留まっているフレーム数
+    anchor_frames: u32,
+}
+impl BallRestDetector {
+    /// 1フレーム分の状態を反映し, 静止していると判定されたらtrueを返す
+    pub fn update(&mut self, pos: Vec2, vel: Vec2) -> bool {
+        if vel.length() < REST_SPEED {
+            self.slow_frames += 1;
+        } else {
+            self.slow_frames = 0;
+        }
+        if pos.distance(self.anchor) < OSCILLATION_RADIUS {
+            self.anchor_frames += 1;
+        } else {
+            self.anchor = pos;
+            self.anchor_frames = 0;
+        }
+        self.slow_frames >= REST_FRAMES || self.anchor_frames >= OSCILLATION_FRAMES
+    }
+}
+
+/// ブロックに触れずに飛び続けているフレーム数
+#[derive(Component, Default)]
+pub struct AirborneFrames(pub u32);
+
 /// 待機状態のボールを表す
 #[derive(Component)]
 pub struct BallNocking;
@@ -2,6 +2,7 @@ use std::f32::consts::{FRAC_2_PI, FRAC_PI_2};
 
 use bevy::prelude::*;
 use bevy_prototype_lyon::shapes;
+use serde::{Deserialize, Serialize};
 
 /// ブロックであることを示す. これを使って衝突判定を行う
 #[derive(Component)]
@@ -12,7 +13,7 @@ pub struct Block;
 pub struct BlockOriginalPos(pub Vec2);
 
 /// ブロックの位置や角度の情報を持っておくコンポーネント
-#[derive(Component, Default)]
+#[derive(Component, Clone, Default, Debug, Serialize, Deserialize)]
 pub struct BlockTransform {
     pub angle: f32,     // 現在の角度
     pub offset: Vec2,   // 位置補正
@@ -52,7 +53,7 @@ impl BlockTransform {
 }
 
 /// 回転の方法
-#[derive(Component, Clone, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub enum RotateStrategy {
     NoRotate,
     Manual(f32),
@@ -65,7 +66,7 @@ impl Default for RotateStrategy {
 }
 
 /// 移動の方法
-#[derive(Component, Clone, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub enum SlideStrategy {
     NoSlide,
     Manual { speed: f32, path: BlockSlidePath }, // キー入力で移動
@@ -89,7 +90,7 @@ impl SlideStrategy {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum BlockSlidePath {
     NoPath,
     StandardLine { theta: f32, width: f32 }, // X軸からの角度を引数に取る
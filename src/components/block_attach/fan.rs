@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use super::utils::{EdgeDirection, ToEdgeDirection};
 
@@ -8,7 +9,7 @@ pub struct WindVisualEffect;
 
 /// 送風機は一定形状のブロックと同じとする.
 /// 当たり判定を拡張するにはブロックと重ねる.
-#[derive(Component, Clone, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Fan {
     pub active: bool,
     pub direction: EdgeDirection,
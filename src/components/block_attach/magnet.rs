@@ -1,8 +1,9 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use super::utils::{EdgeDirection, ToEdgeDirection};
 
-#[derive(Component, Clone, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Magnet {
     pub active: bool,
     pub direction: EdgeDirection,
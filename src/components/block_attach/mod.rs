@@ -1,4 +1,5 @@
 use self::{fan::Fan, magnet::Magnet, switch::SwitchReceiver};
+use super::{goal::GoalHole, hazard::HazardKind};
 
 pub mod fan;
 pub mod magnet;
@@ -15,4 +16,8 @@ pub enum BlockAttachment {
     },
     Fan(Fan),
     Magnet(Magnet),
+    /// ブロックと一緒に動くゴール. 位置はブロックの回転軸からの相対位置で指定する.
+    Goal(GoalHole),
+    /// 触れたボールを壊す
+    Hazard(HazardKind),
 }
@@ -1,9 +1,10 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::components::block::{RotateStrategy, SlideStrategy};
 
 /// ボールが踏んで何らかの効果を発動させるスイッチ
-#[derive(Component, Clone, Debug)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct SwitchTile {
     /// 押された瞬間のみtrueになるフラグ
     pub just_active: bool,
@@ -19,6 +20,10 @@ pub struct SwitchTile {
     pub extents: Vec2,
 }
 
+/// 一度でも押されたスイッチ
+#[derive(Component)]
+pub struct SwitchPressedOnce;
+
 /// 変更する内容ごとにここに登録する
 #[derive(Clone, Debug)]
 pub enum SwitchType {
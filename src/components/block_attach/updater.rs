@@ -23,6 +23,8 @@ pub struct Updater {
     /// i32の列で範囲指定する
     pub range: Vec<i32>,
     pub updater_type: UpdaterType,
+    /// 時間の進む速さが1でないときに持ち越す1フレーム未満の端数
+    pub carry: f32,
 }
 impl Updater {
     pub fn new(range: Vec<i32>, updater_type: UpdaterType) -> Self {
@@ -33,6 +35,7 @@ impl Updater {
             current_range: reversed_range,
             range,
             updater_type,
+            carry: 0.0,
         }
     }
 }
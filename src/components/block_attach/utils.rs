@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 /// 辺の方向として扱えるもの
 pub trait ToEdgeDirection {
     fn to_edge_direction(&self) -> EdgeDirection;
 }
 /// 四角形ブロックに取り付けるときの辺の方向
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum EdgeDirection {
     Up,
     Down,
@@ -0,0 +1,27 @@
+//! フィールドを映すカメラとミニマップ
+use bevy::{prelude::*, render::view::RenderLayers};
+
+/// 残りボール表示やフェード, 黒帯など, カメラが動いても画面に固定して表示するものを描くレイヤー
+pub const HUD_LAYER: u8 = 1;
+
+/// 画面に固定して表示するもののレイヤー
+pub fn hud_layer() -> RenderLayers {
+    RenderLayers::layer(HUD_LAYER)
+}
+
+/// フィールドを映し, ボールを追いかけるカメラ
+#[derive(Component)]
+pub struct FieldCamera;
+
+/// フィールド全体を画面の隅に小さく映すカメラ
+#[derive(Component)]
+pub struct MinimapCamera;
+
+/// フィールドのカメラの状態
+#[derive(Default)]
+pub struct CameraView {
+    /// trueならフィールド全体が見えるように引いて映す
+    pub overview: bool,
+    /// 追いかけているボール
+    pub target: Option<Entity>,
+}
@@ -0,0 +1,36 @@
+//! ブロックやランチャーを別々に操作するための操作グループ
+use bevy::prelude::*;
+
+/// 手動で動かすブロックやランチャーが属する操作グループ
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ControlGroup(pub u32);
+
+/// グループ操作のステージで, いま操作しているグループ. 連動操作のステージでは存在しない.
+pub struct ActiveControlGroup {
+    /// ステージに存在するグループ（昇順）
+    pub groups: Vec<u32>,
+    pub current: usize,
+}
+impl ActiveControlGroup {
+    pub fn new(mut groups: Vec<u32>) -> Self {
+        groups.sort_unstable();
+        groups.dedup();
+        Self { groups, current: 0 }
+    }
+    pub fn current_group(&self) -> u32 {
+        self.groups.get(self.current).copied().unwrap_or_default()
+    }
+    /// 次のグループに切り替える
+    pub fn cycle(&mut self) {
+        if !self.groups.is_empty() {
+            self.current = (self.current + 1) % self.groups.len();
+        }
+    }
+}
+
+/// グループを操作できるかを返す. 連動操作なら常に操作できる
+pub fn is_controllable(active: &Option<Res<ActiveControlGroup>>, group: &ControlGroup) -> bool {
+    active
+        .as_ref()
+        .is_none_or(|active| active.current_group() == group.0)
+}
@@ -0,0 +1,271 @@
+//! ステージエディタで編集中のステージと選択状態
+use bevy::prelude::*;
+
+use super::{
+    block::{BlockSlidePath, RotateStrategy, SlideStrategy},
+    block_attach::{fan::Fan, magnet::Magnet},
+    hazard::HazardKind,
+};
+use crate::stages::file::{AttachmentFile, BlockFile, BlockShapeFile, StageFile, SwitchedMotion};
+
+/// 編集中のステージ. テストプレイの間も残しておき, エディタに戻ったら続きから編集する.
+pub struct EditorStage {
+    pub stage: StageFile,
+    /// 最後に保存してから変更があったか
+    pub modified: bool,
+}
+
+/// テストプレイ中であることを表す. ゲームを抜けるとメニューではなくエディタに戻る.
+pub struct EditorSession;
+
+/// 選択中のもの
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EditorSelection {
+    #[default]
+    Launcher,
+    Block(usize),
+    Goal(usize),
+    Switch(usize),
+}
+
+/// マウスで掴んで動かしているときの, 掴んだ位置から見たものの位置
+pub struct EditorDrag(pub Vec2);
+
+/// エディタで描いた図形. ステージが変更されるたびに描き直す
+#[derive(Component)]
+pub struct EditorShape;
+
+/// 選択中のものの設定を表示するパネル
+#[derive(Component)]
+pub struct EditorPanel;
+
+/// 操作説明のテキスト. 図形と違って描き直さない
+#[derive(Component)]
+pub struct EditorHelp;
+
+/// ランチャーを掴める範囲の半径
+const LAUNCHER_GRAB_RADIUS: f32 = 40.0;
+/// 回転や移動の速さを一度に変える量
+const SPEED_STEP: f32 = 0.005;
+
+/// ブロックの中に点が入っているか. 点をブロックの回転軸から見た座標に直して判定する
+fn block_contains(block: &BlockFile, point: Vec2) -> bool {
+    let local = Vec2::from_angle(-block.default_angle).rotate(point - block.pos);
+    match block.shape {
+        BlockShapeFile::Rect { extents, origin } => {
+            let d = (local - origin).abs();
+            d.x <= extents.x / 2.0 && d.y <= extents.y / 2.0
+        }
+        BlockShapeFile::Ellipse { radii } => (local / radii).length_squared() <= 1.0,
+    }
+}
+
+impl EditorStage {
+    /// 指定した位置にあるものを探す. 小さいものが隠れないようにランチャー, ゴール, スイッチ, ブロックの順で調べる.
+    /// ブロック同士では後から置いたもの（上に描かれるもの）を優先する.
+    pub fn pick(&self, point: Vec2) -> Option<EditorSelection> {
+        let stage = &self.stage;
+        if stage.launcher.pos.distance(point) <= LAUNCHER_GRAB_RADIUS {
+            return Some(EditorSelection::Launcher);
+        }
+        if let Some(idx) = stage
+            .goals
+            .iter()
+            .position(|goal| goal.pos.distance(point) <= goal.radius)
+        {
+            return Some(EditorSelection::Goal(idx));
+        }
+        if let Some(idx) = stage.switches.iter().position(|switch| {
+            let d = (point - switch.pos).abs();
+            d.x <= switch.extents.x / 2.0 && d.y <= switch.extents.y / 2.0
+        }) {
+            return Some(EditorSelection::Switch(idx));
+        }
+        stage
+            .blocks
+            .iter()
+            .rposition(|block| block_contains(block, point))
+            .map(EditorSelection::Block)
+    }
+
+    /// 選択中のものの位置
+    pub fn position(&self, selection: EditorSelection) -> Option<Vec2> {
+        let stage = &self.stage;
+        match selection {
+            EditorSelection::Launcher => Some(stage.launcher.pos),
+            EditorSelection::Block(idx) => stage.blocks.get(idx).map(|block| block.pos),
+            EditorSelection::Goal(idx) => stage.goals.get(idx).map(|goal| goal.pos),
+            EditorSelection::Switch(idx) => stage.switches.get(idx).map(|switch| switch.pos),
+        }
+    }
+
+    /// 選択中のものを動かす
+    pub fn set_position(&mut self, selection: EditorSelection, pos: Vec2) {
+        let stage = &mut self.stage;
+        match selection {
+            EditorSelection::Launcher => stage.launcher.pos = pos,
+            EditorSelection::Block(idx) => stage.blocks[idx].pos = pos,
+            EditorSelection::Goal(idx) => stage.goals[idx].pos = pos,
+            EditorSelection::Switch(idx) => stage.switches[idx].pos = pos,
+        }
+        self.modified = true;
+    }
+
+    /// 選択中のものを回す. ゴールとスイッチは回らない
+    pub fn rotate(&mut self, selection: EditorSelection, angle: f32) {
+        match selection {
+            EditorSelection::Launcher => {
+                let launcher = &mut self.stage.launcher;
+                launcher.default_angle =
+                    (launcher.default_angle + angle).clamp(launcher.min_angle, launcher.max_angle);
+            }
+            EditorSelection::Block(idx) => self.stage.blocks[idx].default_angle += angle,
+            _ => return,
+        }
+        self.modified = true;
+    }
+
+    /// 選択中のものの大きさを倍率で変える. ランチャーは大きさを変えられない
+    pub fn resize(&mut self, selection: EditorSelection, ratio: f32) {
+        match selection {
+            EditorSelection::Launcher => return,
+            EditorSelection::Block(idx) => match &mut self.stage.blocks[idx].shape {
+                BlockShapeFile::Rect { extents, origin } => {
+                    *extents *= ratio;
+                    *origin *= ratio;
+                }
+                BlockShapeFile::Ellipse { radii } => *radii *= ratio,
+            },
+            EditorSelection::Goal(idx) => self.stage.goals[idx].radius *= ratio,
+            EditorSelection::Switch(idx) => self.stage.switches[idx].extents *= ratio,
+        }
+        self.modified = true;
+    }
+
+    /// 選択中のものを消す. ランチャーは消せない. 消したら選択をランチャーに戻す
+    pub fn remove(&mut self, selection: EditorSelection) -> EditorSelection {
+        let stage = &mut self.stage;
+        match selection {
+            EditorSelection::Launcher => return selection,
+            EditorSelection::Block(idx) => {
+                stage.blocks.remove(idx);
+            }
+            EditorSelection::Goal(idx) => {
+                stage.goals.remove(idx);
+            }
+            EditorSelection::Switch(idx) => {
+                stage.switches.remove(idx);
+            }
+        }
+        self.modified = true;
+        EditorSelection::Launcher
+    }
+
+    /// 上下キーで変える値（ランチャーとブロックは回転の速さ, ゴールは点数, スイッチは対象の番号）を段階的に変える
+    pub fn adjust_value(&mut self, selection: EditorSelection, step: i32) {
+        let stage = &mut self.stage;
+        match selection {
+            EditorSelection::Launcher => {
+                let speed = &mut stage.launcher.rotate_speed;
+                *speed = (*speed + SPEED_STEP * step as f32).max(SPEED_STEP);
+            }
+            EditorSelection::Block(idx) => match &mut stage.blocks[idx].rotate_strategy {
+                RotateStrategy::NoRotate => return,
+                RotateStrategy::Manual(speed) => {
+                    *speed = (*speed + SPEED_STEP * step as f32).max(SPEED_STEP)
+                }
+                // 自動回転は負の速さで逆回りになる
+                RotateStrategy::Auto(speed) => *speed += SPEED_STEP * step as f32,
+            },
+            EditorSelection::Goal(idx) => {
+                let score = &mut stage.goals[idx].score;
+                *score = (*score as i32 + step).max(1) as u32;
+            }
+            EditorSelection::Switch(idx) => {
+                let target_id = &mut stage.switches[idx].target_id;
+                *target_id = (*target_id as i32 + step).max(0) as u32;
+            }
+        }
+        self.modified = true;
+    }
+
+    /// 移動するブロックの速さ, 軌道の幅, 軌道の向きをそれぞれ指定した量だけ変える
+    pub fn adjust_slide(&mut self, selection: EditorSelection, speed: f32, width: f32, theta: f32) {
+        let strategy = match selection {
+            EditorSelection::Block(idx) => &mut self.stage.blocks[idx].slide_strategy,
+            _ => return,
+        };
+        let (current_speed, path) = match strategy {
+            SlideStrategy::NoSlide => return,
+            SlideStrategy::Manual { speed, path }
+            | SlideStrategy::AutoWrap { speed, path }
+            | SlideStrategy::Auto { speed, path } => (speed, path),
+        };
+        *current_speed = (*current_speed + speed).max(SPEED_STEP);
+        if let BlockSlidePath::StandardLine {
+            theta: current_theta,
+            width: current_width,
+        } = path
+        {
+            *current_width = (*current_width + width).max(0.0);
+            *current_theta += theta;
+        }
+        self.modified = true;
+    }
+
+    /// 選択中のブロック
+    pub fn block_mut(&mut self, selection: EditorSelection) -> Option<&mut BlockFile> {
+        match selection {
+            EditorSelection::Block(idx) => {
+                self.modified = true;
+                self.stage.blocks.get_mut(idx)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 回転の方法を 回転しない → 手動 → 自動 の順に切り替える. 速さは引き継ぐ
+pub fn next_rotate_strategy(strategy: &RotateStrategy) -> RotateStrategy {
+    match *strategy {
+        RotateStrategy::NoRotate => RotateStrategy::Manual(0.02),
+        RotateStrategy::Manual(speed) => RotateStrategy::Auto(speed),
+        RotateStrategy::Auto(_) => RotateStrategy::NoRotate,
+    }
+}
+
+/// 移動の方法を 移動しない → 手動 → 手動（自動で折り返し） → 自動 の順に切り替える. 速さと軌道は引き継ぐ
+pub fn next_slide_strategy(strategy: &SlideStrategy) -> SlideStrategy {
+    match strategy.clone() {
+        SlideStrategy::NoSlide => SlideStrategy::Manual {
+            speed: 0.02,
+            path: BlockSlidePath::StandardLine {
+                theta: 0.0,
+                width: 100.0,
+            },
+        },
+        SlideStrategy::Manual { speed, path } => SlideStrategy::AutoWrap { speed, path },
+        SlideStrategy::AutoWrap { speed, path } => SlideStrategy::Auto { speed, path },
+        SlideStrategy::Auto { .. } => SlideStrategy::NoSlide,
+    }
+}
+
+/// 取り付けるものを なし → 送風機 → 磁石 → 危険地帯（トゲ, 穴, 溶岩） → スイッチで回る の順に切り替える
+pub fn next_attachment(attachment: &Option<AttachmentFile>) -> Option<AttachmentFile> {
+    match attachment {
+        None => Some(AttachmentFile::Fan(Fan::new(true, 0, 0.1))),
+        Some(AttachmentFile::Fan(_)) => Some(AttachmentFile::Magnet(Magnet::new(true, 0, 14.0))),
+        Some(AttachmentFile::Magnet(_)) => Some(AttachmentFile::Hazard(HazardKind::Spike)),
+        Some(AttachmentFile::Hazard(HazardKind::Spike)) => {
+            Some(AttachmentFile::Hazard(HazardKind::Pit))
+        }
+        Some(AttachmentFile::Hazard(HazardKind::Pit)) => {
+            Some(AttachmentFile::Hazard(HazardKind::Lava))
+        }
+        Some(AttachmentFile::Hazard(HazardKind::Lava)) => Some(AttachmentFile::SwitchReceiver {
+            target_id: 0,
+            motion: SwitchedMotion::Rotate(RotateStrategy::Auto(0.02)),
+        }),
+        Some(AttachmentFile::SwitchReceiver { .. }) => None,
+    }
+}
@@ -1,5 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+use crate::stages::structs::StageInfo;
+
 /// ゲームのルールを表す. グローバルなリソースとして使う.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum GameRule {
     /// 制限時間に入れたボールによるスコア
     BallScore,
@@ -7,19 +11,57 @@ pub enum GameRule {
     LittleOperation,
     /// 1つ目のボールを入れるまでの時間を競う
     TimeAttack,
+    /// 1つ目のボールを入れるまでに発射した回数（打数）をパーと比べる
+    Strokes,
+}
+impl GameRule {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GameRule::BallScore => "BallScore",
+            GameRule::LittleOperation => "LittleOperation",
+            GameRule::TimeAttack => "TimeAttack",
+            GameRule::Strokes => "Strokes",
+        }
+    }
+    /// ボールを失った数に応じたペナルティをスコアに反映させる
+    pub fn apply_lost_ball_penalty(&self, score: u32, lost_ball: u32) -> u32 {
+        match *self {
+            // 1つ失うごとに1点減点
+            GameRule::BallScore => score.saturating_sub(lost_ball),
+            // 1つ失うごとに操作量60フレーム分を加算
+            GameRule::LittleOperation => score + lost_ball * 60,
+            // 1つ失うごとに5秒加算
+            GameRule::TimeAttack => score + lost_ball * 5 * 60,
+            // 1つ失うごとに1打罰
+            GameRule::Strokes => score + lost_ball,
+        }
+    }
+    /// 巻き戻しを使えるか. タイムアタックでは使えない
+    pub fn allows_rewind(&self) -> bool {
+        !matches!(*self, GameRule::TimeAttack)
+    }
+    /// 巻き戻した回数に応じたペナルティをスコアに反映させる. ボールを失ったときと同じ量とする
+    pub fn apply_rewind_penalty(&self, score: u32, rewinds: u32) -> u32 {
+        self.apply_lost_ball_penalty(score, rewinds)
+    }
 }
+/// ルールの数. 設定ファイルやメニューでのルールの番号はこれより小さい
+pub const GAME_RULE_NUM: u32 = 4;
 impl From<u32> for GameRule {
     fn from(n: u32) -> Self {
         match n {
             0 => Self::BallScore,
             1 => Self::LittleOperation,
-            _ => Self::TimeAttack,
+            2 => Self::TimeAttack,
+            _ => Self::Strokes,
         }
     }
 }
 
 /// ゴールしたボール
 pub struct GoaledBall(pub u32);
+/// 失ったボール
+pub struct LostBall(pub u32);
 /// 最初のボールの総数
 pub struct InitialBallNum(pub u32);
 
@@ -28,13 +70,168 @@ pub struct PassedTime(pub u32);
 /// 矢印キーを操作した量
 pub struct OperationAmount(pub u32);
 pub struct Score(pub u32);
+/// 取得したアイテムの数
+pub struct CollectedItems(pub u32);
+/// 番号付きゴールで次に入れるべき番号
+pub struct NextGoalOrder(pub u32);
+/// ステージ固有のクリア条件
+#[derive(Clone, Debug)]
+pub enum WinCondition {
+    /// 指定した名前のゴールにボールを入れる
+    ReachGoal(&'static str),
+    /// すべてのスイッチを一度は押す
+    HitAllSwitches,
+    /// ボールがブロックに触れずに指定フレーム数飛び続ける
+    KeepAirborne(u32),
+    /// 得点を指定値以上にする
+    ScoreAtLeast(u32),
+}
+impl WinCondition {
+    /// メニューに表示する説明
+    pub fn description(&self) -> String {
+        match *self {
+            WinCondition::ReachGoal(name) => format!("reach goal {}", name),
+            WinCondition::HitAllSwitches => "hit all switches".to_string(),
+            WinCondition::KeepAirborne(frames) => format!("airborne {}s", frames / 60),
+            WinCondition::ScoreAtLeast(score) => format!("score {}", score),
+        }
+    }
+}
+/// 名前付きゴールのうちボールが入ったもの
+pub struct ReachedGoals(pub Vec<&'static str>);
+/// ボールがブロックに触れずに飛び続けた最長フレーム数
+pub struct LongestAirborne(pub u32);
+/// ステージ固有のクリア条件をすべて満たしたか
+pub struct WinConditionMet(pub bool);
+
+/// ボールを発射した回数（打数）
+pub struct StrokeCount(pub u32);
+/// ボールがブロックで跳ね返った回数
+pub struct BounceCount(pub u32);
+/// 発射されたボールの最高速度
+pub struct MaxBallSpeed(pub f32);
+
+/// パーに対する打数をゴルフの用語で表す
+pub fn stroke_term(strokes: u32, par: u32) -> String {
+    if strokes == 1 {
+        return "hole in one".to_string();
+    }
+    match strokes as i32 - par as i32 {
+        i32::MIN..=-3 => "albatross".to_string(),
+        -2 => "eagle".to_string(),
+        -1 => "birdie".to_string(),
+        0 => "par".to_string(),
+        1 => "bogey".to_string(),
+        2 => "double bogey".to_string(),
+        3 => "triple bogey".to_string(),
+        over => format!("+{}", over),
+    }
+}
+
+/// 複数のステージを続けて遊ぶコースの進行状況. コースモードのときのみリソースとして存在する.
+pub struct CourseProgress {
+    pub title: &'static str,
+    /// コースに含まれるステージの生成関数
+    pub stages: Vec<fn() -> StageInfo>,
+    /// 現在遊んでいるステージの番号
+    pub current: usize,
+    /// これまでのステージの合計打数
+    pub total_strokes: u32,
+    /// これまでのステージの合計パー
+    pub total_par: u32,
+}
+impl CourseProgress {
+    pub fn new(title: &'static str, stages: Vec<fn() -> StageInfo>) -> Self {
+        Self {
+            title,
+            stages,
+            current: 0,
+            total_strokes: 0,
+            total_par: 0,
+        }
+    }
+    pub fn current_stage(&self) -> StageInfo {
+        self.stages[self.current]()
+    }
+    pub fn has_next(&self) -> bool {
+        self.current + 1 < self.stages.len()
+    }
+}
 /// ResultScoreの拡張, 様々な情報を保存しておく
 pub struct ResultInfoStorage {
     pub score: u32,
+    /// 獲得した星の数（0〜3）
+    pub stars: u32,
+    /// 経過時間（フレーム）
+    pub passed_time: u32,
+    /// 矢印キーを操作した量
+    pub operations: u32,
+    /// 発射したボールの数
+    pub balls_used: u32,
+    /// ブロックで跳ね返った回数
+    pub bounces: u32,
+    /// ボールの最高速度
+    pub max_speed: f32,
+    /// ゴールしたボールの数
+    pub goals: u32,
+    /// 取得したアイテムの数（アイテムのないステージではNone）
+    pub collected_items: Option<u32>,
+    /// 通過されたチェックポイントの数（チェックポイントのないステージではNone）
+    pub passed_checkpoints: Option<u32>,
+    /// 失ったボールの数
+    pub lost_balls: u32,
+    /// 巻き戻した回数
+    pub rewinds: u32,
+    /// スローモーションを使った時間（フレーム）
+    pub slow_motion: u32,
+    /// 打数ルールのときのパー
+    pub par: Option<u32>,
+    /// 結果の見出し（打数ルールでのバーディー等）
+    pub headline: Option<String>,
+    /// コースモードでの合計打数と合計パー
+    pub course_total: Option<(u32, u32)>,
 }
 impl ResultInfoStorage {
     pub fn to_vector(&self) -> std::vec::Vec<(&str, u32)> {
-        vec![("score", self.score)]
+        let mut contents = vec![
+            ("score", self.score),
+            ("time", self.passed_time / 60),
+            ("operations", self.operations),
+            ("balls used", self.balls_used),
+            ("bounces", self.bounces),
+            // 1秒あたりのピクセル数で表示する
+            ("max speed", (self.max_speed * 60.0).round() as u32),
+            ("goals", self.goals),
+        ];
+        if let Some(collected_items) = self.collected_items {
+            contents.push(("items", collected_items));
+        }
+        if let Some(passed_checkpoints) = self.passed_checkpoints {
+            contents.push(("checkpoints", passed_checkpoints));
+        }
+        if self.lost_balls > 0 {
+            contents.push(("lost balls", self.lost_balls));
+        }
+        if self.rewinds > 0 {
+            contents.push(("rewinds", self.rewinds));
+        }
+        if self.slow_motion > 0 {
+            contents.push(("slow motion", self.slow_motion / 60));
+        }
+        if let Some(par) = self.par {
+            contents.push(("par", par));
+        }
+        if let Some((total_strokes, total_par)) = self.course_total {
+            contents.push(("course strokes", total_strokes));
+            contents.push(("course par", total_par));
+        }
+        contents
+    }
+    /// 星の数を★と☆で表した文字列
+    pub fn stars_text(&self) -> String {
+        (0..3)
+            .map(|i| if i < self.stars { '★' } else { '☆' })
+            .collect()
     }
 }
 
@@ -1,20 +1,63 @@
 use bevy::prelude::{Component, Vec2};
 
-use crate::events::goal::SpawnGoalEvent;
+use super::ball::BallType;
 
-#[derive(Component)]
+#[derive(Component, Clone, Debug)]
 pub struct GoalHole {
+    /// 現在の位置（動くゴールでは毎フレーム更新される）
     pub pos: Vec2,
     pub radius: f32,
     pub score: u32,
+    /// 受け入れるボールの種類（Noneならすべて受け入れる）
+    pub accept: Option<Vec<BallType>>,
+    /// 受け入れるボールの数の上限. 達したらゴールは閉じる.
+    pub capacity: Option<u32>,
+    /// 入るときの速さの上限. これより速いボールは通り過ぎる.
+    pub max_entry_speed: Option<f32>,
+    /// 番号付きゴールの番号. 1から順番に入れる必要がある.
+    pub order: Option<u32>,
+    /// これまでに入ったボールの数
+    pub count: u32,
+    /// ステージのクリア条件で指定するための名前
+    pub name: Option<&'static str>,
 }
 impl GoalHole {
     pub fn new(pos: Vec2, radius: f32, score: u32) -> Self {
-        Self { pos, radius, score }
+        Self {
+            pos,
+            radius,
+            score,
+            accept: None,
+            capacity: None,
+            max_entry_speed: None,
+            order: None,
+            count: 0,
+            name: None,
+        }
     }
-}
-impl From<&SpawnGoalEvent> for GoalHole {
-    fn from(ev: &SpawnGoalEvent) -> Self {
-        GoalHole::new(ev.pos, ev.radius, ev.score)
+    /// 上限までボールが入って閉じているか
+    pub fn is_closed(&self) -> bool {
+        self.capacity.is_some_and(|capacity| self.count >= capacity)
+    }
+    /// 番号付きゴールのうちまだ入れられていないものか
+    pub fn is_pending_in_order(&self, next_order: u32) -> bool {
+        self.order.is_some_and(|order| order >= next_order)
+    }
+    /// 指定の種類と速さのボールを受け入れるかどうか
+    pub fn accepts(&self, ball_type: BallType, speed: f32, next_order: u32) -> bool {
+        if self.is_closed() {
+            return false;
+        }
+        if let Some(accept) = &self.accept {
+            if !accept.contains(&ball_type) {
+                return false;
+            }
+        }
+        if let Some(max_entry_speed) = self.max_entry_speed {
+            if speed > max_entry_speed {
+                return false;
+            }
+        }
+        self.order.is_none_or(|order| order == next_order)
     }
 }
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// ボールを壊す危険物の種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HazardKind {
+    Spike,
+    Pit,
+    Lava,
+}
+impl HazardKind {
+    pub fn color(&self) -> Color {
+        match *self {
+            HazardKind::Spike => Color::SILVER,
+            HazardKind::Pit => Color::rgb(0.1, 0.1, 0.1),
+            HazardKind::Lava => Color::ORANGE_RED,
+        }
+    }
+}
+
+/// ボールの中心が入ると壊れる矩形領域
+#[derive(Component, Clone, Debug)]
+pub struct HazardZone {
+    pub kind: HazardKind,
+    pub extents: Vec2,
+}
+
+/// ブロックに付けると触れたボールが壊れる
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HazardBlock(pub HazardKind);
+
+/// ボールを失った原因
+#[derive(Clone, Copy, Debug)]
+pub enum BallLostCause {
+    Hazard(HazardKind),
+    /// フィールドの外に出た
+    OutOfField,
+    /// 静止して動かなくなったので取り除いた
+    Retired,
+}
+impl BallLostCause {
+    /// 壊れるエフェクトの色
+    pub fn color(&self) -> Color {
+        match *self {
+            BallLostCause::Hazard(kind) => kind.color(),
+            BallLostCause::OutOfField => Color::WHITE,
+            BallLostCause::Retired => Color::GRAY,
+        }
+    }
+    /// 失ったボールとして減点の対象となるか
+    pub fn is_penalized(&self) -> bool {
+        !matches!(*self, BallLostCause::Retired)
+    }
+}
+
+/// ボールが壊れたときに飛び散る破片
+#[derive(Component)]
+pub struct BallFragment;
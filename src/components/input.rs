@@ -0,0 +1,192 @@
+//! キーボードやゲームパッドの入力を抽象化した操作
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// ゲーム内の操作. 各システムはキーではなくこれを読む.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    /// ブロックやランチャーを左回りに回す. メニューでは前の選択肢に移る
+    RotateLeft,
+    /// ブロックやランチャーを右回りに回す. メニューでは次の選択肢に移る
+    RotateRight,
+    /// 設定値を増やす
+    Up,
+    /// 設定値を減らす
+    Down,
+    /// ボールを番える, 発射する. メニューでは決定
+    Action,
+    /// メニューでのキャンセル
+    Cancel,
+    Retry,
+    /// メニューに戻る
+    Back,
+    Pause,
+    /// 押している間は回転や移動を遅くして細かく狙えるようにする
+    Precision,
+    /// 操作するグループを切り替える
+    CycleGroup,
+    ToggleFullscreen,
+    /// フィールド全体が見えるように引いて映すかを切り替える
+    Overview,
+    /// 押している間ゲームを巻き戻す
+    Rewind,
+    /// スローモーションを切り替える
+    SlowMotion,
+}
+
+/// 回転や移動の速さの変化のしかた. ステージで指定されていなければ割り当てと同じファイルの値を使う.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlCurve {
+    /// 押し始めの速さの倍率
+    pub start_ratio: f32,
+    /// 押し続けてこのフレーム数で本来の速さになる
+    pub ramp_frames: u32,
+    /// 精密操作中の速さの倍率
+    pub precision_ratio: f32,
+}
+impl ControlCurve {
+    /// 押し続けたフレーム数に対する速さの倍率
+    pub fn ratio(&self, hold_frames: u32) -> f32 {
+        if self.ramp_frames == 0 {
+            return 1.0;
+        }
+        let progress = (hold_frames as f32 / self.ramp_frames as f32).min(1.0);
+        self.start_ratio + (1.0 - self.start_ratio) * progress
+    }
+}
+impl Default for ControlCurve {
+    fn default() -> Self {
+        Self {
+            start_ratio: 0.3,
+            ramp_frames: 30,
+            precision_ratio: 0.25,
+        }
+    }
+}
+
+/// 操作ごとのキーとゲームパッドのボタンの割り当て. グローバルなリソースとして使う.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputBindings {
+    pub keys: HashMap<InputAction, Vec<KeyCode>>,
+    pub buttons: HashMap<InputAction, Vec<GamepadButtonType>>,
+    /// スティックの傾きがこれ以下なら入力なしとみなす
+    pub stick_dead_zone: f32,
+    pub curve: ControlCurve,
+}
+impl Default for InputBindings {
+    fn default() -> Self {
+        use GamepadButtonType::*;
+        use InputAction::*;
+        let keys = HashMap::from([
+            (RotateLeft, vec![KeyCode::Left]),
+            (RotateRight, vec![KeyCode::Right]),
+            (Up, vec![KeyCode::Up]),
+            (Down, vec![KeyCode::Down]),
+            (Action, vec![KeyCode::Z]),
+            (Cancel, vec![KeyCode::X]),
+            (Retry, vec![KeyCode::R]),
+            (Back, vec![KeyCode::B]),
+            (Pause, vec![KeyCode::P]),
+            (Precision, vec![KeyCode::LShift, KeyCode::RShift]),
+            (CycleGroup, vec![KeyCode::C]),
+            (ToggleFullscreen, vec![KeyCode::F11]),
+            (Overview, vec![KeyCode::V]),
+            (Rewind, vec![KeyCode::Back]),
+            (SlowMotion, vec![KeyCode::S]),
+        ]);
+        let buttons = HashMap::from([
+            (RotateLeft, vec![DPadLeft]),
+            (RotateRight, vec![DPadRight]),
+            (Up, vec![DPadUp]),
+            (Down, vec![DPadDown]),
+            (Action, vec![South]),
+            (Cancel, vec![East]),
+            (Retry, vec![West]),
+            (Back, vec![Select]),
+            (Pause, vec![Start]),
+            (Precision, vec![LeftTrigger, RightTrigger]),
+            (CycleGroup, vec![North]),
+            (Overview, vec![RightThumb]),
+            (Rewind, vec![LeftTrigger2]),
+            (SlowMotion, vec![RightTrigger2]),
+        ]);
+        Self {
+            keys,
+            buttons,
+            stick_dead_zone: 0.5,
+            curve: ControlCurve::default(),
+        }
+    }
+}
+
+/// このフレームでの操作の入力状態. `Input<KeyCode>`と同じように使う.
+#[derive(Default)]
+pub struct ActionInput {
+    pressed: HashSet<InputAction>,
+    just_pressed: HashSet<InputAction>,
+    just_released: HashSet<InputAction>,
+    /// 回転の量. 左回りを正とし, 本来の速さを1とする
+    rotate_amount: f32,
+    /// 同じ向きにデジタル入力で回し続けているフレーム数と向き
+    hold: (u32, f32),
+}
+impl ActionInput {
+    /// 現在押されている操作の集合から状態を更新する
+    pub fn update(&mut self, pressed: HashSet<InputAction>) {
+        self.just_pressed = pressed.difference(&self.pressed).copied().collect();
+        self.just_released = self.pressed.difference(&pressed).copied().collect();
+        self.pressed = pressed;
+    }
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.pressed.contains(&action)
+    }
+    pub fn any_pressed(&self, actions: impl IntoIterator<Item = InputAction>) -> bool {
+        actions.into_iter().any(|action| self.pressed(action))
+    }
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.just_pressed.contains(&action)
+    }
+    pub fn any_just_pressed(&self, actions: impl IntoIterator<Item = InputAction>) -> bool {
+        actions.into_iter().any(|action| self.just_pressed(action))
+    }
+    pub fn just_released(&self, action: InputAction) -> bool {
+        self.just_released.contains(&action)
+    }
+    /// デジタル入力（-1, 0, 1）とスティックの傾きから回転の量を更新する.
+    /// デジタル入力は押し続けると加速し, スティックは傾きに比例する. 大きいほうを採用する.
+    pub fn update_rotation(&mut self, digital: f32, analog: f32, curve: &ControlCurve) {
+        self.hold = if digital == 0.0 {
+            (0, 0.0)
+        } else if digital == self.hold.1 {
+            (self.hold.0 + 1, digital)
+        } else {
+            (0, digital)
+        };
+        let digital = digital * curve.ratio(self.hold.0);
+        let amount = if analog.abs() > digital.abs() {
+            analog
+        } else {
+            digital
+        };
+        self.rotate_amount = if self.pressed(InputAction::Precision) {
+            amount * curve.precision_ratio
+        } else {
+            amount
+        };
+    }
+    pub fn rotate_amount(&self) -> f32 {
+        self.rotate_amount
+    }
+    /// 押し続けたフレーム数を数え直し, 次の回転を押し始めの速さからにする
+    pub fn reset_hold(&mut self) {
+        self.hold = (0, 0.0);
+    }
+    /// このフレームでは押された瞬間ではなかったことにする
+    pub fn clear_just_pressed(&mut self, action: InputAction) {
+        self.just_pressed.remove(&action);
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+use super::ball::BallType;
+
+/// アイテムの種類と効果量
+#[derive(Clone, Copy, Debug)]
+pub enum ItemType {
+    /// 得点が加算される
+    Coin(u32),
+    /// 残り時間が延びる（フレーム数）
+    BonusTime(u32),
+    /// 残りボールが増える
+    ExtraBall(BallType),
+}
+impl ItemType {
+    pub fn radius(&self) -> f32 {
+        match *self {
+            ItemType::Coin(_) => 12.0,
+            ItemType::BonusTime(_) => 15.0,
+            ItemType::ExtraBall(ball_type) => ball_type.radius() * 0.6,
+        }
+    }
+    pub fn color(&self) -> Color {
+        match *self {
+            ItemType::Coin(_) => Color::GOLD,
+            ItemType::BonusTime(_) => Color::AQUAMARINE,
+            ItemType::ExtraBall(ball_type) => ball_type.color(),
+        }
+    }
+}
+
+/// ボールが触れると取得されるアイテム
+#[derive(Component, Clone, Debug)]
+pub struct CollectibleItem {
+    pub item_type: ItemType,
+}
+
+/// 取得済みのアイテム. 巻き戻しで取得前に戻せるように, 消さずに隠しておく
+#[derive(Component)]
+pub struct CollectedItem;
+
+/// ボールが通過するチェックポイント
+#[derive(Component, Clone, Debug)]
+pub struct Checkpoint {
+    pub id: u32,
+    pub radius: f32,
+    /// いずれかのボールが一度でも通過したか
+    pub passed: bool,
+}
+
+/// ボールが通過したチェックポイントの記録
+#[derive(Component, Clone, Default, Debug)]
+pub struct CheckpointRecord(pub Vec<u32>);
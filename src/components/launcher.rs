@@ -1,7 +1,8 @@
 use super::ball::BallType;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Copy, Debug)]
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum LauncherState {
     Waiting,
     Nocking,
@@ -10,6 +11,36 @@ pub enum LauncherState {
 #[derive(Component, Debug)]
 pub struct BallMagazine {
     pub balls: Vec<(BallType, Entity)>,
+    /// 次に番えるボールの番号
+    pub selected: usize,
+}
+impl BallMagazine {
+    pub fn new(balls: Vec<(BallType, Entity)>) -> Self {
+        Self { balls, selected: 0 }
+    }
+    /// 次に番えるボールのタイプ
+    pub fn selected_ball(&self) -> Option<BallType> {
+        self.balls
+            .get(self.selected)
+            .map(|(ball_type, _)| *ball_type)
+    }
+    /// 選択を次のボールに進める（末尾なら先頭に戻る）
+    pub fn cycle_selection(&mut self) {
+        if !self.balls.is_empty() {
+            self.selected = (self.selected + 1) % self.balls.len();
+        }
+    }
+    /// 選択中のボールを取り出す
+    pub fn take_selected(&mut self) -> Option<(BallType, Entity)> {
+        if self.selected >= self.balls.len() {
+            return None;
+        }
+        let ball = self.balls.remove(self.selected);
+        if self.selected >= self.balls.len() {
+            self.selected = 0;
+        }
+        Some(ball)
+    }
 }
 
 #[derive(Component, Debug)]
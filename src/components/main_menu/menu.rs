@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use bevy::prelude::*;
 
-use super::super::game::GameRule;
+use super::super::{game::GameRule, settings::SettingKey};
 
 /// メニュー全体を管理するためのリソース
 #[derive(Default)]
@@ -35,9 +35,12 @@ pub struct MenuOptionSets {
     pub option_set: Vec<MenuOptionSet>,
 }
 
+#[derive(Default)]
 pub struct MenuOptionSet {
     pub options: Vec<MenuOption>,
     pub layer_id: u32,
+    /// 最初に選択されている選択肢の番号
+    pub initial_choice: u32,
 }
 
 // TODO: enumをこれに変換するマクロとかあったらよさそう
@@ -45,12 +48,43 @@ pub struct MenuOption {
     /// 一つの選択肢セットの中で被っていなければいい
     pub name: &'static str,
     pub disabled: bool,
+    /// 選択肢の横に小さく表示する補足（選べない理由など）
+    pub note: Option<String>,
+    /// 設定項目であれば, 値を変更できる項目の種類
+    pub setting: Option<SettingKey>,
 }
 impl MenuOption {
     pub fn new(name: &'static str) -> Self {
         Self {
             name,
             disabled: false,
+            note: None,
+            setting: None,
+        }
+    }
+    /// 上下キーで値を変更できる設定項目
+    pub fn setting(key: SettingKey) -> Self {
+        Self {
+            setting: Some(key),
+            ..Self::new(key.label())
+        }
+    }
+    /// 補足付きの選択肢
+    pub fn with_note(name: &'static str, note: String) -> Self {
+        Self {
+            name,
+            disabled: false,
+            note: Some(note),
+            setting: None,
+        }
+    }
+    /// 選べない選択肢. 解放条件を補足として表示する
+    pub fn locked(name: &'static str, requirement: String) -> Self {
+        Self {
+            name,
+            disabled: true,
+            note: Some(requirement),
+            setting: None,
         }
     }
 }
@@ -64,5 +98,8 @@ pub struct OptionText;
 /// 選択中オプション
 #[derive(Component)]
 pub struct CurrentOption;
+/// 選べないオプション
+#[derive(Component)]
+pub struct DisabledOption;
 #[derive(Component)]
 pub struct GameRuleOption(pub GameRule);
@@ -1,13 +1,28 @@
 //! components：Componentをつけた構造体や列挙体と, Eventを定義する
+pub mod agent;
 pub mod ball;
 pub mod block;
 pub mod block_attach;
+pub mod camera;
+pub mod control;
+pub mod editor;
 pub mod effects;
 pub mod field;
 pub mod game;
 pub mod goal;
+pub mod hazard;
 pub mod info;
+pub mod input;
+pub mod item;
 pub mod launcher;
 pub mod main_menu;
+pub mod pause;
 pub mod physics;
+pub mod progress;
+pub mod replay;
+pub mod rewind;
+pub mod screen;
+pub mod settings;
+pub mod suspend;
+pub mod time_scale;
 pub mod timer;
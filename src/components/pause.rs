@@ -0,0 +1,5 @@
+use bevy::prelude::*;
+
+/// 一時停止中にゲーム画面を暗くする背景
+#[derive(Component)]
+pub struct PauseOverlay;
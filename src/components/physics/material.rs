@@ -2,8 +2,9 @@ use std::f32::consts::PI;
 
 use bevy::prelude::Component;
 use bevy_prototype_lyon::shapes::{Circle, Rectangle};
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct PhysicMaterial {
     pub restitution: f32, // 反発係数
     pub density: f32,     // 密度
@@ -0,0 +1,35 @@
+//! キャンペーンの進行状況
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// ステージごとの最高記録
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct StageRecord {
+    pub cleared: bool,
+    pub best_stars: u32,
+}
+
+/// キャンペーンの進行状況. ステージ名をキーにして記録を持ち, ファイルに保存される.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CampaignProgress {
+    pub records: HashMap<String, StageRecord>,
+}
+impl CampaignProgress {
+    pub fn is_cleared(&self, stage_title: &str) -> bool {
+        self.records
+            .get(stage_title)
+            .is_some_and(|record| record.cleared)
+    }
+    pub fn total_stars(&self) -> u32 {
+        self.records.values().map(|record| record.best_stars).sum()
+    }
+    /// 結果を記録する. 記録が更新されたらtrueを返す
+    pub fn update(&mut self, stage_title: &str, stars: u32) -> bool {
+        let record = self.records.entry(stage_title.to_string()).or_default();
+        let prev = *record;
+        record.cleared |= stars > 0;
+        record.best_stars = record.best_stars.max(stars);
+        prev.cleared != record.cleared || prev.best_stars != record.best_stars
+    }
+}
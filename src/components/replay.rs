@@ -0,0 +1,122 @@
+//! 操作の記録（リプレイ）. ソルバーが見つけた解を保存し, 同じ操作を再生して確かめる.
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::input::InputAction;
+
+/// 同じ操作を続けるフレーム数と, その間押している操作
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputSegment {
+    pub frames: u32,
+    pub actions: Vec<InputAction>,
+}
+
+/// 記録したときの結果. 再生したときに同じになるか比べる
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedOutcome {
+    /// クリアしたときの経過時間（フレーム）
+    pub frames: u32,
+    pub operations: u32,
+    pub strokes: u32,
+}
+
+/// ゲーム開始からの操作の記録
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputScript {
+    /// 遊んだステージ（ステージ名またはステージファイルのパス）
+    pub stage: String,
+    pub segments: Vec<InputSegment>,
+    /// 記録したときの結果. 手で書いた記録などではNone
+    pub expected: Option<RecordedOutcome>,
+}
+impl InputScript {
+    /// 記録の長さ（フレーム数）
+    pub fn frames(&self) -> u32 {
+        self.segments.iter().map(|segment| segment.frames).sum()
+    }
+    /// 操作を追加する. 直前と同じ操作なら一つにまとめる
+    pub fn push(&mut self, frames: u32, actions: Vec<InputAction>) {
+        if frames == 0 {
+            return;
+        }
+        match self.segments.last_mut() {
+            Some(last) if last.actions == actions => last.frames += frames,
+            _ => self.segments.push(InputSegment { frames, actions }),
+        }
+    }
+    /// 指定したフレームで押している操作. 記録が終わっていればNone
+    pub fn actions_at(&self, frame: u32) -> Option<&[InputAction]> {
+        let mut start = 0;
+        for segment in self.segments.iter() {
+            if frame < start + segment.frames {
+                return Some(&segment.actions);
+            }
+            start += segment.frames;
+        }
+        None
+    }
+}
+
+/// 記録した操作を再生する. このリソースがある間は入力機器の代わりに記録から操作を読む.
+pub struct ScriptPlayback {
+    pub script: InputScript,
+    /// 次に再生するフレーム
+    pub frame: u32,
+}
+impl ScriptPlayback {
+    pub fn new(script: InputScript) -> Self {
+        Self { script, frame: 0 }
+    }
+    /// 次のフレームの操作を取り出す. 記録が終わった後は何も押していないことにする
+    pub fn next_actions(&mut self) -> HashSet<InputAction> {
+        let actions = self
+            .script
+            .actions_at(self.frame)
+            .map(|actions| actions.iter().copied().collect())
+            .unwrap_or_default();
+        self.frame += 1;
+        actions
+    }
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.script.frames()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_merges_same_actions() {
+        let mut script = InputScript::default();
+        script.push(3, vec![InputAction::RotateLeft]);
+        script.push(2, vec![InputAction::RotateLeft]);
+        script.push(0, vec![InputAction::Action]);
+        script.push(1, vec![InputAction::Action]);
+        script.push(4, vec![]);
+        assert_eq!(script.segments.len(), 3);
+        assert_eq!(script.segments[0].frames, 5);
+        assert_eq!(script.frames(), 10);
+    }
+
+    #[test]
+    fn actions_at_follows_segments() {
+        let mut script = InputScript::default();
+        script.push(2, vec![InputAction::RotateRight]);
+        script.push(1, vec![InputAction::Action]);
+        assert_eq!(script.actions_at(0), Some(&[InputAction::RotateRight][..]));
+        assert_eq!(script.actions_at(1), Some(&[InputAction::RotateRight][..]));
+        assert_eq!(script.actions_at(2), Some(&[InputAction::Action][..]));
+        assert_eq!(script.actions_at(3), None);
+
+        let mut playback = ScriptPlayback::new(script);
+        for _ in 0..3 {
+            assert!(!playback.is_finished());
+            playback.next_actions();
+        }
+        assert!(playback.is_finished());
+        assert!(playback.next_actions().is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+//! ゲーム中の巻き戻し. 毎フレームの状態を一定時間分だけ記録しておく.
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::{
+    ball::BallType,
+    block::{BlockTransform, RotateStrategy, SlideStrategy},
+    block_attach::{switch::SwitchTile, updater::UpdaterVec},
+    launcher::LauncherState,
+};
+
+/// 巻き戻せるフレーム数（5秒）
+pub const REWIND_FRAMES: usize = 5 * 60;
+/// 巻き戻し中に1フレームで戻るフレーム数
+pub const REWIND_STEP: usize = 2;
+
+/// ボール一つの状態. ゴールに吸い込まれている途中のボールは記録しない
+pub struct BallSnapshot {
+    /// 保存データから作った場合はまだエンティティがない
+    pub entity: Option<Entity>,
+    pub ball_type: BallType,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub nocking: bool,
+    pub airborne_frames: u32,
+    pub checkpoints: Vec<u32>,
+}
+
+/// ブロック一つの状態. 軌道に沿って動くゴールも含む
+pub struct BlockSnapshot {
+    pub entity: Entity,
+    pub transform: Transform,
+    pub block_transform: BlockTransform,
+    /// スイッチで切り替わることがあるので記録する
+    pub rotate_strategy: Option<RotateStrategy>,
+    pub slide_strategy: Option<SlideStrategy>,
+    pub updaters: Option<UpdaterVec>,
+}
+
+/// スイッチ一つの状態
+pub struct SwitchSnapshot {
+    pub entity: Entity,
+    pub switch: SwitchTile,
+    pub pressed_once: bool,
+    /// 自動で戻るまでの残りフレーム数
+    pub timer: Option<u32>,
+}
+
+pub struct LauncherSnapshot {
+    pub entity: Entity,
+    pub angle: f32,
+    pub state: LauncherState,
+}
+
+/// 1フレーム分のゲームの状態. 経過時間や操作量は巻き戻さない
+pub struct Snapshot {
+    pub balls: Vec<BallSnapshot>,
+    pub blocks: Vec<BlockSnapshot>,
+    pub switches: Vec<SwitchSnapshot>,
+    pub launchers: Vec<LauncherSnapshot>,
+    /// ゴールごとの入ったボールの数
+    pub goal_counts: Vec<(Entity, u32)>,
+    /// 残りのボールと選択中のボールの番号
+    pub magazine: (Vec<BallType>, usize),
+    pub score: u32,
+    pub goaled_balls: u32,
+    pub lost_balls: u32,
+    pub strokes: u32,
+    pub next_goal_order: u32,
+    pub reached_goals: Vec<&'static str>,
+    /// アイテムごとの取得済みか
+    pub items: Vec<(Entity, bool)>,
+    /// チェックポイントごとの通過済みか
+    pub checkpoints: Vec<(Entity, bool)>,
+    pub collected_items: u32,
+}
+
+/// 記録した状態. 古いものから順に並び, 上限を超えたら古いものから捨てる
+#[derive(Default)]
+pub struct RewindBuffer(VecDeque<Snapshot>);
+impl RewindBuffer {
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.0.len() >= REWIND_FRAMES {
+            self.0.pop_front();
+        }
+        self.0.push_back(snapshot);
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// 最後に記録した状態
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.0.back()
+    }
+    /// 指定したフレーム数だけ戻り, その時点の状態を取り出す. 記録が足りなければ最も古い状態を返す
+    pub fn rewind(&mut self, frames: usize) -> Option<Snapshot> {
+        let mut snapshot = None;
+        for _ in 0..frames {
+            match self.0.pop_back() {
+                Some(popped) => snapshot = Some(popped),
+                None => break,
+            }
+        }
+        snapshot
+    }
+    /// 消えたボールを出し直したとき, それより前の記録のエンティティも置き換える
+    pub fn replace_entity(&mut self, old: Entity, new: Entity) {
+        for snapshot in self.0.iter_mut() {
+            for ball in snapshot.balls.iter_mut() {
+                if ball.entity == Some(old) {
+                    ball.entity = Some(new);
+                }
+            }
+        }
+    }
+}
+
+/// 巻き戻した回数. ルールに応じたペナルティになる
+pub struct RewindCount(pub u32);
+
+/// 巻き戻しで状態を戻したスイッチ. 再開した最初のフレームではスイッチの効果を発動しない
+#[derive(Component)]
+pub struct RewoundSwitch;
+
+/// 巻き戻し中であることを示す表示
+#[derive(Component)]
+pub struct RewindOverlay;
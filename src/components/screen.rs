@@ -0,0 +1,45 @@
+//! ウィンドウの大きさに関わらず論理的な画面（SCREEN_WIDTH×SCREEN_HEIGHT）に合わせて表示するためのもの
+use bevy::prelude::*;
+
+/// 論理的な画面がウィンドウ内でどこにどの大きさで表示されているか. グローバルなリソースとして使う.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenLayout {
+    /// 論理的な画面の1単位がウィンドウの何ピクセル（論理ピクセル）になるか
+    pub scale: f32,
+    /// ウィンドウの左下から論理的な画面の左下までの距離（黒帯の幅）
+    pub offset: Vec2,
+}
+impl Default for ScreenLayout {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+impl ScreenLayout {
+    /// 論理的な画面の端からの距離を, ウィンドウの端からの距離に変換する
+    pub fn window_position(&self, position: UiRect<Val>) -> UiRect<Val> {
+        let convert = |val: Val, offset: f32| match val {
+            Val::Px(px) => Val::Px(px * self.scale + offset),
+            other => other,
+        };
+        UiRect {
+            left: convert(position.left, self.offset.x),
+            right: convert(position.right, self.offset.x),
+            top: convert(position.top, self.offset.y),
+            bottom: convert(position.bottom, self.offset.y),
+        }
+    }
+}
+
+/// UIを置いたときの論理的な画面での位置と文字の大きさ. ウィンドウの大きさが変わったらここから計算し直す.
+#[derive(Component)]
+pub struct ScreenPosition {
+    pub position: UiRect<Val>,
+    pub font_sizes: Vec<f32>,
+}
+
+/// 論理的な画面の外側を隠す黒帯
+#[derive(Component)]
+pub struct LetterboxBar;
@@ -0,0 +1,104 @@
+//! 設定画面で変更できる設定
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::game::GAME_RULE_NUM;
+
+/// ファイルに保存される設定. グローバルなリソースとして使う.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    /// ウィンドウの拡大率（%）
+    pub window_scale: u32,
+    pub fullscreen: bool,
+    /// 音量（%）. 音を鳴らすときはこの割合を掛ける
+    pub volume: u32,
+    /// 止まったボールを自動で取り除くか（ステージで無効にされている場合はそちらを優先する）
+    pub auto_retire: bool,
+    /// ゲーム中に操作説明を表示するか
+    pub show_guide: bool,
+    /// 最後に選んだルールの番号
+    pub rule: u32,
+}
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            window_scale: 100,
+            fullscreen: false,
+            volume: 100,
+            auto_retire: true,
+            show_guide: true,
+            rule: 0,
+        }
+    }
+}
+impl GameSettings {
+    /// ファイルから読んだ値を設定画面で選べる範囲に収める
+    pub fn sanitized(self) -> Self {
+        Self {
+            window_scale: self.window_scale.clamp(WINDOW_SCALE_MIN, WINDOW_SCALE_MAX),
+            volume: self.volume.min(VOLUME_MAX),
+            rule: self.rule.min(GAME_RULE_NUM - 1),
+            ..self
+        }
+    }
+}
+
+const WINDOW_SCALE_MIN: u32 = 50;
+const WINDOW_SCALE_MAX: u32 = 200;
+const WINDOW_SCALE_STEP: u32 = 10;
+const VOLUME_MAX: u32 = 100;
+const VOLUME_STEP: u32 = 10;
+
+/// 設定画面の項目
+#[derive(Component, Clone, Copy, Debug)]
+pub enum SettingKey {
+    WindowScale,
+    Fullscreen,
+    Volume,
+    AutoRetire,
+    ShowGuide,
+}
+impl SettingKey {
+    pub fn label(&self) -> &'static str {
+        match *self {
+            SettingKey::WindowScale => "Scale",
+            SettingKey::Fullscreen => "Fullscreen",
+            SettingKey::Volume => "Volume",
+            SettingKey::AutoRetire => "AutoRetire",
+            SettingKey::ShowGuide => "Guide",
+        }
+    }
+    pub fn value_text(&self, settings: &GameSettings) -> String {
+        let on_off = |flag: bool| if flag { "on" } else { "off" }.to_string();
+        match *self {
+            SettingKey::WindowScale => format!("{}%", settings.window_scale),
+            SettingKey::Fullscreen => on_off(settings.fullscreen),
+            SettingKey::Volume => format!("{}%", settings.volume),
+            SettingKey::AutoRetire => on_off(settings.auto_retire),
+            SettingKey::ShowGuide => on_off(settings.show_guide),
+        }
+    }
+    /// 値を1段階変える. スライダーは範囲内で増減し, トグルは切り替える.
+    pub fn adjust(&self, settings: &mut GameSettings, increase: bool) {
+        match *self {
+            SettingKey::WindowScale => {
+                settings.window_scale = if increase {
+                    (settings.window_scale + WINDOW_SCALE_STEP).min(WINDOW_SCALE_MAX)
+                } else {
+                    (settings.window_scale.saturating_sub(WINDOW_SCALE_STEP)).max(WINDOW_SCALE_MIN)
+                };
+            }
+            SettingKey::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            SettingKey::Volume => {
+                settings.volume = if increase {
+                    (settings.volume + VOLUME_STEP).min(VOLUME_MAX)
+                } else {
+                    settings.volume.saturating_sub(VOLUME_STEP)
+                };
+            }
+            SettingKey::AutoRetire => settings.auto_retire = !settings.auto_retire,
+            SettingKey::ShowGuide => settings.show_guide = !settings.show_guide,
+        }
+    }
+}
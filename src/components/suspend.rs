@@ -0,0 +1,220 @@
+//! 中断したゲームの保存データ. 巻き戻しの記録をファイルに書ける形にしたもの.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ball::BallType,
+    block::{BlockTransform, RotateStrategy, SlideStrategy},
+    block_attach::switch::SwitchTile,
+    game::GameRule,
+    launcher::LauncherState,
+};
+
+/// ステージ情報の中で何番目に出現したものか. 再開時に保存データとエンティティを対応させるのに使う
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StageObjectId {
+    Block(usize),
+    /// ブロックにくっついたゴール. 番号はブロックのもの
+    BlockGoal(usize),
+    Goal(usize),
+    Switch(usize),
+    Launcher(usize),
+    Item(usize),
+    Checkpoint(usize),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedBall {
+    pub ball_type: BallType,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub nocking: bool,
+    pub airborne_frames: u32,
+    pub checkpoints: Vec<u32>,
+}
+
+/// 関数はファイルに書けないので, 再開時にブロックのSwitchReceiverから取り出す
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum SavedUpdaterType {
+    BlockPos,
+    BlockAngle,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedUpdater {
+    pub current_range: Vec<i32>,
+    pub range: Vec<i32>,
+    pub updater_type: SavedUpdaterType,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedBlock {
+    pub id: StageObjectId,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub block_transform: BlockTransform,
+    pub rotate_strategy: Option<RotateStrategy>,
+    pub slide_strategy: Option<SlideStrategy>,
+    pub updaters: Option<Vec<SavedUpdater>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedSwitch {
+    pub id: StageObjectId,
+    pub switch: SwitchTile,
+    pub pressed_once: bool,
+    pub timer: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedLauncher {
+    pub id: StageObjectId,
+    pub angle: f32,
+    pub state: LauncherState,
+}
+
+/// コースの途中だった場合の進行状況
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedCourse {
+    pub title: String,
+    pub current: usize,
+    pub total_strokes: u32,
+    pub total_par: u32,
+}
+
+/// 中断したときのゲームの状態. ステージはタイトルから作り直す
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedGame {
+    pub stage_title: String,
+    /// 自動生成したステージはパーが作り直すと失われるので保存しておく
+    pub par: u32,
+    pub rule: GameRule,
+    pub course: Option<SavedCourse>,
+    pub remaining_time: u32,
+    pub balls: Vec<SavedBall>,
+    pub blocks: Vec<SavedBlock>,
+    pub switches: Vec<SavedSwitch>,
+    pub launchers: Vec<SavedLauncher>,
+    pub goal_counts: Vec<(StageObjectId, u32)>,
+    pub magazine: (Vec<BallType>, usize),
+    pub score: u32,
+    pub goaled_balls: u32,
+    pub lost_balls: u32,
+    pub strokes: u32,
+    pub next_goal_order: u32,
+    pub reached_goals: Vec<String>,
+    /// 取得済みのアイテムと通過済みのチェックポイント. 再開後に取り直せないようにする
+    pub collected_item_ids: Vec<StageObjectId>,
+    pub passed_checkpoint_ids: Vec<StageObjectId>,
+    /// グループ操作のステージで操作していたグループの番号
+    pub control_group: Option<usize>,
+    pub passed_time: u32,
+    pub operation_amount: u32,
+    pub collected_items: u32,
+    pub bounce_count: u32,
+    pub max_ball_speed: f32,
+    pub longest_airborne: u32,
+    pub rewinds: u32,
+    /// スローモーションの残量と使った量. 再開時はスローモーションを切った状態にする
+    pub slow_motion_meter: u32,
+    pub slow_motion_used: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::block::BlockSlidePath;
+
+    fn saved_game() -> SavedGame {
+        SavedGame {
+            stage_title: "random[20240229:2]".to_string(),
+            par: 3,
+            rule: GameRule::Strokes,
+            course: Some(SavedCourse {
+                title: "course".to_string(),
+                current: 1,
+                total_strokes: 4,
+                total_par: 6,
+            }),
+            remaining_time: 1200,
+            balls: vec![SavedBall {
+                ball_type: BallType::Metal,
+                pos: Vec2::new(-12.5, 40.0),
+                vel: Vec2::new(3.25, -0.1),
+                nocking: false,
+                airborne_frames: 17,
+                checkpoints: vec![0, 2],
+            }],
+            blocks: vec![SavedBlock {
+                id: StageObjectId::Block(2),
+                translation: Vec3::new(10.0, -20.0, 0.5),
+                rotation: Quat::from_rotation_z(0.3),
+                block_transform: BlockTransform {
+                    angle: 0.3,
+                    pos_param: -0.75,
+                    ..Default::default()
+                },
+                rotate_strategy: Some(RotateStrategy::Auto(0.02)),
+                slide_strategy: Some(SlideStrategy::AutoWrap {
+                    speed: 0.01,
+                    path: BlockSlidePath::StandardLine {
+                        theta: 1.5,
+                        width: 60.0,
+                    },
+                }),
+                updaters: Some(vec![SavedUpdater {
+                    current_range: vec![1, 2],
+                    range: vec![0, 1, 2],
+                    updater_type: SavedUpdaterType::BlockAngle,
+                }]),
+            }],
+            switches: vec![SavedSwitch {
+                id: StageObjectId::Switch(0),
+                switch: SwitchTile {
+                    just_active: false,
+                    active: true,
+                    threshold: 1.0,
+                    auto_reverse: Some(60),
+                    target_id: 1,
+                    extents: Vec2::new(40.0, 10.0),
+                },
+                pressed_once: true,
+                timer: Some(12),
+            }],
+            launchers: vec![SavedLauncher {
+                id: StageObjectId::Launcher(0),
+                angle: -0.4,
+                state: LauncherState::Nocking,
+            }],
+            goal_counts: vec![(StageObjectId::BlockGoal(1), 2)],
+            magazine: (vec![BallType::Normal, BallType::Metal], 1),
+            score: 2,
+            goaled_balls: 1,
+            lost_balls: 1,
+            strokes: 2,
+            next_goal_order: 1,
+            reached_goals: vec!["goal".to_string()],
+            collected_item_ids: vec![StageObjectId::Item(0)],
+            passed_checkpoint_ids: vec![StageObjectId::Checkpoint(1)],
+            control_group: Some(1),
+            passed_time: 900,
+            operation_amount: 300,
+            collected_items: 1,
+            bounce_count: 5,
+            max_ball_speed: 7.5,
+            longest_airborne: 40,
+            rewinds: 1,
+            slow_motion_meter: 80,
+            slow_motion_used: 20,
+        }
+    }
+
+    #[test]
+    fn saved_game_round_trips_through_ron() {
+        let saved = saved_game();
+        // 保存するときと同じ書き方にする
+        let text = ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default()).unwrap();
+        let loaded = ron::from_str::<SavedGame>(&text).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", saved));
+    }
+}
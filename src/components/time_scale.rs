@@ -0,0 +1,64 @@
+//! ゲーム内の時間の進み方. スローモーションと, ボールの時間だけを変える領域.
+use bevy::prelude::*;
+
+/// スローモーション中の時間の進む速さ
+pub const SLOW_MOTION_SCALE: f32 = 0.25;
+/// 1ステージでスローモーションを使えるフレーム数（実時間で10秒）
+pub const SLOW_MOTION_FRAMES: u32 = 10 * 60;
+
+/// ゲーム全体の時間の進む速さ. 1.0で通常, 小さいほどゆっくり進む.
+/// 物理, ブロックの自動の動き, Updater, タイマーがこれに従う. グローバルなリソースとして使う.
+pub struct TimeScale(pub f32);
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.0)
+    }
+}
+
+/// スローモーションの状態. ステージごとにリセットされる
+pub struct SlowMotion {
+    pub active: bool,
+    /// 残りの使えるフレーム数
+    pub meter: u32,
+    /// 使ったフレーム数
+    pub used: u32,
+}
+impl Default for SlowMotion {
+    fn default() -> Self {
+        SlowMotion {
+            active: false,
+            meter: SLOW_MOTION_FRAMES,
+            used: 0,
+        }
+    }
+}
+
+/// ボールの中心が入っている間, そのボールの時間の進む速さを変える矩形領域
+#[derive(Component, Clone, Debug)]
+pub struct TimeScaleZone {
+    /// 1.0より小さければ遅く, 大きければ速くなる
+    pub scale: f32,
+    pub extents: Vec2,
+}
+impl TimeScaleZone {
+    pub fn color(&self) -> Color {
+        if self.scale < 1.0 {
+            Color::rgba(0.3, 0.5, 1.0, 0.3)
+        } else {
+            Color::rgba(1.0, 0.6, 0.2, 0.3)
+        }
+    }
+}
+
+/// エンティティごとの時間の進む速さ. 全体の速さに掛けて使う. ボールには生成時から付けておく
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LocalTimeScale(pub f32);
+impl Default for LocalTimeScale {
+    fn default() -> Self {
+        LocalTimeScale(1.0)
+    }
+}
+
+/// スローモーションの残量の表示
+#[derive(Component)]
+pub struct SlowMotionMeter;
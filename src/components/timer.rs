@@ -4,15 +4,22 @@ use bevy::prelude::*;
 #[derive(Component)]
 pub struct CountDownTimer {
     count: u32,
-    pause: bool,
+    /// stopで止められた. 一時停止が終わっても止まったままにする
+    stopped: bool,
+    /// 一時停止中や巻き戻し中
+    paused: bool,
     pub auto_despawn: bool,
+    /// 時間の進む速さが1でないときに持ち越す1フレーム未満の端数
+    carry: f32,
 }
 impl Default for CountDownTimer {
     fn default() -> Self {
         CountDownTimer {
             count: 0,
-            pause: false,
+            stopped: false,
+            paused: false,
             auto_despawn: true,
+            carry: 0.0,
         }
     }
 }
@@ -31,10 +38,15 @@ impl CountDownTimer {
             ..Default::default()
         }
     }
-    pub fn tick(&mut self) {
-        if !self.pause && self.count > 0 {
-            self.count -= 1;
+    /// 時間の進む速さに応じて減らす. 1フレームに満たない分は次に持ち越す
+    pub fn tick(&mut self, scale: f32) {
+        if self.stopped || self.paused || self.count == 0 {
+            return;
         }
+        self.carry += scale;
+        let frames = self.carry.floor();
+        self.carry -= frames;
+        self.count = self.count.saturating_sub(frames as u32);
     }
     pub fn count(&self) -> u32 {
         self.count
@@ -42,11 +54,20 @@ impl CountDownTimer {
     pub fn is_finished(&self) -> bool {
         self.count == 0
     }
+    /// 残りカウントを増やす
+    pub fn extend(&mut self, count: u32) {
+        self.count += count;
+    }
+    /// 残りカウントを減らす. 0より小さくはならない
+    pub fn shorten(&mut self, count: u32) {
+        self.count = self.count.saturating_sub(count);
+    }
     pub fn stop(&mut self) {
-        self.pause = true;
+        self.stopped = true;
     }
-    pub fn toggle_pause(&mut self) {
-        self.pause = !self.pause;
+    /// 一時停止の開始と終了で呼ぶ. stopで止めたタイマーは再開しない
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
     }
 }
 
@@ -71,7 +92,50 @@ impl FrameCounter {
     pub fn reset(&mut self) {
         self.count = 0;
     }
-    pub fn toggle_pause(&mut self) {
-        self.pause = !self.pause;
+    pub fn set_paused(&mut self, paused: bool) {
+        self.pause = paused;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_tick_carries_partial_frames() {
+        let mut timer = CountDownTimer::new(10);
+        for _ in 0..3 {
+            timer.tick(0.25);
+        }
+        assert_eq!(timer.count(), 10);
+        timer.tick(0.25);
+        assert_eq!(timer.count(), 9);
+        for _ in 0..36 {
+            timer.tick(0.25);
+        }
+        assert!(timer.is_finished());
+    }
+
+    #[test]
+    fn fast_tick_counts_several_frames() {
+        let mut timer = CountDownTimer::new(5);
+        timer.tick(2.0);
+        assert_eq!(timer.count(), 3);
+        timer.tick(2.0);
+        timer.tick(2.0);
+        assert_eq!(timer.count(), 0);
+        assert!(timer.is_finished());
+    }
+
+    #[test]
+    fn paused_timer_keeps_count() {
+        let mut timer = CountDownTimer::new(5);
+        timer.tick(0.5);
+        timer.set_paused(true);
+        timer.tick(2.0);
+        assert_eq!(timer.count(), 5);
+        timer.set_paused(false);
+        timer.tick(0.5);
+        assert_eq!(timer.count(), 4);
     }
 }
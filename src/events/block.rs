@@ -25,6 +25,7 @@ pub struct SpawnBlockEvent {
     pub rotate_strategy: RotateStrategy,
     pub slide_strategy: SlideStrategy,
     pub block_attachment: Vec<BlockAttachment>,
+    pub control_group: u32,
 }
 impl ToSpawnEvent for BlockInfo {
     type E = SpawnBlockEvent;
@@ -46,6 +47,7 @@ impl ToSpawnEvent for BlockInfo {
                     rotate_strategy: RotateStrategy::NoRotate,
                     slide_strategy: SlideStrategy::NoSlide,
                     block_attachment: self.block_attachment.clone(),
+                    control_group: self.control_group,
                 }
             }
             BlockShapeInfo::Rect {
@@ -69,6 +71,7 @@ impl ToSpawnEvent for BlockInfo {
                     rotate_strategy: rotate_strategy.clone(),
                     slide_strategy: slide_strategy.clone(),
                     block_attachment: self.block_attachment.clone(),
+                    control_group: self.control_group,
                 }
             }
             BlockShapeInfo::Ellipse {
@@ -92,6 +95,7 @@ impl ToSpawnEvent for BlockInfo {
                     rotate_strategy: rotate_strategy.clone(),
                     slide_strategy: slide_strategy.clone(),
                     block_attachment: self.block_attachment.clone(),
+                    control_group: self.control_group,
                 }
             }
         }
@@ -1 +1,3 @@
 pub struct GameOverEvent;
+/// ゲームを中断してメニューに戻るときに送り, その時点の状態を保存させる
+pub struct SuspendGameEvent;
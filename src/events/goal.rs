@@ -1,7 +1,15 @@
 use bevy::prelude::*;
 
+use crate::components::{block::SlideStrategy, goal::GoalHole};
+
 pub struct SpawnGoalEvent {
-    pub pos: Vec2,
-    pub radius: f32,
+    pub component: GoalHole,
+    pub slide_strategy: SlideStrategy,
+}
+
+/// ボールがゴールに入ったときに送られる. ボールに付くGoalinBallはコマンドで入れるので,
+/// 同じフレームのうちはこちらで入ったことを知る
+pub struct BallGoaledEvent {
+    pub ball: Entity,
     pub score: u32,
 }
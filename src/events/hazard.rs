@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+
+use crate::components::hazard::{BallLostCause, HazardKind};
+
+pub struct SpawnHazardEvent {
+    pub pos: Vec2,
+    pub extents: Vec2,
+    pub kind: HazardKind,
+}
+
+/// ボールを失ったときに送られる
+pub struct BallLostEvent {
+    pub ball: Entity,
+    pub pos: Vec2,
+    pub cause: BallLostCause,
+}
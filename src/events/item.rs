@@ -0,0 +1,18 @@
+use bevy::prelude::*;
+
+use crate::components::item::ItemType;
+
+pub struct SpawnItemEvent {
+    pub pos: Vec2,
+    pub item_type: ItemType,
+}
+
+pub struct SpawnCheckpointEvent {
+    pub pos: Vec2,
+    pub radius: f32,
+}
+
+/// ボールがアイテムを取得したときに送られる
+pub struct CollectItemEvent {
+    pub item_type: ItemType,
+}
@@ -6,4 +6,5 @@ pub struct SpawnLauncherEvent {
     pub rotate_speed: f32,
     pub min_angle: f32,
     pub max_angle: f32,
+    pub control_group: u32,
 }
@@ -2,9 +2,12 @@ pub mod ball;
 pub mod block;
 pub mod game;
 pub mod goal;
+pub mod hazard;
+pub mod item;
 pub mod launcher;
 pub mod main_menu;
 pub mod switch;
+pub mod time_scale;
 
 use bevy::prelude::*;
 
@@ -14,10 +17,13 @@ use crate::components::main_menu::menu::ChangeMenuLayerEvent;
 use self::{
     ball::{LaunchBallEvent, SetBallEvent, SpawnBallEvent},
     block::SpawnBlockEvent,
-    game::GameOverEvent,
-    goal::SpawnGoalEvent,
+    game::{GameOverEvent, SuspendGameEvent},
+    goal::{BallGoaledEvent, SpawnGoalEvent},
+    hazard::{BallLostEvent, SpawnHazardEvent},
+    item::{CollectItemEvent, SpawnCheckpointEvent, SpawnItemEvent},
     launcher::SpawnLauncherEvent,
     switch::SpawnSwitchEvent,
+    time_scale::SpawnTimeScaleZoneEvent,
 };
 
 pub trait ToSpawnEvent {
@@ -31,8 +37,16 @@ pub fn add_events(app: &mut App) {
     app.add_event::<SpawnLauncherEvent>();
     app.add_event::<SpawnBlockEvent>();
     app.add_event::<SpawnGoalEvent>();
+    app.add_event::<BallGoaledEvent>();
     app.add_event::<SetBallEvent>();
     app.add_event::<SpawnSwitchEvent>();
     app.add_event::<GameOverEvent>();
+    app.add_event::<SuspendGameEvent>();
+    app.add_event::<SpawnItemEvent>();
+    app.add_event::<SpawnCheckpointEvent>();
+    app.add_event::<CollectItemEvent>();
+    app.add_event::<SpawnHazardEvent>();
+    app.add_event::<SpawnTimeScaleZoneEvent>();
+    app.add_event::<BallLostEvent>();
     app.add_event::<ChangeMenuLayerEvent>();
 }
@@ -0,0 +1,7 @@
+use bevy::prelude::*;
+
+pub struct SpawnTimeScaleZoneEvent {
+    pub pos: Vec2,
+    pub extents: Vec2,
+    pub scale: f32,
+}
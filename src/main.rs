@@ -3,17 +3,20 @@ mod events;
 mod stages;
 mod systems;
 
-use bevy::{ecs::schedule::ReportExecutionOrderAmbiguities, prelude::*};
+use bevy::{ecs::schedule::ReportExecutionOrderAmbiguities, log::LogPlugin, prelude::*};
 use bevy_prototype_lyon::prelude::*;
 
-use components::timer::CountDownTimer;
+use components::{editor::EditorSession, timer::CountDownTimer};
 use events::add_events;
 use systems::{
-    ball::BallPlugin, block::BlockPlugin, block_attach::BlockAttachmentPlugin,
-    collision::CollisionPlugin, effects::EffectPlugin, field::FieldPlugin, game::GameManagePlugin,
-    goal::GoalPlugin, info_board::InfoBoardPlugin, launcher::LauncherPlugin, load::LoadStagePlugin,
-    main_menu::menu::MainMenuPlugin, physics::PhysicsPlugin, setup::global_setup,
-    timer::TimersPlugin,
+    agent::AgentPlugin, ball::BallPlugin, block::BlockPlugin, block_attach::BlockAttachmentPlugin,
+    camera::CameraPlugin, collision::CollisionPlugin, control::ControlGroupPlugin,
+    editor::EditorPlugin, effects::EffectPlugin, field::FieldPlugin, game::GameManagePlugin,
+    goal::GoalPlugin, hazard::HazardPlugin, info_board::InfoBoardPlugin, input::InputPlugin,
+    item::ItemPlugin, launcher::LauncherPlugin, load::LoadStagePlugin,
+    main_menu::menu::MainMenuPlugin, pause::PausePlugin, physics::PhysicsPlugin,
+    progress::ProgressPlugin, rewind::RewindPlugin, screen::ScreenPlugin, settings::SettingsPlugin,
+    setup::global_setup, suspend::SuspendPlugin, time_scale::TimeScalePlugin, timer::TimersPlugin,
 };
 
 const SCREEN_WIDTH: f32 = 1280.0;
@@ -24,21 +27,58 @@ pub enum AppState {
     Menu,
     Loading,
     Game,
+    /// ゲーム中の一時停止. Gameの上に積まれる
+    Paused,
+    /// ゲーム中の巻き戻し. Gameの上に積まれる
+    Rewinding,
     BackToMenu,
     Result,
+    /// ステージエディタ
+    Editor,
 }
 
 fn main() {
+    // `validate [FILE...]`ならゲームを起動せずにステージを検査する.
+    // `solve STAGE`はステージを自動で解き, `replay FILE`は保存した解を再生して確かめる.
+    // `svg STAGE`はステージの配置をSVG画像に書き出す.
+    // `agent`は画面を出さずにエージェントからの命令でゲームを進める.
+    // `generate [SEED]`は種から解けるステージを作る
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let command = match args.first().map(String::as_str) {
+        Some("validate") => Some(stages::validate::run_validate_command as fn(&[String]) -> i32),
+        Some("solve") => Some(stages::solve::run_solve_command as fn(&[String]) -> i32),
+        Some("replay") => Some(stages::solve::run_replay_command as fn(&[String]) -> i32),
+        Some("svg") => Some(stages::svg::run_svg_command as fn(&[String]) -> i32),
+        Some("agent") => Some(systems::agent::run_agent_command as fn(&[String]) -> i32),
+        Some("generate") => Some(stages::generate::run_generate_command as fn(&[String]) -> i32),
+        _ => None,
+    };
+    if let Some(command) = command {
+        std::process::exit(command(&args[1..]));
+    }
     let window = WindowDescriptor {
         title: "Block Golf".to_string(),
         width: SCREEN_WIDTH,
         height: SCREEN_HEIGHT,
-        resizable: false,
+        resizable: true,
+        resize_constraints: bevy::window::WindowResizeConstraints {
+            min_width: SCREEN_WIDTH / 4.0,
+            min_height: SCREEN_HEIGHT / 4.0,
+            ..Default::default()
+        },
         ..Default::default()
     };
     let mut app = App::new();
     app.insert_resource(window);
-    app.add_plugins(DefaultPlugins);
+    // `--agent`なら画面を出したまま標準入力からエージェントが操作する.
+    // 標準出力は観測に使うのでログは出さない
+    let agent_mode = args.iter().any(|arg| arg == "--agent");
+    if agent_mode {
+        app.add_plugins_with(DefaultPlugins, |group| group.disable::<LogPlugin>());
+        app.add_plugin(AgentPlugin);
+    } else {
+        app.add_plugins(DefaultPlugins);
+    }
     app.add_system(bevy::window::close_on_esc);
     app.add_plugin(ShapePlugin);
     add_events(&mut app);
@@ -52,6 +92,8 @@ fn main() {
     app.add_plugin(LoadStagePlugin);
     app.add_plugin(FieldPlugin);
     app.add_plugin(GoalPlugin);
+    app.add_plugin(ItemPlugin);
+    app.add_plugin(HazardPlugin);
     app.add_plugin(BallPlugin);
     app.add_plugin(BlockPlugin);
     app.add_plugin(BlockAttachmentPlugin);
@@ -60,7 +102,18 @@ fn main() {
     app.add_plugin(PhysicsPlugin);
     app.add_plugin(InfoBoardPlugin);
     app.add_plugin(TimersPlugin);
+    app.add_plugin(TimeScalePlugin);
     app.add_plugin(GameManagePlugin);
+    app.add_plugin(ProgressPlugin);
+    app.add_plugin(PausePlugin);
+    app.add_plugin(RewindPlugin);
+    app.add_plugin(SuspendPlugin);
+    app.add_plugin(SettingsPlugin);
+    app.add_plugin(InputPlugin);
+    app.add_plugin(ControlGroupPlugin);
+    app.add_plugin(ScreenPlugin);
+    app.add_plugin(CameraPlugin);
+    app.add_plugin(EditorPlugin);
     app.run();
 }
 
@@ -81,10 +134,16 @@ impl Plugin for BackToMenuPlugin {
         app.add_system_set(
             SystemSet::on_update(AppState::BackToMenu).with_system(
                 (|mut state: ResMut<State<crate::AppState>>,
-                  timer: Query<&CountDownTimer, With<BackToMenuFlag>>| {
+                  timer: Query<&CountDownTimer, With<BackToMenuFlag>>,
+                  editor_session: Option<Res<EditorSession>>| {
                     if let Ok(timer) = timer.get_single() {
                         if timer.is_finished() {
-                            state.set(AppState::Menu).unwrap();
+                            // テストプレイ中ならエディタに戻る
+                            if editor_session.is_some() {
+                                state.set(AppState::Editor).unwrap();
+                            } else {
+                                state.set(AppState::Menu).unwrap();
+                            }
                         }
                     }
                 })
@@ -46,6 +46,7 @@ pub fn aisle0() -> StageInfo {
         rotate_speed: ROTATE_SPEED,
         min_angle: 0.0,
         max_angle: FRAC_PI_2,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -60,6 +61,7 @@ pub fn aisle0() -> StageInfo {
     StageInfo {
         stage_title: "aisle0",
         time: 60 * 60,
+        par: 1,
         launcher: launcher_info,
         blocks: field_block()
             .into_iter()
@@ -69,6 +71,7 @@ pub fn aisle0() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
 
@@ -89,6 +92,7 @@ pub fn aisle1() -> StageInfo {
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
             pos: orig_point,
@@ -102,6 +106,7 @@ pub fn aisle1() -> StageInfo {
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
     ];
 
@@ -111,6 +116,7 @@ pub fn aisle1() -> StageInfo {
         rotate_speed: ROTATE_SPEED,
         min_angle: 0.0,
         max_angle: FRAC_PI_2,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -120,6 +126,7 @@ pub fn aisle1() -> StageInfo {
         pos: orig_point + Vec2::Y * FIELD_HEIGHT * 0.8,
         radius: 30.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
@@ -134,6 +141,7 @@ pub fn aisle1() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
 
@@ -153,6 +161,7 @@ pub fn aisle2() -> StageInfo {
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
             pos: Vec2::new(-FIELD_WIDTH / 2.0 - 30.0, FIELD_HEIGHT / 2.0 - 60.0),
@@ -172,6 +181,7 @@ pub fn aisle2() -> StageInfo {
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
     ];
 
@@ -181,6 +191,7 @@ pub fn aisle2() -> StageInfo {
         rotate_speed: ROTATE_SPEED,
         min_angle: 0.0,
         max_angle: FRAC_PI_2,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -190,11 +201,13 @@ pub fn aisle2() -> StageInfo {
         pos: Vec2::new(FIELD_WIDTH / 2.0 - 20.0, FIELD_HEIGHT / 2.0 - 40.0),
         radius: 50.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
         stage_title: "aisle2",
         time: 60 * 60,
+        par: 3,
         launcher: launcher_info,
         blocks: field_block()
             .into_iter()
@@ -204,5 +217,6 @@ pub fn aisle2() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
@@ -28,6 +28,7 @@ pub fn debug_stage() -> StageInfo {
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
             pos: Vec2::new(0.0, 0.0),
@@ -41,6 +42,7 @@ pub fn debug_stage() -> StageInfo {
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
     ];
 
@@ -50,6 +52,7 @@ pub fn debug_stage() -> StageInfo {
         rotate_speed: 0.02,
         min_angle: 0.0,
         max_angle: FRAC_PI_2,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -59,6 +62,7 @@ pub fn debug_stage() -> StageInfo {
         pos: Vec2::new(200.0, 150.0),
         radius: 20.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
@@ -73,5 +77,6 @@ pub fn debug_stage() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
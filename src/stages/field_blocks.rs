@@ -5,47 +5,56 @@ use crate::components::physics::material::PhysicMaterial;
 use crate::systems::field::{FIELD_HEIGHT, FIELD_WIDTH};
 
 pub fn field_block() -> Vec<BlockInfo> {
+    field_block_of_size(Vec2::new(FIELD_WIDTH, FIELD_HEIGHT))
+}
+
+/// 指定した大きさのフィールドを囲む壁
+pub fn field_block_of_size(field_size: Vec2) -> Vec<BlockInfo> {
     let material = PhysicMaterial::new(1.0, 1.0, 0.0);
     vec![
         BlockInfo {
-            pos: Vec2::new(FIELD_WIDTH / 2.0 + 30.0, 0.0),
+            pos: Vec2::new(field_size.x / 2.0 + 30.0, 0.0),
             block_shape_info: BlockShapeInfo::Wall {
-                extents: Vec2::new(60.0, FIELD_HEIGHT),
+                extents: Vec2::new(60.0, field_size.y),
             },
             material,
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
-            pos: Vec2::new(-FIELD_WIDTH / 2.0 - 30.0, 0.0),
+            pos: Vec2::new(-field_size.x / 2.0 - 30.0, 0.0),
             block_shape_info: BlockShapeInfo::Wall {
-                extents: Vec2::new(60.0, FIELD_HEIGHT),
+                extents: Vec2::new(60.0, field_size.y),
             },
             material,
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
-            pos: Vec2::new(0.0, FIELD_HEIGHT / 2.0 + 30.0),
+            pos: Vec2::new(0.0, field_size.y / 2.0 + 30.0),
             block_shape_info: BlockShapeInfo::Wall {
-                extents: Vec2::new(FIELD_WIDTH, 60.0),
+                extents: Vec2::new(field_size.x, 60.0),
             },
             material,
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
-            pos: Vec2::new(0.0, -FIELD_HEIGHT / 2.0 - 30.0),
+            pos: Vec2::new(0.0, -field_size.y / 2.0 - 30.0),
             block_shape_info: BlockShapeInfo::Wall {
-                extents: Vec2::new(FIELD_WIDTH, 60.0),
+                extents: Vec2::new(field_size.x, 60.0),
             },
             material,
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
     ]
 }
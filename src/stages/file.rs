@@ -0,0 +1,386 @@
+//! ステージファイル（RON形式）. エディタで作ったステージを保存し, 読み込んで遊べるようにする.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::field_blocks::field_block_of_size;
+use super::intern_title;
+use super::structs::{
+    BallInfo, BlockInfo, BlockShapeInfo, GoalInfo, LauncherInfo, StageInfo, SwitchInfo,
+};
+use crate::{
+    components::{
+        ball::BallType,
+        block::{RotateStrategy, SlideStrategy},
+        block_attach::{
+            fan::Fan,
+            magnet::Magnet,
+            switch::{SwitchReceiver, SwitchType},
+            BlockAttachment,
+        },
+        hazard::HazardKind,
+        physics::material::PhysicMaterial,
+    },
+    systems::field::{FIELD_HEIGHT, FIELD_WIDTH},
+};
+
+/// ファイルに保存できるステージの情報. フィールドを囲む壁は読み込むときに自動で追加される.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StageFile {
+    pub title: String,
+    pub time: u32,
+    pub par: u32,
+    pub field_size: Vec2,
+    pub launcher: LauncherFile,
+    pub balls: Vec<BallType>,
+    pub blocks: Vec<BlockFile>,
+    pub goals: Vec<GoalFile>,
+    pub switches: Vec<SwitchFile>,
+}
+impl Default for StageFile {
+    fn default() -> Self {
+        Self {
+            title: "custom".to_string(),
+            time: 60 * 60,
+            par: 2,
+            field_size: Vec2::new(FIELD_WIDTH, FIELD_HEIGHT),
+            launcher: LauncherFile::default(),
+            balls: vec![BallType::Normal; 3],
+            blocks: vec![],
+            goals: vec![GoalFile {
+                pos: Vec2::new(FIELD_WIDTH / 2.0 - 60.0, 0.0),
+                ..Default::default()
+            }],
+            switches: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LauncherFile {
+    pub pos: Vec2,
+    pub default_angle: f32,
+    pub rotate_speed: f32,
+    pub min_angle: f32,
+    pub max_angle: f32,
+}
+impl Default for LauncherFile {
+    fn default() -> Self {
+        let info = LauncherInfo::default();
+        Self {
+            pos: info.pos,
+            default_angle: info.default_angle,
+            rotate_speed: info.rotate_speed,
+            min_angle: info.min_angle,
+            max_angle: info.max_angle,
+        }
+    }
+}
+
+/// ブロックの形. 回転軸はブロックの位置になる
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum BlockShapeFile {
+    Rect { extents: Vec2, origin: Vec2 },
+    Ellipse { radii: Vec2 },
+}
+
+/// ブロックに取り付けるもの（ファイルで扱えるもののみ）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AttachmentFile {
+    Fan(Fan),
+    Magnet(Magnet),
+    Hazard(HazardKind),
+    /// target_idのスイッチが押されている間, ブロックの動きを切り替える
+    SwitchReceiver {
+        target_id: u32,
+        motion: SwitchedMotion,
+    },
+}
+
+/// スイッチで切り替えた後のブロックの動き. スイッチが戻るとブロック本来の動きに戻る.
+/// 関数で動かす移動や回転はファイルに書けない
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SwitchedMotion {
+    Rotate(RotateStrategy),
+    Slide(SlideStrategy),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockFile {
+    pub pos: Vec2,
+    pub shape: BlockShapeFile,
+    pub material: PhysicMaterial,
+    pub default_angle: f32,
+    pub rotate_strategy: RotateStrategy,
+    pub slide_strategy: SlideStrategy,
+    pub attachment: Option<AttachmentFile>,
+}
+impl Default for BlockFile {
+    fn default() -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            shape: BlockShapeFile::Rect {
+                extents: Vec2::new(160.0, 30.0),
+                origin: Vec2::ZERO,
+            },
+            material: PhysicMaterial::default(),
+            default_angle: 0.0,
+            rotate_strategy: RotateStrategy::NoRotate,
+            slide_strategy: SlideStrategy::NoSlide,
+            attachment: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GoalFile {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub score: u32,
+}
+impl Default for GoalFile {
+    fn default() -> Self {
+        Self {
+            pos: Vec2::ZERO,
+            radius: 40.0,
+            score: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwitchFile {
+    pub pos: Vec2,
+    pub extents: Vec2,
+    pub target_id: u32,
+    pub auto_reverse: Option<u32>,
+}
+impl Default for SwitchFile {
+    fn default() -> Self {
+        let info = SwitchInfo::default();
+        Self {
+            pos: info.pos,
+            extents: info.extents,
+            target_id: info.target_id,
+            auto_reverse: info.auto_reverse,
+        }
+    }
+}
+
+impl BlockFile {
+    fn to_block_info(&self) -> BlockInfo {
+        let block_shape_info = match self.shape {
+            BlockShapeFile::Rect { extents, origin } => BlockShapeInfo::Rect {
+                extents,
+                rect_origin: origin,
+                rotate_strategy: self.rotate_strategy.clone(),
+                slide_strategy: self.slide_strategy.clone(),
+            },
+            BlockShapeFile::Ellipse { radii } => BlockShapeInfo::Ellipse {
+                radii,
+                center: Vec2::ZERO,
+                rotate_strategy: self.rotate_strategy.clone(),
+                slide_strategy: self.slide_strategy.clone(),
+            },
+        };
+        let block_attachment = match &self.attachment {
+            Some(AttachmentFile::Fan(fan)) => vec![BlockAttachment::Fan(fan.clone())],
+            Some(AttachmentFile::Magnet(magnet)) => vec![BlockAttachment::Magnet(magnet.clone())],
+            Some(AttachmentFile::Hazard(kind)) => vec![BlockAttachment::Hazard(*kind)],
+            Some(AttachmentFile::SwitchReceiver { target_id, motion }) => {
+                let switch_type = match motion {
+                    SwitchedMotion::Rotate(after) => SwitchType::ChangeRotateStrategy {
+                        before: self.rotate_strategy.clone(),
+                        after: after.clone(),
+                    },
+                    SwitchedMotion::Slide(after) => SwitchType::ChangeSlideStrategy {
+                        before: self.slide_strategy.clone(),
+                        after: after.clone(),
+                    },
+                };
+                vec![BlockAttachment::SwitchReceiver {
+                    receiver: SwitchReceiver {
+                        switch_type,
+                        target_id: *target_id,
+                    },
+                }]
+            }
+            None => vec![],
+        };
+        BlockInfo {
+            pos: self.pos,
+            block_shape_info,
+            material: self.material,
+            default_angle: self.default_angle,
+            block_attachment,
+            ..Default::default()
+        }
+    }
+}
+
+impl StageFile {
+    /// ファイルから読み込む. 読めなければNone
+    pub fn load(path: &str) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        ron::from_str(&text)
+            .map_err(|err| warn!("failed to parse {}: {}", path, err))
+            .ok()
+    }
+
+    /// ゲームで遊べるステージ情報に変換する
+    pub fn to_stage_info(&self) -> StageInfo {
+        let launcher = LauncherInfo {
+            pos: self.launcher.pos,
+            default_angle: self.launcher.default_angle,
+            rotate_speed: self.launcher.rotate_speed,
+            min_angle: self.launcher.min_angle,
+            max_angle: self.launcher.max_angle,
+            ..Default::default()
+        };
+        let goal_pos = self
+            .goals
+            .iter()
+            .map(|goal| GoalInfo {
+                pos: goal.pos,
+                radius: goal.radius,
+                score: goal.score,
+                ..Default::default()
+            })
+            .collect();
+        let switches = self
+            .switches
+            .iter()
+            .map(|switch| SwitchInfo {
+                pos: switch.pos,
+                extents: switch.extents,
+                target_id: switch.target_id,
+                auto_reverse: switch.auto_reverse,
+                ..Default::default()
+            })
+            .collect();
+        StageInfo {
+            stage_title: intern_title(&self.title),
+            time: self.time,
+            par: self.par,
+            field_size: self.field_size,
+            launcher,
+            blocks: field_block_of_size(self.field_size)
+                .into_iter()
+                .chain(self.blocks.iter().map(BlockFile::to_block_info))
+                .collect(),
+            balls: self
+                .balls
+                .iter()
+                .map(|ball_type| BallInfo::from_type(*ball_type))
+                .collect(),
+            goal_pos,
+            switches,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_stage_info_adds_walls_around_field() {
+        let stage_file = StageFile {
+            field_size: Vec2::new(1200.0, 800.0),
+            blocks: vec![BlockFile::default()],
+            ..Default::default()
+        };
+        let stage = stage_file.to_stage_info();
+        assert_eq!(stage.stage_title, "custom");
+        assert_eq!(stage.field_size, Vec2::new(1200.0, 800.0));
+        // 四方の壁の後に置いたブロックが続く
+        assert_eq!(stage.blocks.len(), 5);
+        assert!(stage.blocks[..4]
+            .iter()
+            .all(|block| matches!(block.block_shape_info, BlockShapeInfo::Wall { .. })));
+        assert_eq!(stage.blocks[0].pos, Vec2::new(630.0, 0.0));
+        assert!(matches!(
+            stage.blocks[4].block_shape_info,
+            BlockShapeInfo::Rect { .. }
+        ));
+        assert_eq!(stage.balls.len(), 3);
+        assert_eq!(stage.goal_pos.len(), 1);
+        assert_eq!(stage.goal_pos[0].pos, stage_file.goals[0].pos);
+    }
+
+    #[test]
+    fn to_stage_info_reuses_interned_title() {
+        let stage_file = StageFile {
+            title: "test play".to_string(),
+            ..Default::default()
+        };
+        let first = stage_file.to_stage_info().stage_title;
+        let second = stage_file.to_stage_info().stage_title;
+        assert_eq!(first, "test play");
+        assert!(std::ptr::eq(first, second));
+    }
+
+    #[test]
+    fn switch_receiver_returns_to_block_motion() {
+        let stage_file = StageFile {
+            blocks: vec![BlockFile {
+                rotate_strategy: RotateStrategy::Manual(0.01),
+                attachment: Some(AttachmentFile::SwitchReceiver {
+                    target_id: 2,
+                    motion: SwitchedMotion::Rotate(RotateStrategy::Auto(0.05)),
+                }),
+                ..Default::default()
+            }],
+            switches: vec![SwitchFile {
+                target_id: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let stage = stage_file.to_stage_info();
+        assert_eq!(stage.switches[0].target_id, 2);
+        match &stage.blocks[4].block_attachment[..] {
+            [BlockAttachment::SwitchReceiver { receiver }] => {
+                assert_eq!(receiver.target_id, 2);
+                assert!(matches!(
+                    receiver.switch_type,
+                    SwitchType::ChangeRotateStrategy {
+                        before: RotateStrategy::Manual(_),
+                        after: RotateStrategy::Auto(_),
+                    }
+                ));
+            }
+            other => panic!("unexpected attachment {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stage_file_round_trips_through_ron() {
+        let stage_file = StageFile {
+            title: "round trip".to_string(),
+            balls: vec![BallType::Normal, BallType::Metal],
+            blocks: vec![BlockFile {
+                attachment: Some(AttachmentFile::Hazard(HazardKind::Lava)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let text = ron::to_string(&stage_file).unwrap();
+        let loaded: StageFile = ron::from_str(&text).unwrap();
+        assert_eq!(loaded.title, "round trip");
+        assert_eq!(loaded.balls, stage_file.balls);
+        assert!(matches!(
+            loaded.blocks[0].attachment,
+            Some(AttachmentFile::Hazard(HazardKind::Lava))
+        ));
+        // 書かれていない項目は既定値になる
+        let partial: StageFile = ron::from_str("(title: \"partial\")").unwrap();
+        assert_eq!(partial.title, "partial");
+        assert_eq!(partial.balls.len(), 3);
+    }
+}
@@ -0,0 +1,449 @@
+//! シード値からステージを自動で作る. 決まった形のブロック（テンプレート）を乱数で並べ,
+//! ソルバーで解けることを確かめてから遊ぶ.
+use std::f32::consts::FRAC_PI_2;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    field_blocks::field_block,
+    intern_title,
+    solve::find_solution,
+    structs::{
+        ArrangeBallInfo, BallInfo, BlockInfo, BlockShapeInfo, GoalInfo, LauncherInfo, StageInfo,
+    },
+};
+use crate::{
+    components::{
+        ball::BallType,
+        block::{BlockSlidePath, RotateStrategy, SlideStrategy},
+        block_attach::{fan::Fan, magnet::Magnet, BlockAttachment},
+        game::GameRule,
+        physics::{force::Gravity, material::PhysicMaterial},
+    },
+    systems::{
+        field::{FIELD_HEIGHT, FIELD_WIDTH},
+        utils::{load_ron_file, save_ron_file, Rng},
+    },
+};
+
+/// 難易度の上限. 1から始まり, 上がるほどブロックが増えゴールが小さくなる
+pub const MAX_DIFFICULTY: u32 = 3;
+/// デイリーチャレンジの難易度
+pub const DAILY_DIFFICULTY: u32 = 2;
+/// 解けるステージが見つかるまでに試す種の数
+const ATTEMPTS: u64 = 8;
+/// 解けるか確かめるときのソルバーの評価回数の上限
+const CHECK_BUDGET: u32 = 120;
+/// ブロック同士, ブロックとランチャーやゴールの間に空ける距離
+const BLOCK_SPACING: f32 = 130.0;
+
+/// 並べるブロックの形
+#[derive(Clone, Copy)]
+enum Template {
+    /// 縦長の柱
+    Pillar,
+    /// 斜めに置いた板
+    Slab,
+    /// よく跳ねる丸いブロック
+    Bumper,
+    /// 自動で回り続ける棒
+    Spinner,
+    /// 端を軸に手で回す棒
+    Lever,
+    /// 往復するブロック
+    Slider,
+    /// 風を出すブロック
+    FanBlock,
+    /// 鉄球を引き寄せるブロック
+    MagnetBlock,
+}
+const TEMPLATES: [Template; 8] = [
+    Template::Pillar,
+    Template::Slab,
+    Template::Bumper,
+    Template::Spinner,
+    Template::Lever,
+    Template::Slider,
+    Template::FanBlock,
+    Template::MagnetBlock,
+];
+impl Template {
+    /// このテンプレートを使い始める難易度
+    fn min_difficulty(self) -> u32 {
+        match self {
+            Template::Pillar | Template::Slab | Template::Bumper => 1,
+            Template::Spinner | Template::Lever | Template::Slider => 2,
+            Template::FanBlock | Template::MagnetBlock => 3,
+        }
+    }
+
+    fn block(self, pos: Vec2, rng: &mut Rng) -> BlockInfo {
+        let sign = if rng.range(0, 1) == 0 { 1.0 } else { -1.0 };
+        let rect = |extents: Vec2, rotate_strategy, slide_strategy| BlockShapeInfo::Rect {
+            extents,
+            rect_origin: Vec2::ZERO,
+            rotate_strategy,
+            slide_strategy,
+        };
+        let mut block = BlockInfo {
+            pos,
+            ..Default::default()
+        };
+        match self {
+            Template::Pillar => {
+                let extents = Vec2::new(rng.range(30, 60) as f32, rng.range(120, 220) as f32);
+                block.block_shape_info =
+                    rect(extents, RotateStrategy::NoRotate, SlideStrategy::NoSlide);
+            }
+            Template::Slab => {
+                let extents = Vec2::new(rng.range(120, 200) as f32, 30.0);
+                block.block_shape_info =
+                    rect(extents, RotateStrategy::NoRotate, SlideStrategy::NoSlide);
+                block.default_angle = (rng.range(-60, 60) as f32).to_radians();
+            }
+            Template::Bumper => {
+                let radius = rng.range(30, 60) as f32;
+                block.block_shape_info = BlockShapeInfo::Ellipse {
+                    radii: Vec2::splat(radius),
+                    center: Vec2::ZERO,
+                    rotate_strategy: RotateStrategy::NoRotate,
+                    slide_strategy: SlideStrategy::NoSlide,
+                };
+                block.material = PhysicMaterial::new(1.2, 1.0, 0.0);
+            }
+            Template::Spinner => {
+                let speed = rng.range(10, 40) as f32 * 0.001 * sign;
+                block.block_shape_info = rect(
+                    Vec2::new(rng.range(120, 180) as f32, 24.0),
+                    RotateStrategy::Auto(speed),
+                    SlideStrategy::NoSlide,
+                );
+            }
+            Template::Lever => {
+                block.block_shape_info = BlockShapeInfo::Rect {
+                    extents: Vec2::new(160.0, 30.0),
+                    rect_origin: Vec2::new(70.0, 0.0),
+                    rotate_strategy: RotateStrategy::Manual(0.04 * sign),
+                    slide_strategy: SlideStrategy::NoSlide,
+                };
+                block.default_angle = (rng.range(0, 3) as f32) * FRAC_PI_2;
+            }
+            Template::Slider => {
+                let path = BlockSlidePath::StandardLine {
+                    theta: if rng.range(0, 1) == 0 { 0.0 } else { FRAC_PI_2 },
+                    width: rng.range(50, 100) as f32,
+                };
+                let slide_strategy = if rng.range(0, 1) == 0 {
+                    SlideStrategy::Auto { speed: 0.01, path }
+                } else {
+                    SlideStrategy::Manual { speed: 0.05, path }
+                };
+                block.block_shape_info = rect(
+                    Vec2::new(rng.range(60, 100) as f32, rng.range(60, 100) as f32),
+                    RotateStrategy::NoRotate,
+                    slide_strategy,
+                );
+            }
+            Template::FanBlock => {
+                block.block_shape_info = rect(
+                    Vec2::new(140.0, 50.0),
+                    RotateStrategy::NoRotate,
+                    SlideStrategy::NoSlide,
+                );
+                // 上向きか下向き
+                let direction = rng.range(0, 1) as u32;
+                block.block_attachment = vec![BlockAttachment::Fan(Fan::new(true, direction, 0.1))];
+            }
+            Template::MagnetBlock => {
+                block.block_shape_info = rect(
+                    Vec2::new(140.0, 60.0),
+                    RotateStrategy::NoRotate,
+                    SlideStrategy::NoSlide,
+                );
+                let direction = rng.range(0, 1) as u32;
+                block.block_attachment =
+                    vec![BlockAttachment::Magnet(Magnet::new(true, direction, 14.0))];
+            }
+        }
+        block
+    }
+}
+
+/// 下向きの重力
+fn downward_gravity(_: Vec2) -> Vec2 {
+    Vec2::new(0.0, -0.5)
+}
+
+/// フィールドの中心に引き寄せる重力
+fn central_gravity(pos: Vec2) -> Vec2 {
+    -0.0005 * pos
+}
+
+/// 生成したステージの名前. この名前からいつでも同じステージを作り直せる
+pub fn generated_title(seed: u64, difficulty: u32) -> String {
+    format!("random[{}:{}]", seed, difficulty)
+}
+
+/// `random[種:難易度]`の形の名前から種と難易度を取り出す
+pub fn parse_generated_title(name: &str) -> Option<(u64, u32)> {
+    let (seed, difficulty) = name
+        .strip_prefix("random[")?
+        .strip_suffix(']')?
+        .split_once(':')?;
+    Some((seed.parse().ok()?, difficulty.parse().ok()?))
+}
+
+/// 種と難易度からステージを作る. 同じ種と難易度なら必ず同じステージになるが, 解けるとは限らない
+pub fn generate_stage(seed: u64, difficulty: u32) -> StageInfo {
+    let difficulty = difficulty.clamp(1, MAX_DIFFICULTY);
+    // 小さな種でも最初から散らばった値が出るように混ぜる
+    let mut rng = Rng::new(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ difficulty as u64);
+    let half = Vec2::new(FIELD_WIDTH, FIELD_HEIGHT) / 2.0;
+
+    let launcher = LauncherInfo {
+        pos: Vec2::new(-half.x + 30.0, rng.range(-200, 200) as f32),
+        ..Default::default()
+    };
+
+    let goal_pos = Vec2::new(rng.range(250, 430) as f32, rng.range(-220, 220) as f32);
+    // 最高難易度ではゴールが上下に動くことがある
+    let goal_slide = if difficulty >= 3 && rng.range(0, 2) == 0 {
+        SlideStrategy::Auto {
+            speed: 0.01,
+            path: BlockSlidePath::StandardLine {
+                theta: FRAC_PI_2,
+                width: 60.0,
+            },
+        }
+    } else {
+        SlideStrategy::NoSlide
+    };
+    let goal = GoalInfo {
+        pos: goal_pos,
+        radius: 45.0 - 8.0 * difficulty as f32,
+        score: 1,
+        slide_strategy: goal_slide,
+        ..Default::default()
+    };
+
+    let templates = TEMPLATES
+        .into_iter()
+        .filter(|template| template.min_difficulty() <= difficulty)
+        .collect::<Vec<Template>>();
+    let mut blocks = vec![];
+    let mut uses_magnet = false;
+    for _ in 0..(1 + 2 * difficulty) {
+        // 他のものと重ならない位置が見つからなければそのブロックは置かない
+        let pos = (0..20)
+            .map(|_| Vec2::new(rng.range(-330, 220) as f32, rng.range(-210, 210) as f32))
+            .find(|pos| {
+                pos.distance(launcher.pos) > BLOCK_SPACING + 20.0
+                    && pos.distance(goal_pos) > BLOCK_SPACING
+                    && blocks
+                        .iter()
+                        .all(|block: &BlockInfo| pos.distance(block.pos) > BLOCK_SPACING)
+            });
+        if let Some(pos) = pos {
+            let template = templates[rng.range(0, templates.len() as i32 - 1) as usize];
+            uses_magnet |= matches!(template, Template::MagnetBlock);
+            blocks.push(template.block(pos, &mut rng));
+        }
+    }
+
+    // 難易度2からは重力がかかることがある
+    let gravity = match rng.range(0, 2) {
+        1 if difficulty >= 2 => Gravity::new_as_some(downward_gravity),
+        2 if difficulty >= 2 => Gravity::new_as_some(central_gravity),
+        _ => None,
+    };
+
+    let mut balls = Vec::<BallInfo>::new();
+    balls.set_balls(BallType::Normal, 2 + difficulty);
+    if uses_magnet {
+        balls.set_balls(BallType::Metal, 1);
+    }
+
+    StageInfo {
+        // 生成したステージの数だけなので, 名前は確保したままにする
+        stage_title: intern_title(&generated_title(seed, difficulty)),
+        time: 45 * 60,
+        launcher,
+        blocks: field_block().into_iter().chain(blocks).collect(),
+        balls,
+        goal_pos: vec![goal],
+        // 重力や磁石があると止まったまま残るボールが出やすい
+        auto_retire: gravity.is_some() || uses_magnet,
+        gravity,
+        rule: Some(GameRule::Strokes),
+        ..Default::default()
+    }
+}
+
+/// 解けるステージを作る. 解けなければ種を一つずつずらして作り直し, 見つからなければNoneを返す.
+/// 基準打数はソルバーが見つけた解のうち最も少ない打数にする. シミュレーションもソルバーの乱数も決まっているので,
+/// 同じ種と難易度なら毎回同じステージが選ばれる
+pub fn generate_solvable_stage(seed: u64, difficulty: u32) -> Option<StageInfo> {
+    (0..ATTEMPTS).find_map(|attempt| {
+        let mut stage = generate_stage(seed.wrapping_add(attempt), difficulty);
+        let outcome = find_solution(&stage, CHECK_BUDGET)?;
+        stage.par = outcome.strokes;
+        Some(stage)
+    })
+}
+
+/// デイリーチャレンジに選んだステージを覚えておくファイル
+const DAILY_FILE: &str = "save/daily.ron";
+
+/// ある日のデイリーチャレンジ. 解けるステージが見つからなかった日は`stage`がNoneになる
+#[derive(Default, Serialize, Deserialize)]
+struct DailyRecord {
+    date: String,
+    stage: Option<DailyStage>,
+}
+
+/// デイリーチャレンジに選んだステージの種と基準打数
+#[derive(Serialize, Deserialize)]
+struct DailyStage {
+    seed: u64,
+    par: u32,
+}
+
+/// 今日のデイリーチャレンジのステージ. 解けるか確かめるのは一日一回だけで,
+/// 同じ日にもう一度選ばれたら保存しておいた種と基準打数から作り直す
+pub fn daily_stage() -> Option<StageInfo> {
+    let (date, seed) = daily_seed();
+    let record = load_ron_file::<DailyRecord>(DAILY_FILE);
+    if record.date == date {
+        return record.stage.map(|daily| StageInfo {
+            par: daily.par,
+            ..generate_stage(daily.seed, DAILY_DIFFICULTY)
+        });
+    }
+    let stage = generate_solvable_stage(seed, DAILY_DIFFICULTY);
+    let daily = stage.as_ref().and_then(|stage| {
+        let (seed, _) = parse_generated_title(stage.stage_title)?;
+        Some(DailyStage {
+            seed,
+            par: stage.par,
+        })
+    });
+    save_ron_file(DAILY_FILE, &DailyRecord { date, stage: daily });
+    stage
+}
+
+/// 1970年1月1日からの日数を年月日に直す
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// 今日（UTC）のデイリーチャレンジの日付と種. 種は日付をYYYYMMDDの形の数にしたもの
+pub fn daily_seed() -> (String, u64) {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    daily_seed_of(days)
+}
+
+/// 1970年1月1日からの日数の日のデイリーチャレンジの日付と種
+fn daily_seed_of(days: i64) -> (String, u64) {
+    let (year, month, day) = civil_from_days(days);
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        (year * 10_000 + month as i64 * 100 + day as i64) as u64,
+    )
+}
+
+/// `generate`サブコマンド. `generate [SEED] [--difficulty N]`
+/// 解けるステージを作り, その名前と基準打数を表示する. 種を省略すると今日のデイリーチャレンジを作る.
+/// 表示された名前は`solve`や`svg`などのステージ名として使える.
+pub fn run_generate_command(args: &[String]) -> i32 {
+    let (_, mut seed) = daily_seed();
+    let mut difficulty = DAILY_DIFFICULTY;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            "--difficulty" => iter
+                .next()
+                .and_then(|value| value.parse::<u32>().ok())
+                .map(|value| difficulty = value),
+            value => value.parse::<u64>().ok().map(|value| seed = value),
+        };
+        if parsed.is_none() {
+            eprintln!("usage: generate [SEED] [--difficulty 1-{}]", MAX_DIFFICULTY);
+            return 2;
+        }
+    }
+    match generate_solvable_stage(seed, difficulty) {
+        Some(stage) => {
+            println!("{}: par {}", stage.stage_title, stage.par);
+            0
+        }
+        None => {
+            eprintln!("no solvable stage found from seed {}", seed);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_title_parses_back() {
+        let title = generated_title(20240229, 2);
+        assert_eq!(parse_generated_title(&title), Some((20240229, 2)));
+        assert_eq!(parse_generated_title("random[1:x]"), None);
+        assert_eq!(parse_generated_title("random[1]"), None);
+        assert_eq!(parse_generated_title("stage[1:2]"), None);
+    }
+
+    #[test]
+    fn days_convert_to_civil_date() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+        assert_eq!(
+            daily_seed_of(19_782),
+            ("2024-02-29".to_string(), 20_240_229)
+        );
+    }
+
+    #[test]
+    fn same_seed_generates_same_stage() {
+        for difficulty in 1..=MAX_DIFFICULTY {
+            let first = generate_stage(12_345, difficulty);
+            let second = generate_stage(12_345, difficulty);
+            assert_eq!(first.stage_title, second.stage_title);
+            assert_eq!(
+                format!("{:?}", first.launcher),
+                format!("{:?}", second.launcher)
+            );
+            assert_eq!(
+                format!("{:?}", first.blocks),
+                format!("{:?}", second.blocks)
+            );
+            assert_eq!(first.goal_pos[0].pos, second.goal_pos[0].pos);
+            assert_eq!(first.balls.len(), second.balls.len());
+            assert_eq!(first.gravity.is_some(), second.gravity.is_some());
+        }
+        let other = generate_stage(12_346, 2);
+        assert_ne!(
+            format!("{:?}", generate_stage(12_345, 2).blocks),
+            format!("{:?}", other.blocks)
+        );
+    }
+}
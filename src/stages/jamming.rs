@@ -56,6 +56,7 @@ pub fn jamming1() -> StageInfo {
                 default_angle: (i * j) as f32,
                 default_pos_param: 0.0,
                 block_attachment,
+                control_group: 0,
             })
         }
     }
@@ -65,6 +66,7 @@ pub fn jamming1() -> StageInfo {
         rotate_speed: 0.05,
         min_angle: -FRAC_PI_4,
         max_angle: FRAC_PI_4,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -75,11 +77,13 @@ pub fn jamming1() -> StageInfo {
             pos: Vec2::new(-120.0, 0.0),
             radius: 30.0,
             score: 1,
+            ..Default::default()
         },
         GoalInfo {
             pos: Vec2::new(340.0, 0.0),
             radius: 50.0,
             score: 3,
+            ..Default::default()
         },
     ];
 
@@ -110,5 +114,8 @@ pub fn jamming1() -> StageInfo {
         goal_pos: goal_list,
         switches,
         gravity: None,
+        // ブロックの間に挟まって動けなくなったボールを取り除く
+        auto_retire: true,
+        ..Default::default()
     }
 }
@@ -0,0 +1,195 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use super::field_blocks::field_block;
+use super::structs::{
+    ArrangeBallInfo, BallInfo, BlockInfo, BlockShapeInfo, GoalInfo, LauncherInfo, StageInfo,
+    SwitchInfo,
+};
+use crate::components::{
+    ball::BallType,
+    block::{BlockSlidePath, RotateStrategy, SlideStrategy},
+    game::{GameRule, WinCondition},
+};
+use crate::systems::field::{FIELD_HEIGHT, FIELD_WIDTH};
+
+/// スイッチをすべて押してからBのゴールに入れるステージ
+pub fn switch_mission() -> StageInfo {
+    let block_list = vec![BlockInfo {
+        pos: Vec2::new(0.0, 0.0),
+        block_shape_info: BlockShapeInfo::Rect {
+            extents: Vec2::new(200.0, 30.0),
+            rect_origin: Vec2::ZERO,
+            rotate_strategy: RotateStrategy::Manual(0.04),
+            slide_strategy: SlideStrategy::NoSlide,
+        },
+        ..Default::default()
+    }];
+
+    let launcher_info = LauncherInfo::default();
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 4);
+
+    let goal_list = vec![
+        GoalInfo {
+            pos: Vec2::new(FIELD_WIDTH / 2.0 - 60.0, FIELD_HEIGHT / 2.0 - 60.0),
+            radius: 40.0,
+            score: 1,
+            name: Some("A"),
+            ..Default::default()
+        },
+        GoalInfo {
+            pos: Vec2::new(FIELD_WIDTH / 2.0 - 60.0, -FIELD_HEIGHT / 2.0 + 60.0),
+            radius: 40.0,
+            score: 1,
+            name: Some("B"),
+            ..Default::default()
+        },
+    ];
+
+    let switches = vec![
+        SwitchInfo {
+            target_id: 0,
+            pos: Vec2::new(200.0, FIELD_HEIGHT / 2.0 - 20.0),
+            ..Default::default()
+        },
+        SwitchInfo {
+            target_id: 0,
+            pos: Vec2::new(200.0, -FIELD_HEIGHT / 2.0 + 20.0),
+            ..Default::default()
+        },
+    ];
+
+    StageInfo {
+        stage_title: "mission[switch]",
+        time: 60 * 60,
+        launcher: launcher_info,
+        blocks: field_block()
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        switches,
+        rule: Some(GameRule::BallScore),
+        win_conditions: vec![WinCondition::HitAllSwitches, WinCondition::ReachGoal("B")],
+        ..Default::default()
+    }
+}
+
+/// ブロックに触れずに長く飛ばし, 得点を稼ぐステージ
+pub fn airborne_mission() -> StageInfo {
+    let block_list = vec![BlockInfo {
+        pos: Vec2::new(100.0, 0.0),
+        block_shape_info: BlockShapeInfo::Rect {
+            extents: Vec2::new(30.0, 160.0),
+            rect_origin: Vec2::ZERO,
+            rotate_strategy: RotateStrategy::Manual(0.04),
+            slide_strategy: SlideStrategy::NoSlide,
+        },
+        ..Default::default()
+    }];
+
+    let launcher_info = LauncherInfo {
+        pos: Vec2::new(-FIELD_WIDTH / 2.0 + 30.0, -FIELD_HEIGHT / 2.0 + 30.0),
+        min_angle: 0.0,
+        ..Default::default()
+    };
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 3);
+
+    let goal_list = vec![
+        GoalInfo {
+            pos: Vec2::new(-FIELD_WIDTH / 2.0 + 60.0, FIELD_HEIGHT / 2.0 - 60.0),
+            radius: 40.0,
+            score: 1,
+            ..Default::default()
+        },
+        GoalInfo {
+            pos: Vec2::new(FIELD_WIDTH / 2.0 - 60.0, FIELD_HEIGHT / 2.0 - 60.0),
+            radius: 40.0,
+            score: 2,
+            ..Default::default()
+        },
+    ];
+
+    StageInfo {
+        stage_title: "mission[airborne]",
+        time: 60 * 60,
+        launcher: launcher_info,
+        blocks: field_block()
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        win_conditions: vec![
+            WinCondition::KeepAirborne(3 * 60),
+            WinCondition::ScoreAtLeast(2),
+        ],
+        ..Default::default()
+    }
+}
+
+/// 操作グループを切り替えながら, 上下に動く壁の隙間を回転する板で狙うステージ
+pub fn group_mission() -> StageInfo {
+    let block_list = vec![
+        BlockInfo {
+            pos: Vec2::new(0.0, 0.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(30.0, 300.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::NoRotate,
+                slide_strategy: SlideStrategy::Manual {
+                    speed: 0.02,
+                    path: BlockSlidePath::StandardLine {
+                        theta: FRAC_PI_2,
+                        width: 400.0,
+                    },
+                },
+            },
+            control_group: 1,
+            ..Default::default()
+        },
+        BlockInfo {
+            pos: Vec2::new(300.0, 0.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(160.0, 30.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::Manual(0.04),
+                slide_strategy: SlideStrategy::NoSlide,
+            },
+            control_group: 2,
+            ..Default::default()
+        },
+    ];
+
+    let launcher_info = LauncherInfo::default();
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 4);
+
+    let goal_list = vec![GoalInfo {
+        pos: Vec2::new(FIELD_WIDTH / 2.0 - 60.0, FIELD_HEIGHT / 2.0 - 60.0),
+        radius: 40.0,
+        score: 1,
+        ..Default::default()
+    }];
+
+    StageInfo {
+        stage_title: "mission[group]",
+        time: 60 * 60,
+        launcher: launcher_info,
+        blocks: field_block()
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        grouped_control: true,
+        ..Default::default()
+    }
+}
@@ -1,53 +1,190 @@
 use self::{
     aisle::{aisle0, aisle1, aisle2},
+    debug::debug_stage,
     jamming::jamming1,
+    mission::{airborne_mission, group_mission, switch_mission},
     planet::{square_planet, strange_gravity},
+    sample::sample_stage,
     structs::StageInfo,
     tutorial_stage::{
-        fan_tutorial, gravity_tutorial, magnet_tutorial, switch_tutorial, tutorial1, tutorial2,
+        fan_tutorial, goal_tutorial, gravity_tutorial, hazard_tutorial, item_tutorial,
+        magnet_tutorial, switch_tutorial, time_tutorial, tutorial1, tutorial2,
     },
+    wide::wide_field,
 };
+use crate::components::progress::CampaignProgress;
 use itertools::Itertools;
+use std::sync::Mutex;
 
+pub mod file;
+pub mod generate;
+pub mod solve;
 pub mod structs;
+pub mod svg;
+pub mod validate;
 
 mod aisle;
 mod debug;
 mod field_blocks;
 mod jamming;
+mod mission;
 mod planet;
 mod sample;
 mod tutorial_stage;
+mod wide;
 
-type GenerateStageInfoFunc = fn() -> StageInfo;
+pub type GenerateStageInfoFunc = fn() -> StageInfo;
 
-fn stage_vec() -> Vec<GenerateStageInfoFunc> {
+/// ステージやワールドの解放条件
+pub enum UnlockCondition {
+    Always,
+    /// 指定したステージをクリアする
+    Clear(&'static str),
+    /// 星を合計でこの数だけ集める
+    Stars(u32),
+}
+impl UnlockCondition {
+    pub fn is_satisfied(&self, progress: &CampaignProgress) -> bool {
+        match *self {
+            UnlockCondition::Always => true,
+            UnlockCondition::Clear(stage_title) => progress.is_cleared(stage_title),
+            UnlockCondition::Stars(stars) => progress.total_stars() >= stars,
+        }
+    }
+    /// メニューに表示する解放条件の説明
+    pub fn requirement_text(&self) -> String {
+        match *self {
+            UnlockCondition::Always => String::new(),
+            UnlockCondition::Clear(stage_title) => format!("clear {}", stage_title),
+            UnlockCondition::Stars(stars) => format!("{} stars", stars),
+        }
+    }
+}
+
+/// 順番に遊ぶステージをまとめたもの
+pub struct CampaignWorld {
+    pub title: &'static str,
+    pub unlock: UnlockCondition,
+    pub stages: Vec<GenerateStageInfoFunc>,
+}
+impl CampaignWorld {
+    /// ワールド内のidx番目のステージの解放条件. 最初のステージ以外は直前のステージのクリアが条件となる
+    pub fn stage_unlock_condition(&self, idx: usize) -> UnlockCondition {
+        if idx == 0 {
+            UnlockCondition::Always
+        } else {
+            UnlockCondition::Clear(self.stages[idx - 1]().stage_title)
+        }
+    }
+}
+
+pub fn campaign() -> Vec<CampaignWorld> {
     vec![
-        tutorial1,
-        tutorial2,
-        fan_tutorial,
-        magnet_tutorial,
-        switch_tutorial,
-        gravity_tutorial,
-        // sample_stage,
-        aisle0,
-        aisle1,
-        aisle2,
-        jamming1,
-        // debug_stage,
-        strange_gravity,
-        square_planet,
+        CampaignWorld {
+            title: "tutorial",
+            unlock: UnlockCondition::Always,
+            stages: vec![
+                tutorial1,
+                tutorial2,
+                fan_tutorial,
+                magnet_tutorial,
+                switch_tutorial,
+                gravity_tutorial,
+                goal_tutorial,
+                item_tutorial,
+                hazard_tutorial,
+                time_tutorial,
+            ],
+        },
+        CampaignWorld {
+            title: "aisle",
+            unlock: UnlockCondition::Clear("tutorial[2]"),
+            stages: vec![aisle0, aisle1, aisle2],
+        },
+        CampaignWorld {
+            title: "mission",
+            unlock: UnlockCondition::Clear("tutorial[switch]"),
+            stages: vec![switch_mission, airborne_mission, group_mission],
+        },
+        CampaignWorld {
+            title: "challenge",
+            unlock: UnlockCondition::Stars(12),
+            // sample_stage, debug_stage はここに入れていない
+            stages: vec![jamming1, strange_gravity, square_planet, wide_field],
+        },
     ]
 }
 
-// NOTE: ステージ名をキーにした辞書形式で持つほうが楽だし自然かもしれない
-pub fn stage_title_vec() -> Vec<&'static str> {
-    stage_vec()
+/// 検査などのためのすべてのステージ. キャンペーンに入っていないものも含む
+pub fn all_stages() -> Vec<GenerateStageInfoFunc> {
+    let mut stages = campaign()
         .into_iter()
-        .map(|generator| generator().stage_title)
-        .collect_vec()
+        .flat_map(|world| world.stages)
+        .collect_vec();
+    stages.extend([sample_stage as GenerateStageInfoFunc, debug_stage]);
+    stages
+}
+
+/// ステージ名かステージファイルのパス, または生成したステージの名前からステージ情報を作る. コマンドラインのツールで使う
+pub fn find_stage(name: &str) -> Option<StageInfo> {
+    if name.ends_with(".ron") {
+        return file::StageFile::load(name).map(|stage| stage.to_stage_info());
+    }
+    if let Some((seed, difficulty)) = generate::parse_generated_title(name) {
+        return Some(generate::generate_stage(seed, difficulty));
+    }
+    all_stages()
+        .into_iter()
+        .map(|generator| generator())
+        .find(|stage| stage.stage_title == name)
+}
+
+/// ステージ名（またはステージファイルのパス）を出力ファイルの名前に使える形にする
+pub fn stage_file_name(name: &str) -> String {
+    std::path::Path::new(name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(name)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// 読み込んだり作ったりしたステージの名前を`&'static str`にする.
+/// 名前ごとに一度だけ確保し, 同じ名前のステージを何度作っても使い回す
+pub fn intern_title(title: &str) -> &'static str {
+    static TITLES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+    let mut titles = TITLES.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(interned) = titles.iter().find(|interned| **interned == title) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(title.to_string().into_boxed_str());
+    titles.push(interned);
+    interned
 }
 
-pub fn select_stage(idx: usize) -> StageInfo {
-    stage_vec()[idx]()
+/// 続けて遊ぶステージの組
+pub struct Course {
+    pub title: &'static str,
+    pub stages: Vec<GenerateStageInfoFunc>,
+}
+
+pub fn course_vec() -> Vec<Course> {
+    vec![
+        Course {
+            title: "tutorial course",
+            stages: vec![tutorial1, tutorial2, fan_tutorial, magnet_tutorial],
+        },
+        Course {
+            title: "aisle course",
+            stages: vec![aisle0, aisle1, aisle2],
+        },
+    ]
+}
+
+pub fn course_title_vec() -> Vec<&'static str> {
+    course_vec()
+        .into_iter()
+        .map(|course| course.title)
+        .collect_vec()
 }
@@ -11,6 +11,7 @@ use crate::components::physics::material::PhysicMaterial;
 use crate::components::{
     ball::BallType,
     block::{RotateStrategy, SlideStrategy},
+    input::ControlCurve,
 };
 use crate::systems::field::FIELD_WIDTH;
 
@@ -23,6 +24,7 @@ pub fn strange_gravity() -> StageInfo {
         rotate_speed: 0.0,
         min_angle: 0.0,
         max_angle: 0.0,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -33,6 +35,7 @@ pub fn strange_gravity() -> StageInfo {
         pos: Vec2::ZERO,
         radius: 20.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
@@ -47,6 +50,15 @@ pub fn strange_gravity() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: Gravity::new_as_some(|pos: Vec2| -0.001 * pos),
+        // 中心の近くで止まったボールを取り除く
+        auto_retire: true,
+        // 発射角度だけで決まるので, ゆっくり加速させて狙いやすくする
+        control_curve: Some(ControlCurve {
+            start_ratio: 0.1,
+            ramp_frames: 60,
+            ..Default::default()
+        }),
+        ..Default::default()
     }
 }
 
@@ -92,6 +104,7 @@ pub fn square_planet() -> StageInfo {
         rotate_speed: 0.05,
         min_angle: -FRAC_PI_4,
         max_angle: FRAC_PI_4,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -103,11 +116,13 @@ pub fn square_planet() -> StageInfo {
             pos: Vec2::new(FIELD_WIDTH / 2.0 - 30.0, 0.0),
             radius: 30.0,
             score: 2,
+            ..Default::default()
         },
         GoalInfo {
             pos: Vec2::new(FIELD_WIDTH / 2.0 - 340.0, 0.0),
             radius: 30.0,
             score: 1,
+            ..Default::default()
         },
     ];
 
@@ -123,5 +138,8 @@ pub fn square_planet() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: Gravity::new_as_some(|pos: Vec2| -0.0005 * pos),
+        // 惑星の表面で止まったボールを取り除く
+        auto_retire: true,
+        ..Default::default()
     }
 }
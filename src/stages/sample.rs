@@ -34,6 +34,7 @@ pub fn sample_stage() -> StageInfo {
             default_angle: 2.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
             pos: Vec2::ZERO,
@@ -47,6 +48,7 @@ pub fn sample_stage() -> StageInfo {
             default_angle: 0.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
             pos: Vec2::new(200.0, 50.0),
@@ -60,6 +62,7 @@ pub fn sample_stage() -> StageInfo {
             default_angle: 1.0,
             default_pos_param: 0.0,
             block_attachment: vec![],
+            control_group: 0,
         },
         BlockInfo {
             pos: Vec2::new(300.0, -160.0),
@@ -79,6 +82,7 @@ pub fn sample_stage() -> StageInfo {
             default_angle: -FRAC_PI_2,
             default_pos_param: -1.0,
             block_attachment: vec![],
+            control_group: 0,
         },
     ];
 
@@ -88,6 +92,7 @@ pub fn sample_stage() -> StageInfo {
         rotate_speed: 0.02,
         min_angle: FRAC_PI_2 * -0.2,
         max_angle: FRAC_PI_2 * 1.2,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -97,6 +102,7 @@ pub fn sample_stage() -> StageInfo {
         pos: Vec2::new(350.0, 150.0),
         radius: 30.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
@@ -111,5 +117,6 @@ pub fn sample_stage() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
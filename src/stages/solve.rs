@@ -0,0 +1,384 @@
+//! ステージを自動で解くソルバー. 画面を出さずにステージを動かし, ゴールに届く操作を探す.
+//! ランチャーの向きを格子状に調べた後, よかったものを少しずつ変えながら絞り込む（ビームサーチ）.
+use std::path::Path;
+
+use super::{find_stage, stage_file_name, structs::StageInfo};
+use crate::{
+    components::{input::InputAction, replay::InputScript},
+    systems::{
+        simulate::{simulate, SimulationOutcome},
+        utils::{load_ron_file, save_ron_file, Rng},
+    },
+};
+
+/// 見つけた解を保存するディレクトリ
+const REPLAY_DIR: &str = "save/replays";
+/// ビームサーチで残す候補の数
+const BEAM_WIDTH: usize = 6;
+/// 一つの候補から作る変化させた候補の数
+const MUTATIONS: usize = 4;
+/// ボールを番えてから発射するまで, 発射してから次の操作までに置くフレーム数
+const NOCK_FRAMES: u32 = 2;
+
+/// 解を比べるときに何を少なくするか. どちらの場合も打数が少ないものを優先する
+#[derive(Clone, Copy, Debug)]
+enum Objective {
+    Time,
+    Operations,
+}
+
+/// ボール一つ分の操作
+#[derive(Clone, Debug)]
+pub(super) struct Shot {
+    /// 番える前にランチャーを回すフレーム数. 正なら左回り, 負なら右回り
+    aim: i32,
+    /// 番えてから発射するまでに待つフレーム数（動くブロックに合わせるため）
+    delay: u32,
+    /// 発射した後の矢印キーの操作（向き, フレーム数）. 向きは正なら左, 負なら右, 0なら何もしない
+    steer: Vec<(i32, u32)>,
+}
+impl Shot {
+    pub(super) fn aimed(aim: i32) -> Self {
+        Self {
+            aim,
+            delay: 0,
+            steer: vec![],
+        }
+    }
+}
+
+fn rotate_actions(direction: i32) -> Vec<InputAction> {
+    match direction.signum() {
+        1 => vec![InputAction::RotateLeft],
+        -1 => vec![InputAction::RotateRight],
+        _ => vec![],
+    }
+}
+
+/// ボールごとの操作を1フレームずつの操作の記録に直す
+pub(super) fn build_script(stage: &str, shots: &[Shot]) -> InputScript {
+    let mut script = InputScript {
+        stage: stage.to_string(),
+        segments: vec![],
+        expected: None,
+    };
+    for shot in shots {
+        script.push(shot.aim.unsigned_abs(), rotate_actions(shot.aim));
+        // 短く押して離すと番え, もう一度押すと発射する
+        script.push(1, vec![InputAction::Action]);
+        script.push(NOCK_FRAMES + shot.delay, vec![]);
+        script.push(1, vec![InputAction::Action]);
+        script.push(NOCK_FRAMES, vec![]);
+        for &(direction, frames) in shot.steer.iter() {
+            script.push(frames, rotate_actions(direction));
+        }
+    }
+    script
+}
+
+/// 候補の評価. 小さいほどよい. クリアしたものは打数, 目的の値の順に比べ, しなかったものはゴールへの近さで比べる
+fn cost(outcome: &SimulationOutcome, objective: Objective) -> f64 {
+    if outcome.cleared {
+        let value = match objective {
+            Objective::Time => outcome.frames,
+            Objective::Operations => outcome.operations,
+        };
+        outcome.strokes as f64 * 1e6 + value as f64
+    } else {
+        1e12 + outcome.closest as f64
+    }
+}
+
+struct Candidate {
+    shots: Vec<Shot>,
+    outcome: SimulationOutcome,
+    cost: f64,
+}
+
+struct Solver<'a> {
+    stage_name: &'a str,
+    stage_info: &'a StageInfo,
+    objective: Objective,
+    evaluations: u32,
+    /// trueなら解けるかと最少打数だけを確かめる. 1打でクリアできた時点で止め, 途中経過を表示しない
+    check_only: bool,
+}
+impl<'a> Solver<'a> {
+    /// これより少ない打数の解はないので探索をやめてよいか
+    fn is_done(&self, candidate: &Candidate) -> bool {
+        self.check_only && candidate.outcome.cleared && candidate.outcome.strokes <= 1
+    }
+
+    fn evaluate(&mut self, shots: Vec<Shot>) -> Candidate {
+        self.evaluations += 1;
+        let outcome = simulate(self.stage_info, &build_script(self.stage_name, &shots));
+        Candidate {
+            cost: cost(&outcome, self.objective),
+            shots,
+            outcome,
+        }
+    }
+
+    /// 候補を少し変える. 向き, 発射の待ち時間, 発射後の操作のどれかを変え, ボールが余っていれば次のボールを足すこともある
+    fn mutate(&self, shots: &[Shot], rng: &mut Rng) -> Vec<Shot> {
+        let mut shots = shots.to_vec();
+        let balls = self.stage_info.balls.len();
+        if shots.len() < balls && rng.range(0, 5) == 0 {
+            let mut next = shots[shots.len() - 1].clone();
+            next.aim = rng.range(-20, 20);
+            next.steer = vec![(0, rng.range(30, 180) as u32)];
+            // 前のボールが落ち着くまで待ってから次を打つ
+            shots.last_mut().unwrap().steer.push((0, 60));
+            shots.push(next);
+            return shots;
+        }
+        let idx = rng.range(0, shots.len() as i32 - 1) as usize;
+        let shot = &mut shots[idx];
+        match rng.range(0, 3) {
+            0 => shot.aim += rng.range(-8, 8),
+            1 => shot.delay = (shot.delay as i32 + rng.range(-20, 20)).max(0) as u32,
+            2 if !shot.steer.is_empty() => {
+                let step = rng.range(0, shot.steer.len() as i32 - 1) as usize;
+                let (direction, frames) = &mut shot.steer[step];
+                if rng.range(0, 1) == 0 {
+                    *direction = rng.range(-1, 1);
+                } else {
+                    *frames = (*frames as i32 + rng.range(-15, 15)).max(1) as u32;
+                }
+            }
+            _ => shot.steer.push((rng.range(-1, 1), rng.range(5, 60) as u32)),
+        }
+        shots
+    }
+
+    /// 探索する. 評価回数の上限に達するまで続ける
+    fn solve(&mut self, budget: u32, seed: u64) -> Candidate {
+        let launcher = &self.stage_info.launcher;
+        // ランチャーが端から端まで回る程度のフレーム数. 押し始めは遅いのでその分を足す
+        let aim_limit =
+            ((launcher.max_angle - launcher.min_angle) / launcher.rotate_speed) as i32 + 30;
+        let aim_step = (aim_limit / 24).max(1);
+        let mut beam = vec![];
+        for aim in (-aim_limit..=aim_limit).step_by(aim_step as usize) {
+            let candidate = self.evaluate(vec![Shot::aimed(aim)]);
+            if self.is_done(&candidate) {
+                return candidate;
+            }
+            beam.push(candidate);
+        }
+        beam.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+        beam.truncate(BEAM_WIDTH);
+        let mut rng = Rng::new(seed);
+        let mut generation = 0;
+        while self.evaluations < budget {
+            generation += 1;
+            let mut children = vec![];
+            for candidate in beam.iter() {
+                for _ in 0..MUTATIONS {
+                    children.push(self.mutate(&candidate.shots, &mut rng));
+                }
+            }
+            beam.extend(children.into_iter().map(|shots| self.evaluate(shots)));
+            beam.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+            beam.truncate(BEAM_WIDTH);
+            let best = &beam[0];
+            if self.is_done(best) {
+                break;
+            }
+            if self.check_only {
+                continue;
+            }
+            println!(
+                "generation {}: {} evaluations, best: {}",
+                generation,
+                self.evaluations,
+                describe(&best.outcome)
+            );
+        }
+        beam.swap_remove(0)
+    }
+}
+
+/// ステージが解けるか確かめる. 解けたら見つけた解のうち最も打数の少ないものの結果を返す
+pub(super) fn find_solution(stage_info: &StageInfo, budget: u32) -> Option<SimulationOutcome> {
+    let mut solver = Solver {
+        stage_name: stage_info.stage_title,
+        stage_info,
+        objective: Objective::Time,
+        evaluations: 0,
+        check_only: true,
+    };
+    let best = solver.solve(budget, 1);
+    best.outcome.cleared.then_some(best.outcome)
+}
+
+fn describe(outcome: &SimulationOutcome) -> String {
+    if outcome.cleared {
+        format!(
+            "cleared in {:.2}s with {} operations and {} strokes",
+            outcome.frames as f32 / 60.0,
+            outcome.operations,
+            outcome.strokes
+        )
+    } else {
+        format!("not cleared (closest {:.1})", outcome.closest)
+    }
+}
+
+/// `solve`サブコマンド. `solve STAGE [--objective time|operations] [--budget N] [--seed N]`
+/// STAGEはステージ名またはステージファイルのパス. 解が見つかればリプレイとして保存し0を返す.
+pub fn run_solve_command(args: &[String]) -> i32 {
+    let mut stage_name = None;
+    let mut objective = Objective::Time;
+    let mut budget = 300;
+    let mut seed = 1;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--objective" => match iter.next().map(String::as_str) {
+                Some("time") => objective = Objective::Time,
+                Some("operations") => objective = Objective::Operations,
+                _ => {
+                    eprintln!("--objective must be time or operations");
+                    return 2;
+                }
+            },
+            "--budget" | "--seed" => {
+                let value = iter.next();
+                let parsed = match arg.as_str() {
+                    "--budget" => value
+                        .and_then(|value| value.parse::<u32>().ok())
+                        .map(|value| budget = value),
+                    _ => value
+                        .and_then(|value| value.parse::<u64>().ok())
+                        .map(|value| seed = value),
+                };
+                if parsed.is_none() {
+                    eprintln!("{} needs a number", arg);
+                    return 2;
+                }
+            }
+            name => stage_name = Some(name.to_string()),
+        }
+    }
+    let stage_name = if let Some(name) = stage_name {
+        name
+    } else {
+        eprintln!("usage: solve STAGE [--objective time|operations] [--budget N] [--seed N]");
+        return 2;
+    };
+    let stage_info = if let Some(stage_info) = find_stage(&stage_name) {
+        stage_info
+    } else {
+        eprintln!("{}: stage not found", stage_name);
+        return 2;
+    };
+    let mut solver = Solver {
+        stage_name: &stage_name,
+        stage_info: &stage_info,
+        objective,
+        evaluations: 0,
+        check_only: false,
+    };
+    let best = solver.solve(budget, seed);
+    println!("{}: {}", stage_info.stage_title, describe(&best.outcome));
+    if !best.outcome.cleared {
+        return 1;
+    }
+    println!(
+        "suggested par: {} (current {})",
+        best.outcome.strokes, stage_info.par
+    );
+    let path = format!("{}/{}.ron", REPLAY_DIR, stage_file_name(&stage_name));
+    let mut script = build_script(&stage_name, &best.shots);
+    script.expected = Some(best.outcome.recorded());
+    save_ron_file(&path, &script);
+    println!("replay saved to {}", path);
+    0
+}
+
+/// `replay`サブコマンド. 保存したリプレイを画面を出さずに再生し, 同じ結果になるか確かめる.
+/// クリアでき, 記録した結果（経過時間, 操作量, 打数）があればそれとも一致すれば0を返す.
+pub fn run_replay_command(args: &[String]) -> i32 {
+    let path = if let Some(path) = args.first() {
+        path
+    } else {
+        eprintln!("usage: replay FILE");
+        return 2;
+    };
+    if !Path::new(path).exists() {
+        eprintln!("{}: file not found", path);
+        return 2;
+    }
+    let script = load_ron_file::<InputScript>(path);
+    let stage_info = if let Some(stage_info) = find_stage(&script.stage) {
+        stage_info
+    } else {
+        eprintln!("{}: stage not found", script.stage);
+        return 2;
+    };
+    let outcome = simulate(&stage_info, &script);
+    println!("{}: {}", stage_info.stage_title, describe(&outcome));
+    if !outcome.cleared {
+        return 1;
+    }
+    match script.expected {
+        Some(expected) if expected != outcome.recorded() => {
+            println!(
+                "mismatch: recorded {:.2}s with {} operations and {} strokes",
+                expected.frames as f32 / 60.0,
+                expected.operations,
+                expected.strokes
+            );
+            1
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::Vec2;
+
+    use crate::{
+        components::ball::BallType,
+        stages::file::{GoalFile, LauncherFile, StageFile},
+    };
+
+    #[test]
+    fn solved_script_replays_with_same_outcome() {
+        // ランチャーの正面にゴールを置き, 向きを少ししか変えられないようにしてすぐに解けるようにする
+        let launcher = LauncherFile {
+            min_angle: -0.1,
+            max_angle: 0.1,
+            ..Default::default()
+        };
+        let stage_info = StageFile {
+            time: 600,
+            balls: vec![BallType::Normal],
+            goals: vec![GoalFile {
+                pos: launcher.pos + Vec2::new(300.0, 0.0),
+                ..Default::default()
+            }],
+            launcher,
+            ..Default::default()
+        }
+        .to_stage_info();
+        let mut solver = Solver {
+            stage_name: stage_info.stage_title,
+            stage_info: &stage_info,
+            objective: Objective::Time,
+            evaluations: 0,
+            check_only: true,
+        };
+        let best = solver.solve(60, 1);
+        assert!(best.outcome.cleared);
+        let script = build_script(stage_info.stage_title, &best.shots);
+        for _ in 0..3 {
+            let replayed = simulate(&stage_info, &script);
+            assert!(replayed.cleared);
+            assert_eq!(replayed.recorded(), best.outcome.recorded());
+        }
+    }
+}
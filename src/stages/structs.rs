@@ -1,5 +1,5 @@
 //! ステージを定義する構造体を定義するモジュール.
-use crate::systems::field::FIELD_WIDTH;
+use crate::systems::field::{FIELD_HEIGHT, FIELD_WIDTH};
 use std::f32::consts::FRAC_PI_2;
 
 use bevy::prelude::*;
@@ -7,13 +7,21 @@ use bevy::prelude::*;
 use crate::components::physics::force::Gravity;
 use crate::events::ball::SetBallEvent;
 use crate::events::goal::SpawnGoalEvent;
+use crate::events::hazard::SpawnHazardEvent;
+use crate::events::item::{SpawnCheckpointEvent, SpawnItemEvent};
 use crate::events::launcher::SpawnLauncherEvent;
 use crate::events::switch::SpawnSwitchEvent;
+use crate::events::time_scale::SpawnTimeScaleZoneEvent;
 use crate::{
     components::{
         ball::BallType,
         block::{RotateStrategy, SlideStrategy},
         block_attach::{switch::SwitchTile, BlockAttachment},
+        game::{GameRule, WinCondition},
+        goal::GoalHole,
+        hazard::HazardKind,
+        input::ControlCurve,
+        item::ItemType,
         physics::material::PhysicMaterial,
     },
     events::ToSpawnEvent,
@@ -36,6 +44,81 @@ pub struct StageInfo {
     pub switches: Vec<SwitchInfo>,
     /// 重力場
     pub gravity: Option<Gravity>,
+    /// 拾うと効果のあるアイテム
+    pub items: Vec<ItemInfo>,
+    /// ゴールの前に通過する必要のあるチェックポイント
+    pub checkpoints: Vec<CheckpointInfo>,
+    /// ボールを壊す危険地帯
+    pub hazards: Vec<HazardInfo>,
+    /// 通過するボールの時間の進み方を変える領域
+    pub time_scale_zones: Vec<TimeScaleZoneInfo>,
+    /// 止まってしまったボールを自動で取り除くか
+    pub auto_retire: bool,
+    /// 打数ルールでの基準打数
+    pub par: u32,
+    /// 星の評価基準. Noneなら制限時間から決める
+    pub star_thresholds: Option<StarThresholds>,
+    /// 選択されたルールに関わらずこのルールで遊ぶ
+    pub rule: Option<GameRule>,
+    /// ステージ固有のクリア条件. 空でなければルールによる終了条件の代わりにすべて満たしたときにクリアとなる
+    pub win_conditions: Vec<WinCondition>,
+    /// 回転や移動の加速のしかた. Noneなら全体の設定を使う
+    pub control_curve: Option<ControlCurve>,
+    /// trueならブロックとランチャーを操作グループごとに切り替えて動かす. falseならすべて連動して動く
+    pub grouped_control: bool,
+    /// フィールドの大きさ. 標準より大きければカメラがボールを追いかけ, ミニマップが表示される
+    pub field_size: Vec2,
+}
+impl StageInfo {
+    pub fn star_thresholds(&self) -> StarThresholds {
+        self.star_thresholds.unwrap_or(StarThresholds {
+            time: self.time / 2,
+            operations: self.time / 4,
+        })
+    }
+}
+
+/// クリアで星1つ, 以下の基準をそれぞれ満たすと星1つずつ追加される
+#[derive(Clone, Copy, Debug)]
+pub struct StarThresholds {
+    /// この時間（フレーム）以内にクリアする
+    pub time: u32,
+    /// この操作量以内でクリアする
+    pub operations: u32,
+}
+impl StarThresholds {
+    pub fn stars(&self, cleared: bool, passed_time: u32, operations: u32) -> u32 {
+        if !cleared {
+            return 0;
+        }
+        1 + (passed_time <= self.time) as u32 + (operations <= self.operations) as u32
+    }
+}
+impl Default for StageInfo {
+    fn default() -> Self {
+        StageInfo {
+            stage_title: "",
+            time: 60 * 60,
+            launcher: LauncherInfo::default(),
+            blocks: vec![],
+            balls: vec![],
+            goal_pos: vec![],
+            switches: vec![],
+            gravity: None,
+            items: vec![],
+            checkpoints: vec![],
+            hazards: vec![],
+            time_scale_zones: vec![],
+            auto_retire: false,
+            par: 2,
+            star_thresholds: None,
+            rule: None,
+            win_conditions: vec![],
+            control_curve: None,
+            grouped_control: false,
+            field_size: Vec2::new(FIELD_WIDTH, FIELD_HEIGHT),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -45,6 +128,8 @@ pub struct LauncherInfo {
     pub rotate_speed: f32,
     pub min_angle: f32,
     pub max_angle: f32,
+    /// 操作グループ（グループ操作のステージでのみ使う）
+    pub control_group: u32,
 }
 impl Default for LauncherInfo {
     fn default() -> Self {
@@ -54,6 +139,7 @@ impl Default for LauncherInfo {
             rotate_speed: 0.02,
             min_angle: -FRAC_PI_2,
             max_angle: FRAC_PI_2,
+            control_group: 0,
         }
     }
 }
@@ -66,6 +152,7 @@ impl ToSpawnEvent for LauncherInfo {
             rotate_speed: self.rotate_speed,
             min_angle: self.min_angle,
             max_angle: self.max_angle,
+            control_group: self.control_group,
         }
     }
 }
@@ -109,6 +196,7 @@ pub struct BlockInfo {
     pub default_angle: f32,                     // 初期角度
     pub default_pos_param: f32,                 // 初期位置パラメータ
     pub block_attachment: Vec<BlockAttachment>, // ブロックにくっつけるもの
+    pub control_group: u32,                     // 操作グループ（グループ操作のステージでのみ使う）
 }
 
 /// ボールひとつの情報
@@ -146,14 +234,38 @@ pub struct GoalInfo {
     pub pos: Vec2,
     pub radius: f32,
     pub score: u32,
+    /// 受け入れるボールの種類（Noneならすべて）
+    pub accept: Option<Vec<BallType>>,
+    /// 受け入れるボールの数の上限
+    pub capacity: Option<u32>,
+    /// 入るときの速さの上限（落ち着いて入ったボールのみ受け入れる）
+    pub max_entry_speed: Option<f32>,
+    /// 番号付きゴールの番号（1から順に入れる必要がある）
+    pub order: Option<u32>,
+    /// ゴールの移動方法. ブロックと同じく軌道に沿って動く.
+    pub slide_strategy: SlideStrategy,
+    /// クリア条件で指定するための名前
+    pub name: Option<&'static str>,
+}
+impl GoalInfo {
+    /// ゴールの設定からコンポーネントを作成する. ブロックに取り付ける場合にも使う.
+    pub fn to_goal_hole(&self) -> GoalHole {
+        GoalHole {
+            accept: self.accept.clone(),
+            capacity: self.capacity,
+            max_entry_speed: self.max_entry_speed,
+            order: self.order,
+            name: self.name,
+            ..GoalHole::new(self.pos, self.radius, self.score)
+        }
+    }
 }
 impl ToSpawnEvent for GoalInfo {
     type E = SpawnGoalEvent;
     fn to_spawn_event(&self) -> Self::E {
         SpawnGoalEvent {
-            pos: self.pos,
-            radius: self.radius,
-            score: self.score,
+            component: self.to_goal_hole(),
+            slide_strategy: self.slide_strategy.clone(),
         }
     }
 }
@@ -163,6 +275,12 @@ impl Default for GoalInfo {
             pos: Vec2::ZERO,
             radius: 30.0,
             score: 1,
+            accept: None,
+            capacity: None,
+            max_entry_speed: None,
+            order: None,
+            slide_strategy: SlideStrategy::NoSlide,
+            name: None,
         }
     }
 }
@@ -207,3 +325,79 @@ impl ToSpawnEvent for SwitchInfo {
         }
     }
 }
+
+/// アイテム一つの情報
+#[derive(Clone)]
+pub struct ItemInfo {
+    pub pos: Vec2,
+    pub item_type: ItemType,
+}
+impl ToSpawnEvent for ItemInfo {
+    type E = SpawnItemEvent;
+    fn to_spawn_event(&self) -> Self::E {
+        SpawnItemEvent {
+            pos: self.pos,
+            item_type: self.item_type,
+        }
+    }
+}
+
+/// チェックポイント一つの情報
+#[derive(Clone)]
+pub struct CheckpointInfo {
+    pub pos: Vec2,
+    pub radius: f32,
+}
+impl Default for CheckpointInfo {
+    fn default() -> Self {
+        CheckpointInfo {
+            pos: Vec2::ZERO,
+            radius: 40.0,
+        }
+    }
+}
+impl ToSpawnEvent for CheckpointInfo {
+    type E = SpawnCheckpointEvent;
+    fn to_spawn_event(&self) -> Self::E {
+        SpawnCheckpointEvent {
+            pos: self.pos,
+            radius: self.radius,
+        }
+    }
+}
+
+/// 危険地帯一つの情報
+#[derive(Clone)]
+pub struct HazardInfo {
+    pub pos: Vec2,
+    pub extents: Vec2,
+    pub kind: HazardKind,
+}
+impl ToSpawnEvent for HazardInfo {
+    type E = SpawnHazardEvent;
+    fn to_spawn_event(&self) -> Self::E {
+        SpawnHazardEvent {
+            pos: self.pos,
+            extents: self.extents,
+            kind: self.kind,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TimeScaleZoneInfo {
+    pub pos: Vec2,
+    pub extents: Vec2,
+    /// 領域内のボールの時間の進む速さ
+    pub scale: f32,
+}
+impl ToSpawnEvent for TimeScaleZoneInfo {
+    type E = SpawnTimeScaleZoneEvent;
+    fn to_spawn_event(&self) -> Self::E {
+        SpawnTimeScaleZoneEvent {
+            pos: self.pos,
+            extents: self.extents,
+            scale: self.scale,
+        }
+    }
+}
@@ -0,0 +1,537 @@
+//! ステージの配置をSVG画像に書き出す. ゲームを起動せずにステージの資料やレビュー用の図を作るために使う.
+//! ブロックとゴールは出現したときの位置と角度で描き, 動くものは軌道も描く. 操作の記録があればボールの軌跡を重ねる.
+use std::{fmt::Write, path::Path};
+
+use bevy::prelude::*;
+
+use super::{
+    find_stage,
+    solve::{build_script, Shot},
+    stage_file_name,
+    structs::{BlockInfo, BlockShapeInfo, StageInfo},
+};
+use crate::{
+    components::{
+        ball::BallType,
+        block::{RotateStrategy, SlideStrategy},
+        block_attach::{utils::EdgeDirection, BlockAttachment},
+        goal::GoalHole,
+        replay::InputScript,
+        time_scale::TimeScaleZone,
+    },
+    systems::{
+        simulate::{simulate_trajectories, BallTrajectory},
+        utils::load_ron_file,
+    },
+};
+
+/// 書き出したSVGを保存するディレクトリ
+const SVG_DIR: &str = "save/svg";
+/// フィールドの周りに空ける余白
+const MARGIN: f32 = 60.0;
+/// ランチャーの可動範囲を表す扇形の半径
+const LAUNCHER_ARC_RADIUS: f32 = 80.0;
+
+/// bevyの色をSVGの色にする
+fn svg_color(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_f32();
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        (r * 255.0) as u8,
+        (g * 255.0) as u8,
+        (b * 255.0) as u8
+    )
+}
+
+/// 文字列をSVGの中に書けるようにする
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// SVGは下向きがy軸の正なので上下を反転する
+fn flip(v: Vec2) -> Vec2 {
+    Vec2::new(v.x, -v.y)
+}
+
+/// 位置と角度を表すtransform属性
+fn placement(pos: Vec2, angle: f32) -> String {
+    let pos = flip(pos);
+    format!(
+        "translate({:.1} {:.1}) rotate({:.2})",
+        pos.x,
+        pos.y,
+        -angle.to_degrees()
+    )
+}
+
+/// 矩形の辺の両端（矩形の中心を原点とする座標）
+fn edge_points(direction: &EdgeDirection, extents: Vec2) -> [Vec2; 2] {
+    let half = extents / 2.0;
+    match direction {
+        EdgeDirection::Up => [Vec2::new(-half.x, half.y), Vec2::new(half.x, half.y)],
+        EdgeDirection::Down => [Vec2::new(-half.x, -half.y), Vec2::new(half.x, -half.y)],
+        EdgeDirection::Left => [Vec2::new(-half.x, -half.y), Vec2::new(-half.x, half.y)],
+        EdgeDirection::Right => [Vec2::new(half.x, -half.y), Vec2::new(half.x, half.y)],
+    }
+}
+
+/// 辺の外向きの単位ベクトル
+fn edge_normal(direction: &EdgeDirection) -> Vec2 {
+    match direction {
+        EdgeDirection::Up => Vec2::Y,
+        EdgeDirection::Down => -Vec2::Y,
+        EdgeDirection::Left => -Vec2::X,
+        EdgeDirection::Right => Vec2::X,
+    }
+}
+
+fn line(svg: &mut String, from: Vec2, to: Vec2, style: &str) {
+    let (from, to) = (flip(from), flip(to));
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" {}/>"#,
+        from.x, from.y, to.x, to.y, style
+    );
+}
+
+fn circle(svg: &mut String, center: Vec2, radius: f32, style: &str) {
+    let center = flip(center);
+    let _ = writeln!(
+        svg,
+        r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" {}/>"#,
+        center.x, center.y, radius, style
+    );
+}
+
+fn rect(svg: &mut String, center: Vec2, extents: Vec2, style: &str) {
+    let corner = flip(center) - extents / 2.0;
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" {}/>"#,
+        corner.x, corner.y, extents.x, extents.y, style
+    );
+}
+
+fn text(svg: &mut String, pos: Vec2, size: f32, content: &str) {
+    let pos = flip(pos);
+    let _ = writeln!(
+        svg,
+        r#"<text x="{:.1}" y="{:.1}" font-size="{}" font-family="sans-serif" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+        pos.x,
+        pos.y,
+        size,
+        escape(content)
+    );
+}
+
+/// 軌道の両端を結ぶ破線. 軌道はパラメータが-1から1の間を往復する
+fn slide_path(svg: &mut String, origin: Vec2, slide_strategy: &SlideStrategy, color: &str) {
+    if let SlideStrategy::NoSlide = slide_strategy {
+        return;
+    }
+    let path = slide_strategy.get_path();
+    line(
+        svg,
+        origin + path.calc_orbit(-1.0),
+        origin + path.calc_orbit(1.0),
+        &format!(
+            r#"stroke="{}" stroke-width="3" stroke-dasharray="8 6""#,
+            color
+        ),
+    );
+}
+
+/// ゴールを描く. 番号付きゴールは番号を, 名前付きゴールは名前を添える
+fn goal(svg: &mut String, pos: Vec2, goal: &GoalHole) {
+    circle(
+        svg,
+        pos,
+        goal.radius,
+        r##"fill="#222222" stroke="white" stroke-width="3""##,
+    );
+    let label = match (goal.order, goal.name) {
+        (Some(order), _) => order.to_string(),
+        (None, Some(name)) => name.to_string(),
+        (None, None) => String::new(),
+    };
+    if !label.is_empty() {
+        text(svg, pos, 20.0, &label);
+    }
+}
+
+fn block(svg: &mut String, block: &BlockInfo) {
+    let hazard = block.block_attachment.iter().find_map(|com| match com {
+        BlockAttachment::Hazard(kind) => Some(kind.color()),
+        _ => None,
+    });
+    let (rotate_strategy, slide_strategy) = match &block.block_shape_info {
+        BlockShapeInfo::Wall { .. } => (&RotateStrategy::NoRotate, &SlideStrategy::NoSlide),
+        BlockShapeInfo::Rect {
+            rotate_strategy,
+            slide_strategy,
+            ..
+        }
+        | BlockShapeInfo::Ellipse {
+            rotate_strategy,
+            slide_strategy,
+            ..
+        } => (rotate_strategy, slide_strategy),
+    };
+    slide_path(svg, block.pos, slide_strategy, "#008b8b");
+    let pos = block.pos
+        + slide_strategy
+            .get_path()
+            .calc_orbit(block.default_pos_param);
+    let _ = writeln!(
+        svg,
+        r#"<g transform="{}">"#,
+        placement(pos, block.default_angle)
+    );
+    let outline = r##"stroke="#a9a9a9" stroke-width="3""##;
+    match block.block_shape_info {
+        BlockShapeInfo::Wall { extents } => {
+            let color = svg_color(hazard.unwrap_or(Color::BLACK));
+            rect(
+                svg,
+                Vec2::ZERO,
+                extents,
+                &format!(r#"fill="{}" {}"#, color, outline),
+            );
+        }
+        BlockShapeInfo::Rect {
+            extents,
+            rect_origin,
+            ..
+        } => {
+            let color = svg_color(hazard.unwrap_or(Color::CYAN));
+            rect(
+                svg,
+                rect_origin,
+                extents,
+                &format!(r#"fill="{}" {}"#, color, outline),
+            );
+            // 送風機と磁石は取り付けた辺を色付きの線で示し, 向きを矢印で描く
+            for com in block.block_attachment.iter() {
+                let (direction, color) = match com {
+                    BlockAttachment::Fan(fan) => (&fan.direction, "#4169e1"),
+                    BlockAttachment::Magnet(magnet) => (&magnet.direction, "#dc143c"),
+                    _ => continue,
+                };
+                let [from, to] = edge_points(direction, extents);
+                let style = format!(r#"stroke="{}" stroke-width="6""#, color);
+                line(svg, rect_origin + from, rect_origin + to, &style);
+                let mid = rect_origin + (from + to) / 2.0;
+                let tip = mid + edge_normal(direction) * 40.0;
+                let arrow = format!(r#"stroke="{}" stroke-width="3""#, color);
+                line(svg, mid, tip, &arrow);
+                let back = (mid - tip).normalize() * 10.0;
+                line(svg, tip, tip + back + back.perp(), &arrow);
+                line(svg, tip, tip + back - back.perp(), &arrow);
+            }
+        }
+        BlockShapeInfo::Ellipse { radii, center, .. } => {
+            let color = svg_color(hazard.unwrap_or(Color::PINK));
+            let center = flip(center);
+            let _ = writeln!(
+                svg,
+                r#"<ellipse cx="{:.1}" cy="{:.1}" rx="{:.1}" ry="{:.1}" fill="{}" {}/>"#,
+                center.x, center.y, radii.x, radii.y, color, outline
+            );
+        }
+    }
+    // ブロックに付いたゴールは回転軸からの相対位置にある
+    for com in block.block_attachment.iter() {
+        if let BlockAttachment::Goal(attached) = com {
+            goal(svg, attached.pos, attached);
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+    // 回転するブロックは回転軸に印を付ける
+    if !matches!(rotate_strategy, RotateStrategy::NoRotate) {
+        circle(
+            svg,
+            pos,
+            5.0,
+            r#"fill="white" stroke="black" stroke-width="2""#,
+        );
+    }
+}
+
+/// ステージ（と軌跡）をSVGの文字列にする
+pub fn render_stage_svg(stage: &StageInfo, trajectories: &[BallTrajectory]) -> String {
+    let half = stage.field_size / 2.0 + MARGIN;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.1} {:.1} {:.1} {:.1}">"#,
+        -half.x,
+        -half.y,
+        half.x * 2.0,
+        half.y * 2.0
+    );
+    let _ = writeln!(svg, "<title>{}</title>", escape(stage.stage_title));
+    rect(
+        &mut svg,
+        Vec2::ZERO,
+        stage.field_size,
+        &format!(r#"fill="{}""#, svg_color(Color::LIME_GREEN)),
+    );
+    for hazard in stage.hazards.iter() {
+        let style = format!(r#"fill="{}" opacity="0.8""#, svg_color(hazard.kind.color()));
+        rect(&mut svg, hazard.pos, hazard.extents, &style);
+    }
+    for zone in stage.time_scale_zones.iter() {
+        let color = TimeScaleZone {
+            scale: zone.scale,
+            extents: zone.extents,
+        }
+        .color();
+        let style = format!(r#"fill="{}" opacity="0.5""#, svg_color(color));
+        rect(&mut svg, zone.pos, zone.extents, &style);
+        text(&mut svg, zone.pos, 16.0, &format!("x{}", zone.scale));
+    }
+    for switch in stage.switches.iter() {
+        rect(
+            &mut svg,
+            switch.pos,
+            switch.extents,
+            r##"fill="#ffd700" stroke="#b8860b" stroke-width="3""##,
+        );
+        text(&mut svg, switch.pos, 16.0, &switch.target_id.to_string());
+    }
+    // 動くゴールは出現したときの位置に描き, 軌道の両端にも輪郭を描いて動く範囲を示す
+    for goal_info in stage.goal_pos.iter() {
+        let path = goal_info.slide_strategy.get_path();
+        slide_path(&mut svg, goal_info.pos, &goal_info.slide_strategy, "white");
+        if !matches!(goal_info.slide_strategy, SlideStrategy::NoSlide) {
+            for param in [-1.0, 1.0] {
+                circle(
+                    &mut svg,
+                    goal_info.pos + path.calc_orbit(param),
+                    goal_info.radius,
+                    r#"fill="none" stroke="white" stroke-width="2" stroke-dasharray="4 4""#,
+                );
+            }
+        }
+        goal(
+            &mut svg,
+            goal_info.pos + path.calc_orbit(0.0),
+            &goal_info.to_goal_hole(),
+        );
+    }
+    for block_info in stage.blocks.iter() {
+        block(&mut svg, block_info);
+    }
+    for checkpoint in stage.checkpoints.iter() {
+        circle(
+            &mut svg,
+            checkpoint.pos,
+            checkpoint.radius,
+            r#"fill="none" stroke="white" stroke-width="3" stroke-dasharray="6 4""#,
+        );
+    }
+    for item in stage.items.iter() {
+        let style = format!(r#"fill="{}""#, svg_color(item.item_type.color()));
+        circle(&mut svg, item.pos, item.item_type.radius(), &style);
+    }
+
+    // ランチャーは回せる範囲を扇形で, 初期の向きを線で描く
+    let launcher = &stage.launcher;
+    let arc_point = |angle: f32| flip(launcher.pos + Vec2::from_angle(angle) * LAUNCHER_ARC_RADIUS);
+    let (start, end) = (arc_point(launcher.min_angle), arc_point(launcher.max_angle));
+    let center = flip(launcher.pos);
+    let large_arc = (launcher.max_angle - launcher.min_angle > std::f32::consts::PI) as u32;
+    let _ = writeln!(
+        svg,
+        r#"<path d="M {:.1} {:.1} L {:.1} {:.1} A {r} {r} 0 {} 1 {:.1} {:.1} Z" fill="white" fill-opacity="0.3" stroke="white" stroke-width="2"/>"#,
+        center.x,
+        center.y,
+        start.x,
+        start.y,
+        large_arc,
+        end.x,
+        end.y,
+        r = LAUNCHER_ARC_RADIUS
+    );
+    line(
+        &mut svg,
+        launcher.pos,
+        launcher.pos + Vec2::from_angle(launcher.default_angle) * LAUNCHER_ARC_RADIUS,
+        r#"stroke="black" stroke-width="4""#,
+    );
+    circle(&mut svg, launcher.pos, 20.0, r##"fill="#696969""##);
+
+    for trajectory in trajectories.iter() {
+        let points = trajectory
+            .points
+            .iter()
+            .map(|&point| {
+                let point = flip(point);
+                format!("{:.1},{:.1}", point.x, point.y)
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        let color = match trajectory.ball_type {
+            BallType::Normal => "#0000ff",
+            BallType::Metal => "#404040",
+        };
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="3" stroke-opacity="0.8"/>"#,
+            points, color
+        );
+        if let Some(&last) = trajectory.points.last() {
+            let style = format!(r#"fill="none" stroke="{}" stroke-width="2""#, color);
+            circle(&mut svg, last, trajectory.ball_type.radius(), &style);
+        }
+    }
+    let _ = writeln!(svg, "</svg>");
+    svg
+}
+
+/// `svg`サブコマンド. `svg STAGE [--replay FILE | --aim N] [-o FILE]`
+/// `--replay`は保存した操作を, `--aim`はランチャーをNフレーム回して（負なら右回り）1球打つ操作を動かし, ボールの軌跡を重ねる.
+pub fn run_svg_command(args: &[String]) -> i32 {
+    let mut stage_name = None;
+    let mut script = None;
+    let mut output = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match (arg.as_str(), iter.clone().next()) {
+            ("--replay", Some(path)) => {
+                if !Path::new(path).exists() {
+                    eprintln!("{}: file not found", path);
+                    return 2;
+                }
+                script = Some(load_ron_file::<InputScript>(path));
+                iter.next();
+            }
+            ("--aim", Some(aim)) => match aim.parse::<i32>() {
+                Ok(aim) => {
+                    script = Some(build_script("", &[Shot::aimed(aim)]));
+                    iter.next();
+                }
+                Err(_) => {
+                    eprintln!("--aim needs a number");
+                    return 2;
+                }
+            },
+            ("-o", Some(path)) => {
+                output = Some(path.clone());
+                iter.next();
+            }
+            ("--replay" | "--aim" | "-o", None) => {
+                eprintln!("{} needs a value", arg);
+                return 2;
+            }
+            (name, _) => stage_name = Some(name.to_string()),
+        }
+    }
+    // リプレイにはステージ名が記録されているので, ステージを省略できる
+    let stage_name = match (stage_name, &script) {
+        (Some(name), _) => name,
+        (None, Some(script)) if !script.stage.is_empty() => script.stage.clone(),
+        _ => {
+            eprintln!("usage: svg STAGE [--replay FILE | --aim N] [-o FILE]");
+            return 2;
+        }
+    };
+    let stage_info = if let Some(stage_info) = find_stage(&stage_name) {
+        stage_info
+    } else {
+        eprintln!("{}: stage not found", stage_name);
+        return 2;
+    };
+    let trajectories = match script {
+        Some(script) => simulate_trajectories(&stage_info, &script).1,
+        None => vec![],
+    };
+    let output =
+        output.unwrap_or_else(|| format!("{}/{}.svg", SVG_DIR, stage_file_name(&stage_name)));
+    if let Some(dir) = Path::new(&output).parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match std::fs::write(&output, render_stage_svg(&stage_info, &trajectories)) {
+        Ok(_) => {
+            println!("saved {}", output);
+            0
+        }
+        Err(err) => {
+            eprintln!("failed to write {}: {}", output, err);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::{
+        components::block::BlockSlidePath,
+        stages::structs::{GoalInfo, LauncherInfo},
+    };
+
+    fn small_stage() -> StageInfo {
+        StageInfo {
+            field_size: Vec2::new(400.0, 300.0),
+            launcher: LauncherInfo {
+                pos: Vec2::new(-150.0, -50.0),
+                ..Default::default()
+            },
+            goal_pos: vec![
+                GoalInfo {
+                    pos: Vec2::new(120.0, 80.0),
+                    ..Default::default()
+                },
+                GoalInfo {
+                    pos: Vec2::new(100.0, -40.0),
+                    radius: 20.0,
+                    slide_strategy: SlideStrategy::Auto {
+                        speed: 0.01,
+                        path: BlockSlidePath::StandardLine {
+                            theta: FRAC_PI_2,
+                            width: 50.0,
+                        },
+                    },
+                    ..Default::default()
+                },
+            ],
+            blocks: vec![],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_stage_with_flipped_trajectory() {
+        let trajectory = BallTrajectory {
+            ball_type: BallType::Normal,
+            points: vec![Vec2::new(-150.0, -50.0), Vec2::new(0.0, 20.0)],
+        };
+        let svg = render_stage_svg(&small_stage(), &[trajectory]);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // フィールドは原点を中心にした矩形
+        assert!(svg.contains(&format!(
+            r#"<rect x="-200.0" y="-150.0" width="400.0" height="300.0" fill="{}"/>"#,
+            svg_color(Color::LIME_GREEN)
+        )));
+        // y軸は反転する
+        assert!(svg.contains(r#"<circle cx="120.0" cy="-80.0" r="30.0""#));
+        assert!(svg.contains(r##"<circle cx="-150.0" cy="50.0" r="20.0" fill="#696969"/>"##));
+        assert!(svg.contains(r#"<polyline points="-150.0,50.0 0.0,-20.0""#));
+    }
+
+    #[test]
+    fn moving_goal_shows_its_path_ends() {
+        let svg = render_stage_svg(&small_stage(), &[]);
+        // 出現したときの位置と, 上下50の軌道の両端
+        assert!(svg.contains(r##"<circle cx="100.0" cy="40.0" r="20.0" fill="#222222""##));
+        assert!(svg.contains(r#"<line x1="100.0" y1="90.0" x2="100.0" y2="-10.0""#));
+        assert!(svg.contains(r#"<circle cx="100.0" cy="90.0" r="20.0" fill="none""#));
+        assert!(svg.contains(r#"<circle cx="100.0" cy="-10.0" r="20.0" fill="none""#));
+    }
+}
@@ -4,7 +4,8 @@ use bevy::prelude::*;
 use itertools::Itertools;
 
 use super::structs::{
-    ArrangeBallInfo, BlockInfo, BlockShapeInfo, GoalInfo, LauncherInfo, StageInfo, SwitchInfo,
+    ArrangeBallInfo, BlockInfo, BlockShapeInfo, CheckpointInfo, GoalInfo, HazardInfo, ItemInfo,
+    LauncherInfo, StageInfo, StarThresholds, SwitchInfo, TimeScaleZoneInfo,
 };
 use super::{field_blocks::field_block, structs::BallInfo};
 use crate::components::ball::BallType;
@@ -13,6 +14,10 @@ use crate::components::block_attach::fan::Fan;
 use crate::components::block_attach::magnet::Magnet;
 use crate::components::block_attach::switch::{SwitchReceiver, SwitchType};
 use crate::components::block_attach::BlockAttachment;
+use crate::components::game::GameRule;
+use crate::components::goal::GoalHole;
+use crate::components::hazard::HazardKind;
+use crate::components::item::ItemType;
 use crate::components::physics::force::Gravity;
 use crate::systems::field::{FIELD_HEIGHT, FIELD_WIDTH};
 
@@ -55,11 +60,17 @@ pub fn tutorial1() -> StageInfo {
         pos: Vec2::new(FIELD_WIDTH / 2.0 - 20.0, 0.0),
         radius: 40.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
         stage_title: "tutorial[1]",
+        rule: Some(GameRule::BallScore),
         time: 30 * 60,
+        star_thresholds: Some(StarThresholds {
+            time: 10 * 60,
+            operations: 3 * 60,
+        }),
         launcher: launcher_info,
         blocks: field_block()
             .into_iter()
@@ -69,6 +80,7 @@ pub fn tutorial1() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
 
@@ -94,11 +106,14 @@ pub fn tutorial2() -> StageInfo {
         pos: Vec2::new(FIELD_WIDTH / 2.0 - 20.0, 0.0),
         radius: 40.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
         stage_title: "tutorial[2]",
+        rule: Some(GameRule::BallScore),
         time: 30 * 60,
+        par: 1,
         launcher: launcher_info,
         blocks: field_block()
             .into_iter()
@@ -108,6 +123,7 @@ pub fn tutorial2() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
 
@@ -142,6 +158,7 @@ pub fn fan_tutorial() -> StageInfo {
         rotate_speed: 0.02,
         min_angle: -FRAC_PI_2,
         max_angle: 0.0,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -151,11 +168,13 @@ pub fn fan_tutorial() -> StageInfo {
         pos: Vec2::new(FIELD_WIDTH / 2.0 - 20.0, FIELD_HEIGHT / 2.0 - 20.0),
         radius: 40.0,
         score: 1,
+        ..Default::default()
     }];
 
     StageInfo {
         stage_title: "tutorial[fan]",
         time: 30 * 60,
+        par: 1,
         launcher: launcher_info,
         blocks: field_block()
             .into_iter()
@@ -165,6 +184,7 @@ pub fn fan_tutorial() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        ..Default::default()
     }
 }
 
@@ -187,6 +207,7 @@ pub fn magnet_tutorial() -> StageInfo {
         rotate_speed: 0.02,
         min_angle: -FRAC_PI_2,
         max_angle: 0.0,
+        control_group: 0,
     };
 
     let mut ball_list = Vec::<BallInfo>::new();
@@ -198,11 +219,13 @@ pub fn magnet_tutorial() -> StageInfo {
             pos: Vec2::new(FIELD_WIDTH / 2.0 - 20.0, FIELD_HEIGHT / 2.0 - 20.0),
             radius: 40.0,
             score: 1,
+            ..Default::default()
         },
         GoalInfo {
             pos: Vec2::new(FIELD_WIDTH / 2.0 - 20.0, -FIELD_HEIGHT / 2.0 + 50.0),
             radius: 40.0,
             score: 1,
+            ..Default::default()
         },
     ];
 
@@ -218,6 +241,9 @@ pub fn magnet_tutorial() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: None,
+        // 磁石に付いたまま離れないボールを取り除く
+        auto_retire: true,
+        ..Default::default()
     }
 }
 
@@ -303,11 +329,13 @@ pub fn switch_tutorial() -> StageInfo {
             pos: Vec2::new(450.0, -100.0),
             radius: 40.0,
             score: 1,
+            ..Default::default()
         },
         GoalInfo {
             pos: Vec2::new(0.0, 220.0),
             radius: 30.0,
             score: 2,
+            ..Default::default()
         },
     ];
 
@@ -339,6 +367,7 @@ pub fn switch_tutorial() -> StageInfo {
         switches,
         // gravity: Some(Gravity::simple_gravity()),
         gravity: None,
+        ..Default::default()
     }
 }
 
@@ -391,11 +420,13 @@ pub fn gravity_tutorial() -> StageInfo {
             pos: Vec2::new(FIELD_WIDTH / 2.0 - 20.0, 90.0),
             radius: 40.0,
             score: 1,
+            ..Default::default()
         },
         GoalInfo {
             pos: Vec2::new(80.0, 170.0),
             radius: 40.0,
             score: 3,
+            ..Default::default()
         },
     ];
 
@@ -411,5 +442,270 @@ pub fn gravity_tutorial() -> StageInfo {
         goal_pos: goal_list,
         switches: vec![],
         gravity: Gravity::new_as_some(|_: Vec2| Vec2::Y * (-1.5)),
+        // 重力で床に落ちて止まったボールを取り除く
+        auto_retire: true,
+        ..Default::default()
+    }
+}
+
+pub fn goal_tutorial() -> StageInfo {
+    let block_list = vec![BlockInfo {
+        pos: Vec2::new(-60.0, 0.0),
+        block_shape_info: BlockShapeInfo::Rect {
+            extents: Vec2::new(40.0, 160.0),
+            rect_origin: Vec2::ZERO,
+            rotate_strategy: RotateStrategy::Auto(0.01),
+            slide_strategy: SlideStrategy::NoSlide,
+        },
+        // 回転するブロックに付いたゴール. ゆっくり入れないと通り過ぎる.
+        block_attachment: vec![BlockAttachment::Goal(GoalHole {
+            max_entry_speed: Some(3.0),
+            ..GoalHole::new(Vec2::new(0.0, 120.0), 30.0, 2)
+        })],
+        ..Default::default()
+    }];
+
+    let launcher_info = LauncherInfo::default();
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 3);
+    ball_list.set_balls(BallType::Metal, 2);
+
+    let goal_list = vec![
+        // 番号順に入れる必要があるゴール
+        GoalInfo {
+            pos: Vec2::new(180.0, 160.0),
+            radius: 35.0,
+            score: 1,
+            order: Some(1),
+            ..Default::default()
+        },
+        GoalInfo {
+            pos: Vec2::new(180.0, -160.0),
+            radius: 35.0,
+            score: 1,
+            order: Some(2),
+            ..Default::default()
+        },
+        // 上下に動き, 鉄球を一つだけ受け入れるゴール
+        GoalInfo {
+            pos: Vec2::new(FIELD_WIDTH / 2.0 - 60.0, 0.0),
+            radius: 40.0,
+            score: 3,
+            accept: Some(vec![BallType::Metal]),
+            capacity: Some(1),
+            slide_strategy: SlideStrategy::Auto {
+                speed: 0.01,
+                path: BlockSlidePath::StandardLine {
+                    theta: FRAC_PI_2,
+                    width: 150.0,
+                },
+            },
+            ..Default::default()
+        },
+    ];
+
+    StageInfo {
+        stage_title: "tutorial[goal]",
+        time: 45 * 60,
+        launcher: launcher_info,
+        blocks: field_block()
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        switches: vec![],
+        gravity: None,
+        ..Default::default()
+    }
+}
+
+pub fn item_tutorial() -> StageInfo {
+    let block_list = vec![BlockInfo {
+        pos: Vec2::new(0.0, 0.0),
+        block_shape_info: BlockShapeInfo::Rect {
+            extents: Vec2::new(160.0, 30.0),
+            rect_origin: Vec2::ZERO,
+            rotate_strategy: RotateStrategy::Manual(0.04),
+            slide_strategy: SlideStrategy::NoSlide,
+        },
+        ..Default::default()
+    }];
+
+    let launcher_info = LauncherInfo::default();
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 2);
+
+    let goal_list = vec![GoalInfo {
+        pos: Vec2::new(FIELD_WIDTH / 2.0 - 40.0, -FIELD_HEIGHT / 2.0 + 40.0),
+        radius: 40.0,
+        score: 3,
+        ..Default::default()
+    }];
+
+    // 上側を回るとコインが取れる
+    let mut items = (0..5)
+        .map(|i| ItemInfo {
+            pos: Vec2::new(-250.0 + i as f32 * 80.0, 180.0),
+            item_type: ItemType::Coin(1),
+        })
+        .collect_vec();
+    items.push(ItemInfo {
+        pos: Vec2::new(-200.0, -180.0),
+        item_type: ItemType::BonusTime(10 * 60),
+    });
+    items.push(ItemInfo {
+        pos: Vec2::new(0.0, -200.0),
+        item_type: ItemType::ExtraBall(BallType::Metal),
+    });
+
+    // ゴールする前に両方通過する必要がある
+    let checkpoints = vec![
+        CheckpointInfo {
+            pos: Vec2::new(250.0, 150.0),
+            ..Default::default()
+        },
+        CheckpointInfo {
+            pos: Vec2::new(250.0, -150.0),
+            ..Default::default()
+        },
+    ];
+
+    StageInfo {
+        stage_title: "tutorial[item]",
+        time: 30 * 60,
+        launcher: launcher_info,
+        blocks: field_block()
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        items,
+        checkpoints,
+        ..Default::default()
+    }
+}
+
+pub fn hazard_tutorial() -> StageInfo {
+    let block_list = vec![
+        // 中央の棘付きブロックに触れるとボールが壊れる
+        BlockInfo {
+            pos: Vec2::new(0.0, 0.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(30.0, 200.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::NoRotate,
+                slide_strategy: SlideStrategy::NoSlide,
+            },
+            block_attachment: vec![BlockAttachment::Hazard(HazardKind::Spike)],
+            ..Default::default()
+        },
+        BlockInfo {
+            pos: Vec2::new(-250.0, 0.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(160.0, 30.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::Manual(0.04),
+                slide_strategy: SlideStrategy::NoSlide,
+            },
+            ..Default::default()
+        },
+    ];
+
+    let launcher_info = LauncherInfo::default();
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 3);
+
+    let goal_list = vec![GoalInfo {
+        pos: Vec2::new(FIELD_WIDTH / 2.0 - 80.0, 0.0),
+        radius: 40.0,
+        score: 3,
+        ..Default::default()
+    }];
+
+    // 上下の壁際は危険地帯になっている
+    let hazards = vec![
+        HazardInfo {
+            pos: Vec2::new(100.0, FIELD_HEIGHT / 2.0 - 30.0),
+            extents: Vec2::new(400.0, 60.0),
+            kind: HazardKind::Lava,
+        },
+        HazardInfo {
+            pos: Vec2::new(100.0, -FIELD_HEIGHT / 2.0 + 30.0),
+            extents: Vec2::new(400.0, 60.0),
+            kind: HazardKind::Pit,
+        },
+    ];
+
+    StageInfo {
+        stage_title: "tutorial[hazard]",
+        launcher: launcher_info,
+        blocks: field_block()
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        hazards,
+        ..Default::default()
+    }
+}
+
+pub fn time_tutorial() -> StageInfo {
+    let block_list = vec![
+        // ゴールの手前で回り続ける棒. スローモーションを使うと隙間を狙いやすい
+        BlockInfo {
+            pos: Vec2::new(250.0, 0.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(30.0, 300.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::Auto(0.03),
+                slide_strategy: SlideStrategy::NoSlide,
+            },
+            ..Default::default()
+        },
+    ];
+
+    let launcher_info = LauncherInfo::default();
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 3);
+
+    let goal_list = vec![GoalInfo {
+        pos: Vec2::new(FIELD_WIDTH / 2.0 - 80.0, 0.0),
+        radius: 40.0,
+        score: 3,
+        ..Default::default()
+    }];
+
+    // 棒の手前ではボールが速くなり, ゴールの周りでは遅くなる
+    let time_scale_zones = vec![
+        TimeScaleZoneInfo {
+            pos: Vec2::new(50.0, 0.0),
+            extents: Vec2::new(200.0, 300.0),
+            scale: 2.0,
+        },
+        TimeScaleZoneInfo {
+            pos: Vec2::new(FIELD_WIDTH / 2.0 - 80.0, 0.0),
+            extents: Vec2::new(160.0, 300.0),
+            scale: 0.5,
+        },
+    ];
+
+    StageInfo {
+        stage_title: "tutorial[time]",
+        launcher: launcher_info,
+        blocks: field_block()
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        time_scale_zones,
+        ..Default::default()
     }
 }
@@ -0,0 +1,379 @@
+//! ステージの間違いを調べる. コマンドラインから全ステージに対して実行でき, デバッグビルドではロード時にも実行される.
+use std::fmt;
+
+use bevy::prelude::*;
+
+use super::{
+    all_stages,
+    file::StageFile,
+    structs::{BlockInfo, BlockShapeInfo, StageInfo},
+};
+use crate::components::{block_attach::BlockAttachment, game::WinCondition};
+
+/// 問題の重さ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// 遊べない, または明らかに意図と違う動きになる
+    Error,
+    /// 遊べるが意図していない可能性が高い
+    Warning,
+}
+
+/// ステージの問題一つ
+#[derive(Clone, Debug)]
+pub struct StageIssue {
+    pub severity: Severity,
+    /// 問題のあるもの（"block[3]"など）
+    pub object: String,
+    pub message: String,
+}
+impl fmt::Display for StageIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.object, self.message)
+    }
+}
+
+/// 点がブロックからどれだけ離れているか. ブロックの中なら0. 楕円は中心からの方向の半径で近似する
+fn block_gap(block: &BlockInfo, point: Vec2) -> f32 {
+    let local = Vec2::from_angle(-block.default_angle).rotate(point - block.pos);
+    let rect_gap = |p: Vec2, extents: Vec2| (p.abs() - extents / 2.0).max(Vec2::ZERO).length();
+    match block.block_shape_info {
+        BlockShapeInfo::Wall { extents } => rect_gap(local, extents),
+        BlockShapeInfo::Rect {
+            extents,
+            rect_origin,
+            ..
+        } => rect_gap(local - rect_origin, extents),
+        BlockShapeInfo::Ellipse { radii, center, .. } => {
+            let p = local - center;
+            if (p / radii).length_squared() <= 1.0 {
+                return 0.0;
+            }
+            let radius = radii.x * radii.y / (radii.y * p.x).hypot(radii.x * p.y) * p.length();
+            p.length() - radius
+        }
+    }
+}
+
+/// ブロックの種類と番号. 壁はステージの外枠として別の名前にする
+fn block_name(idx: usize, block: &BlockInfo) -> String {
+    match block.block_shape_info {
+        BlockShapeInfo::Wall { .. } => format!("wall[{}]", idx),
+        _ => format!("block[{}]", idx),
+    }
+}
+
+/// ステージ情報の間違いを調べる
+pub fn validate_stage(stage: &StageInfo) -> Vec<StageIssue> {
+    let mut issues = vec![];
+    let mut report = |severity, object: String, message: String| {
+        issues.push(StageIssue {
+            severity,
+            object,
+            message,
+        })
+    };
+
+    if stage.balls.is_empty() {
+        report(Severity::Error, "stage".into(), "no balls".into());
+    }
+    let has_attached_goal = stage.blocks.iter().any(|block| {
+        block
+            .block_attachment
+            .iter()
+            .any(|attachment| matches!(attachment, BlockAttachment::Goal(_)))
+    });
+    if stage.goal_pos.is_empty() && !has_attached_goal && stage.win_conditions.is_empty() {
+        report(Severity::Warning, "stage".into(), "no goals".into());
+    }
+
+    let launcher = &stage.launcher;
+    if launcher.min_angle > launcher.max_angle {
+        report(
+            Severity::Error,
+            "launcher".into(),
+            format!(
+                "min_angle ({:.2}) is greater than max_angle ({:.2})",
+                launcher.min_angle, launcher.max_angle
+            ),
+        );
+    } else if launcher.default_angle < launcher.min_angle
+        || launcher.default_angle > launcher.max_angle
+    {
+        report(
+            Severity::Warning,
+            "launcher".into(),
+            format!(
+                "default_angle ({:.2}) is out of range",
+                launcher.default_angle
+            ),
+        );
+    }
+    for (idx, block) in stage.blocks.iter().enumerate() {
+        if block_gap(block, launcher.pos) == 0.0 {
+            report(
+                Severity::Error,
+                "launcher".into(),
+                format!("inside {}", block_name(idx, block)),
+            );
+        }
+    }
+
+    for (idx, goal) in stage.goal_pos.iter().enumerate() {
+        let object = format!("goal[{}]", idx);
+        for (block_idx, block) in stage.blocks.iter().enumerate() {
+            let gap = block_gap(block, goal.pos);
+            if gap >= goal.radius {
+                continue;
+            }
+            // 中心が壁に埋まったゴールにはボールが入らない.
+            // 縁だけ重なっているものは入りにくいだけで, ブロックは動いて重ならなくなることもあるので警告にする
+            let (severity, message) = match block.block_shape_info {
+                BlockShapeInfo::Wall { .. } if gap == 0.0 => (Severity::Error, "buried in"),
+                _ if gap == 0.0 => (Severity::Warning, "buried in"),
+                _ => (Severity::Warning, "overlaps"),
+            };
+            report(
+                severity,
+                object.clone(),
+                format!("{} {}", message, block_name(block_idx, block)),
+            );
+        }
+        for (other_idx, other) in stage.goal_pos.iter().enumerate().skip(idx + 1) {
+            if goal.pos.distance(other.pos) < goal.radius + other.radius {
+                report(
+                    Severity::Error,
+                    object.clone(),
+                    format!("overlaps goal[{}]", other_idx),
+                );
+            }
+        }
+    }
+
+    for (idx, block) in stage.blocks.iter().enumerate() {
+        let object = block_name(idx, block);
+        let is_rect = matches!(block.block_shape_info, BlockShapeInfo::Rect { .. });
+        for attachment in block.block_attachment.iter() {
+            match attachment {
+                BlockAttachment::SwitchReceiver { receiver }
+                    if !stage
+                        .switches
+                        .iter()
+                        .any(|switch| switch.target_id == receiver.target_id) =>
+                {
+                    report(
+                        Severity::Warning,
+                        object.clone(),
+                        format!("no switch has target_id {}", receiver.target_id),
+                    )
+                }
+                // 送風機と磁石は矩形のブロックにしか取り付けられない
+                BlockAttachment::Fan(_) if !is_rect => report(
+                    Severity::Warning,
+                    object.clone(),
+                    "fan on a non-rect block does nothing".into(),
+                ),
+                BlockAttachment::Magnet(_) if !is_rect => report(
+                    Severity::Warning,
+                    object.clone(),
+                    "magnet on a non-rect block does nothing".into(),
+                ),
+                _ => {}
+            }
+        }
+    }
+
+    for condition in stage.win_conditions.iter() {
+        match *condition {
+            WinCondition::ReachGoal(name)
+                if !stage.goal_pos.iter().any(|goal| goal.name == Some(name)) =>
+            {
+                report(
+                    Severity::Error,
+                    "win condition".into(),
+                    format!("no goal named {}", name),
+                )
+            }
+            WinCondition::HitAllSwitches if stage.switches.is_empty() => report(
+                Severity::Error,
+                "win condition".into(),
+                "no switches to hit".into(),
+            ),
+            _ => {}
+        }
+    }
+    issues
+}
+
+/// `validate`サブコマンド. ファイルが指定されればそのステージを, なければすべてのステージを調べて結果を表示する.
+/// エラーがあれば1を返す.
+pub fn run_validate_command(paths: &[String]) -> i32 {
+    let stages = if paths.is_empty() {
+        all_stages()
+            .into_iter()
+            .map(|generator| generator())
+            .collect()
+    } else {
+        let mut stages = vec![];
+        for path in paths {
+            match StageFile::load(path) {
+                Some(stage) => stages.push(stage.to_stage_info()),
+                None => {
+                    eprintln!("{}: cannot read stage file", path);
+                    return 1;
+                }
+            }
+        }
+        stages
+    };
+    let mut error_count = 0;
+    let mut warning_count = 0;
+    for stage in stages.iter() {
+        let issues = validate_stage(stage);
+        if issues.is_empty() {
+            continue;
+        }
+        println!("{}", stage.stage_title);
+        for issue in issues {
+            match issue.severity {
+                Severity::Error => error_count += 1,
+                Severity::Warning => warning_count += 1,
+            }
+            println!("  {}", issue);
+        }
+    }
+    println!(
+        "checked {} stages: {} errors, {} warnings",
+        stages.len(),
+        error_count,
+        warning_count
+    );
+    (error_count > 0) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::block::RotateStrategy;
+    use crate::components::block_attach::switch::{SwitchReceiver, SwitchType};
+    use crate::stages::structs::{GoalInfo, SwitchInfo};
+
+    fn messages(stage: &StageInfo) -> Vec<(Severity, String)> {
+        validate_stage(stage)
+            .into_iter()
+            .map(|issue| (issue.severity, issue.message))
+            .collect()
+    }
+
+    #[test]
+    fn default_stage_file_has_no_issues() {
+        let stage = StageFile::default().to_stage_info();
+        assert!(validate_stage(&stage).is_empty());
+    }
+
+    #[test]
+    fn stage_without_balls_is_error() {
+        let mut stage = StageFile::default().to_stage_info();
+        stage.balls.clear();
+        assert!(messages(&stage).contains(&(Severity::Error, "no balls".to_string())));
+    }
+
+    #[test]
+    fn launcher_range_is_checked() {
+        let mut stage = StageFile::default().to_stage_info();
+        stage.launcher.min_angle = 1.0;
+        stage.launcher.max_angle = 0.0;
+        let issues = validate_stage(&stage);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].object, "launcher");
+
+        stage.launcher.min_angle = -0.5;
+        stage.launcher.max_angle = 0.5;
+        stage.launcher.default_angle = 1.0;
+        let issues = validate_stage(&stage);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn overlapping_goals_are_error() {
+        let mut stage = StageFile::default().to_stage_info();
+        let goal = stage.goal_pos[0].clone();
+        stage.goal_pos.push(GoalInfo {
+            pos: goal.pos + Vec2::new(goal.radius, 0.0),
+            ..goal
+        });
+        assert!(messages(&stage).contains(&(Severity::Error, "overlaps goal[1]".to_string())));
+    }
+
+    #[test]
+    fn goal_overlapping_wall_is_reported() {
+        let mut stage = StageFile::default().to_stage_info();
+        // 一番右の壁の内側の辺
+        let (wall_idx, inner_edge) = stage
+            .blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, block)| match block.block_shape_info {
+                BlockShapeInfo::Wall { extents } => Some((idx, block.pos.x - extents.x / 2.0)),
+                _ => None,
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+        let radius = stage.goal_pos[0].radius;
+
+        // 縁だけ右の壁に重なる
+        stage.goal_pos[0].pos = Vec2::new(inner_edge - radius / 2.0, 0.0);
+        let expected = format!("overlaps wall[{}]", wall_idx);
+        assert!(messages(&stage).contains(&(Severity::Warning, expected)));
+
+        // 中心まで埋まる
+        stage.goal_pos[0].pos = Vec2::new(inner_edge + 1.0, 0.0);
+        let expected = format!("buried in wall[{}]", wall_idx);
+        assert!(messages(&stage).contains(&(Severity::Error, expected)));
+    }
+
+    #[test]
+    fn receiver_without_switch_is_warning() {
+        let mut stage = StageFile::default().to_stage_info();
+        stage.blocks.push(BlockInfo {
+            block_attachment: vec![BlockAttachment::SwitchReceiver {
+                receiver: SwitchReceiver {
+                    switch_type: SwitchType::ChangeRotateStrategy {
+                        before: RotateStrategy::NoRotate,
+                        after: RotateStrategy::Auto(0.02),
+                    },
+                    target_id: 3,
+                },
+            }],
+            ..Default::default()
+        });
+        let expected = (Severity::Warning, "no switch has target_id 3".to_string());
+        assert!(messages(&stage).contains(&expected));
+
+        stage.switches.push(SwitchInfo {
+            target_id: 3,
+            pos: Vec2::new(0.0, 200.0),
+            ..Default::default()
+        });
+        assert!(!messages(&stage).contains(&expected));
+    }
+
+    #[test]
+    fn win_conditions_need_their_targets() {
+        let mut stage = StageFile::default().to_stage_info();
+        stage.win_conditions = vec![WinCondition::ReachGoal("A"), WinCondition::HitAllSwitches];
+        let found = messages(&stage);
+        assert!(found.contains(&(Severity::Error, "no goal named A".to_string())));
+        assert!(found.contains(&(Severity::Error, "no switches to hit".to_string())));
+
+        stage.goal_pos[0].name = Some("A");
+        stage.switches.push(SwitchInfo::default());
+        assert!(validate_stage(&stage).is_empty());
+    }
+}
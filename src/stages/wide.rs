@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+
+use super::field_blocks::field_block_of_size;
+use super::structs::{
+    ArrangeBallInfo, BallInfo, BlockInfo, BlockShapeInfo, GoalInfo, LauncherInfo, StageInfo,
+};
+use crate::components::{
+    ball::BallType,
+    block::{RotateStrategy, SlideStrategy},
+};
+
+/// 画面より大きいフィールドで, 反射板を回して遠くのゴールを狙うステージ
+pub fn wide_field() -> StageInfo {
+    let field_size = Vec2::new(2400.0, 1200.0);
+    let block_list = vec![
+        BlockInfo {
+            pos: Vec2::new(-400.0, 300.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(200.0, 30.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::Manual(0.03),
+                slide_strategy: SlideStrategy::NoSlide,
+            },
+            ..Default::default()
+        },
+        BlockInfo {
+            pos: Vec2::new(0.0, -100.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(40.0, 800.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::NoRotate,
+                slide_strategy: SlideStrategy::NoSlide,
+            },
+            ..Default::default()
+        },
+        BlockInfo {
+            pos: Vec2::new(600.0, 300.0),
+            block_shape_info: BlockShapeInfo::Rect {
+                extents: Vec2::new(200.0, 30.0),
+                rect_origin: Vec2::ZERO,
+                rotate_strategy: RotateStrategy::Manual(-0.03),
+                slide_strategy: SlideStrategy::NoSlide,
+            },
+            ..Default::default()
+        },
+    ];
+
+    let launcher_info = LauncherInfo {
+        pos: Vec2::new(-field_size.x / 2.0 + 30.0, -field_size.y / 2.0 + 100.0),
+        min_angle: 0.0,
+        ..Default::default()
+    };
+
+    let mut ball_list = Vec::<BallInfo>::new();
+    ball_list.set_balls(BallType::Normal, 5);
+
+    let goal_list = vec![GoalInfo {
+        pos: Vec2::new(field_size.x / 2.0 - 80.0, -field_size.y / 2.0 + 80.0),
+        radius: 50.0,
+        score: 1,
+        ..Default::default()
+    }];
+
+    StageInfo {
+        stage_title: "wide_field",
+        time: 90 * 60,
+        launcher: launcher_info,
+        blocks: field_block_of_size(field_size)
+            .into_iter()
+            .chain(block_list)
+            .collect::<Vec<BlockInfo>>(),
+        balls: ball_list,
+        goal_pos: goal_list,
+        field_size,
+        ..Default::default()
+    }
+}
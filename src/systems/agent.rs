@@ -0,0 +1,489 @@
+//! エージェント（自動で遊ぶプログラム）から1行1つのJSONで命令を受け取り, 観測を返す.
+//! 画面を出さずに命令ごとにフレームを進める`agent`サブコマンドと,
+//! 画面を出したまま標準入力から操作し毎フレーム観測を出力する`--agent`オプションがある.
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    sync::{mpsc, Mutex},
+};
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    components::{
+        agent::{
+            AgentCommand, AgentResponse, BallObservation, BlockObservation, LauncherObservation,
+            Observation,
+        },
+        ball::{Ball, BallNocking, BallType},
+        block::{Block, BlockTransform, BlockType},
+        control::ControlGroup,
+        editor::EditorSession,
+        game::{
+            CourseProgress, GameRule, OperationAmount, PassedTime, ResultInfoStorage, Score,
+            StrokeCount,
+        },
+        info::RemainingTime,
+        input::InputAction,
+        launcher::{BallMagazine, Launcher, LauncherState},
+        physics::{position::Position, velocity::Velocity},
+        replay::{InputScript, ScriptPlayback},
+        settings::GameSettings,
+        timer::CountDownTimer,
+    },
+    stages::{all_stages, find_stage, structs::StageInfo},
+    AppState,
+};
+
+use super::{simulate::headless_app, utils::Rng};
+
+/// リセットしてからステージのものが出そろうまでに待つフレーム数の上限
+const START_FRAMES: u32 = 30;
+
+/// 今の状態を観測する. rewardは前回の得点からの増分で, 前回の得点を更新する
+pub fn observe(world: &mut World, last_score: &mut u32) -> Observation {
+    let stage = world
+        .get_resource::<StageInfo>()
+        .map_or(String::new(), |stage| stage.stage_title.to_string());
+    let tick = world.get_resource::<PassedTime>().map_or(0, |time| time.0);
+    let score = world.get_resource::<Score>().map_or(0, |score| score.0);
+    let strokes = world
+        .get_resource::<StrokeCount>()
+        .map_or(0, |count| count.0);
+    let operations = world
+        .get_resource::<OperationAmount>()
+        .map_or(0, |amount| amount.0);
+    let (done, cleared) = world
+        .get_resource::<ResultInfoStorage>()
+        .map_or((false, false), |result| (true, result.stars > 0));
+    let remaining_time = world
+        .query_filtered::<&CountDownTimer, With<RemainingTime>>()
+        .iter(world)
+        .next()
+        .map_or(0, |timer| timer.count());
+    let balls = world
+        .query::<(&Ball, &Position, &Velocity, Option<&BallNocking>)>()
+        .iter(world)
+        .map(|(ball, pos, vel, nocking)| BallObservation {
+            ball_type: ball.ball_type,
+            pos: pos.0,
+            vel: vel.0,
+            nocking: nocking.is_some(),
+        })
+        .collect();
+    let blocks = world
+        .query_filtered::<(&BlockType, &BlockTransform, &Transform, &ControlGroup), With<Block>>()
+        .iter(world)
+        .filter(|(block_type, ..)| !matches!(block_type, BlockType::Wall { .. }))
+        .map(|(_, block_trans, trans, group)| BlockObservation {
+            pos: trans.translation.truncate(),
+            angle: block_trans.angle,
+            control_group: group.0,
+        })
+        .collect();
+    let launchers = world
+        .query::<(&Launcher, &LauncherState, &Transform)>()
+        .iter(world)
+        .map(|(launcher, state, trans)| LauncherObservation {
+            pos: trans.translation.truncate(),
+            angle: launcher.angle,
+            nocking: matches!(state, LauncherState::Nocking),
+        })
+        .collect();
+    let magazine = world
+        .query::<&BallMagazine>()
+        .iter(world)
+        .flat_map(|magazine| magazine.balls.iter().map(|(ball_type, _)| *ball_type))
+        .collect::<Vec<BallType>>();
+    let reward = score.saturating_sub(*last_score) as f32;
+    *last_score = score;
+    Observation {
+        stage,
+        tick,
+        remaining_time,
+        remaining_balls: magazine.len() as u32,
+        magazine,
+        score,
+        strokes,
+        operations,
+        balls,
+        blocks,
+        launchers,
+        reward,
+        done,
+        cleared,
+    }
+}
+
+/// 遊ぶステージを決める. 名前があればそのステージ, 種が設定されていればその種で選んだステージ,
+/// どちらもなければ今のステージ, それもなければ種1で選んだステージにする
+fn choose_stage(
+    name: Option<&str>,
+    current: Option<&StageInfo>,
+    seed: Option<u64>,
+) -> Result<StageInfo, String> {
+    match (name, current, seed) {
+        (Some(name), ..) => find_stage(name).ok_or_else(|| format!("{}: stage not found", name)),
+        (None, Some(current), None) => Ok(current.clone()),
+        (None, _, seed) => {
+            let stages = all_stages();
+            let mut rng = Rng::new(seed.unwrap_or(1));
+            Ok(stages[(rng.next_u64() % stages.len() as u64) as usize]())
+        }
+    }
+}
+
+fn error(message: impl Into<String>) -> AgentResponse {
+    AgentResponse::Error {
+        message: message.into(),
+    }
+}
+
+/// 画面を出さずに遊ぶときの状態
+struct HeadlessSession {
+    app: Option<App>,
+    stage_info: Option<StageInfo>,
+    /// 次のリセットでステージを選ぶ種. リセットすると使い終わる
+    next_seed: Option<u64>,
+    last_score: u32,
+}
+impl HeadlessSession {
+    fn handle(&mut self, command: AgentCommand) -> AgentResponse {
+        match command {
+            AgentCommand::Reset { stage } => self.reset(stage.as_deref()),
+            AgentCommand::Seed { seed } => {
+                self.next_seed = Some(seed);
+                AgentResponse::Seed { seed }
+            }
+            AgentCommand::Step { actions, frames } => self.step(actions, frames),
+            AgentCommand::Close => AgentResponse::Closed,
+        }
+    }
+
+    fn reset(&mut self, name: Option<&str>) -> AgentResponse {
+        let stage_info = match choose_stage(name, self.stage_info.as_ref(), self.next_seed.take()) {
+            Ok(stage_info) => stage_info,
+            Err(message) => return error(message),
+        };
+        let mut app = headless_app(&stage_info);
+        // ゲームが始まり, 出現イベントでランチャーが置かれるまで進める
+        for _ in 0..START_FRAMES {
+            app.update();
+            let world = &mut app.world;
+            let playing = *world.resource::<State<AppState>>().current() == AppState::Game;
+            if playing && world.query::<&Launcher>().iter(world).next().is_some() {
+                break;
+            }
+        }
+        self.last_score = 0;
+        let observation = observe(&mut app.world, &mut self.last_score);
+        self.app = Some(app);
+        self.stage_info = Some(stage_info);
+        AgentResponse::Observation(observation)
+    }
+
+    /// 操作を押し続けてフレームを進める. 途中でゲームが終わればそこで止める. rewardは進めた間の合計.
+    /// 0フレームなら進めずに今の状態を返す
+    fn step(&mut self, actions: Vec<InputAction>, frames: u32) -> AgentResponse {
+        let app = if let Some(app) = self.app.as_mut() {
+            app
+        } else {
+            return error("no stage is loaded; send reset first");
+        };
+        if app.world.contains_resource::<ResultInfoStorage>() {
+            return error("the episode is done; send reset");
+        }
+        let mut script = InputScript::default();
+        script.push(frames, actions);
+        app.world.insert_resource(ScriptPlayback::new(script));
+        let mut observation = observe(&mut app.world, &mut self.last_score);
+        let mut reward = observation.reward;
+        for _ in 0..frames {
+            app.update();
+            observation = observe(&mut app.world, &mut self.last_score);
+            reward += observation.reward;
+            if observation.done {
+                break;
+            }
+        }
+        observation.reward = reward;
+        AgentResponse::Observation(observation)
+    }
+}
+
+/// 1行ずつ命令を読み, 1行ずつ返答を書く. closeを受け取るか入力が終わるまで続ける
+fn serve(
+    reader: impl BufRead,
+    mut writer: impl Write,
+    session: &mut HeadlessSession,
+) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<AgentCommand>(&line) {
+            Ok(command) => session.handle(command),
+            Err(err) => error(err.to_string()),
+        };
+        writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+        writer.flush()?;
+        if let AgentResponse::Closed = response {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// `agent`サブコマンド. `agent [STAGE] [--socket PATH]`
+/// 標準入力（`--socket`ならUnixソケットに最初に接続したクライアント）から命令を読み, 返答を書く.
+/// STAGEはステージを指定しないresetで遊ぶステージになる.
+pub fn run_agent_command(args: &[String]) -> i32 {
+    let mut stage_name = None;
+    let mut socket = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--socket" => match iter.next() {
+                Some(path) => socket = Some(path.clone()),
+                None => {
+                    eprintln!("--socket needs a path");
+                    return 2;
+                }
+            },
+            name => stage_name = Some(name.to_string()),
+        }
+    }
+    let mut session = HeadlessSession {
+        app: None,
+        stage_info: None,
+        next_seed: None,
+        last_score: 0,
+    };
+    if let Some(name) = stage_name {
+        match find_stage(&name) {
+            Some(stage_info) => session.stage_info = Some(stage_info),
+            None => {
+                eprintln!("{}: stage not found", name);
+                return 2;
+            }
+        }
+    }
+    let result = match socket {
+        Some(path) => serve_socket(&path, &mut session),
+        None => serve(io::stdin().lock(), io::stdout().lock(), &mut session),
+    };
+    match result {
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("agent: {}", err);
+            1
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serve_socket(path: &str, session: &mut HeadlessSession) -> io::Result<()> {
+    use std::os::unix::net::UnixListener;
+    // 前回のソケットファイルが残っていると作れないので消しておく
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    eprintln!("agent: listening on {}", path);
+    let (stream, _) = listener.accept()?;
+    let result = serve(BufReader::new(stream.try_clone()?), stream, session);
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_path: &str, _session: &mut HeadlessSession) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "unix sockets are not supported on this platform",
+    ))
+}
+
+fn print_response(response: &AgentResponse) {
+    if let Ok(line) = serde_json::to_string(response) {
+        println!("{}", line);
+    }
+}
+
+/// 標準入力から届いた行. 読み込みは別スレッドで行う
+struct AgentInbox(Mutex<mpsc::Receiver<String>>);
+
+/// 画面を出して遊ぶときの状態
+struct AgentWindowState {
+    /// 次のリセットでステージを選ぶ種. リセットすると使い終わる
+    next_seed: Option<u64>,
+    last_score: u32,
+}
+
+/// 画面を出したままリセットする. メニューかゲーム中でなければできない
+fn reset_window(world: &mut World, name: Option<&str>) -> Result<(), String> {
+    let state = world.resource::<State<AppState>>().current().clone();
+    if !matches!(state, AppState::Menu | AppState::Game) {
+        return Err(format!("cannot reset in {:?} state", state));
+    }
+    let current = world.get_resource::<StageInfo>().cloned();
+    let seed = world.resource_mut::<AgentWindowState>().next_seed.take();
+    let stage_info = choose_stage(name, current.as_ref(), seed)?;
+    let settings_rule = world.resource::<GameSettings>().rule;
+    let rule = stage_info
+        .rule
+        .unwrap_or_else(|| GameRule::from(settings_rule));
+    // 遊んでいる途中ならリトライと同じくタイマーを消してからロードし直す
+    let timers = world
+        .query_filtered::<Entity, (With<CountDownTimer>, With<RemainingTime>)>()
+        .iter(world)
+        .collect::<Vec<Entity>>();
+    for ent in timers {
+        world.despawn(ent);
+    }
+    world.remove_resource::<CourseProgress>();
+    world.remove_resource::<EditorSession>();
+    world.insert_resource(stage_info);
+    world.insert_resource(rule);
+    world.resource_mut::<AgentWindowState>().last_score = 0;
+    world
+        .resource_mut::<State<AppState>>()
+        .set(AppState::Loading)
+        .map_err(|err| err.to_string())
+}
+
+/// 届いた命令を処理する. 操作は次のフレームから再生し, 再生し終えたらキーボードに戻す
+fn receive_agent_commands(world: &mut World) {
+    let lines = world
+        .resource::<AgentInbox>()
+        .0
+        .lock()
+        .map(|receiver| receiver.try_iter().collect::<Vec<String>>())
+        .unwrap_or_default();
+    if world
+        .get_resource::<ScriptPlayback>()
+        .is_some_and(|playback| playback.is_finished())
+    {
+        world.remove_resource::<ScriptPlayback>();
+    }
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<AgentCommand>(&line) {
+            Ok(AgentCommand::Step { actions, frames }) => {
+                let mut script = InputScript::default();
+                script.push(frames, actions);
+                world.insert_resource(ScriptPlayback::new(script));
+                None
+            }
+            Ok(AgentCommand::Reset { stage }) => {
+                reset_window(world, stage.as_deref()).err().map(error)
+            }
+            Ok(AgentCommand::Seed { seed }) => {
+                world.resource_mut::<AgentWindowState>().next_seed = Some(seed);
+                Some(AgentResponse::Seed { seed })
+            }
+            Ok(AgentCommand::Close) => {
+                world.resource_mut::<Events<AppExit>>().send(AppExit);
+                Some(AgentResponse::Closed)
+            }
+            Err(err) => Some(error(err.to_string())),
+        };
+        if let Some(response) = response {
+            print_response(&response);
+        }
+    }
+}
+
+/// ゲーム中は毎フレーム観測を出力する
+fn send_observation(world: &mut World) {
+    if *world.resource::<State<AppState>>().current() != AppState::Game {
+        return;
+    }
+    let mut last_score = world.resource::<AgentWindowState>().last_score;
+    let observation = observe(world, &mut last_score);
+    world.resource_mut::<AgentWindowState>().last_score = last_score;
+    print_response(&AgentResponse::Observation(observation));
+}
+
+/// 画面を出したままエージェントが操作できるようにする
+pub struct AgentPlugin;
+impl Plugin for AgentPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        app.insert_resource(AgentInbox(Mutex::new(receiver)));
+        app.insert_resource(AgentWindowState {
+            next_seed: None,
+            last_score: 0,
+        });
+        app.add_system_to_stage(CoreStage::First, receive_agent_commands.exclusive_system());
+        app.add_system_to_stage(CoreStage::Last, send_observation.exclusive_system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> HeadlessSession {
+        HeadlessSession {
+            app: None,
+            stage_info: None,
+            next_seed: None,
+            last_score: 0,
+        }
+    }
+
+    fn stage_of(response: &AgentResponse) -> String {
+        match response {
+            AgentResponse::Observation(observation) => observation.stage.clone(),
+            _ => panic!("not an observation"),
+        }
+    }
+
+    #[test]
+    fn seed_chooses_stage_on_next_reset() {
+        let first = choose_stage(None, None, None).unwrap().stage_title;
+        // 種1以外で最初と違うステージになる種を探す
+        let (seed, other) = (2..100)
+            .map(|seed| (seed, choose_stage(None, None, Some(seed)).unwrap()))
+            .find(|(_, stage)| stage.stage_title != first)
+            .unwrap();
+        let mut session = session();
+        assert_eq!(
+            stage_of(&session.handle(AgentCommand::Reset { stage: None })),
+            first
+        );
+        session.handle(AgentCommand::Seed { seed });
+        let reset = session.handle(AgentCommand::Reset { stage: None });
+        assert_eq!(stage_of(&reset), other.stage_title);
+        // 種は一度のリセットで使い終わり, その後は今のステージを遊ぶ
+        let reset = session.handle(AgentCommand::Reset { stage: None });
+        assert_eq!(stage_of(&reset), other.stage_title);
+    }
+
+    #[test]
+    fn step_with_zero_frames_does_not_advance() {
+        let mut session = session();
+        session.handle(AgentCommand::Reset { stage: None });
+        let tick = |response: AgentResponse| match response {
+            AgentResponse::Observation(observation) => observation.tick,
+            _ => panic!("not an observation"),
+        };
+        let step = |session: &mut HeadlessSession, frames| {
+            session.handle(AgentCommand::Step {
+                actions: vec![],
+                frames,
+            })
+        };
+        let before = tick(step(&mut session, 0));
+        assert_eq!(tick(step(&mut session, 0)), before);
+        assert_eq!(tick(step(&mut session, 3)), before + 3);
+    }
+}
@@ -1,49 +1,69 @@
 use crate::{
     components::{
-        ball::{Ball, BallNocking, BallType, MetalBall},
+        ball::{
+            AirborneFrames, Ball, BallNocking, BallRestDetector, BallType, GoalinBall, MetalBall,
+        },
+        game::LongestAirborne,
+        hazard::BallLostCause,
+        item::CheckpointRecord,
         physics::{
             material::PhysicMaterial, position::Position, velocity::Velocity, BasicPhysicsBundle,
         },
+        settings::GameSettings,
+        time_scale::LocalTimeScale,
+    },
+    events::{
+        ball::{LaunchBallEvent, SpawnBallEvent},
+        hazard::BallLostEvent,
     },
-    events::ball::{LaunchBallEvent, SpawnBallEvent},
+    stages::structs::StageInfo,
     AppState,
 };
 use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::*;
 
+/// 番えた状態のボールを出現させる. 巻き戻しでボールを出し直すときにも使う
+pub fn spawn_ball_entity(commands: &mut Commands, ball_type: BallType, pos: Vec2) -> Entity {
+    let ball_shape = shapes::Circle {
+        radius: ball_type.radius(),
+        ..Default::default()
+    };
+    let ball_ent = commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &ball_shape,
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(ball_type.color()),
+                outline_mode: StrokeMode::new(Color::DARK_GRAY, 2.0),
+            },
+            Transform {
+                translation: pos.extend(11.0),
+                ..Default::default()
+            },
+        ))
+        .insert(Ball::new(ball_type))
+        .insert_bundle(BasicPhysicsBundle::new(
+            pos,
+            Vec2::ZERO,
+            Vec2::ZERO,
+            PhysicMaterial::new(ball_type.restitution(), ball_type.density(), 0.0),
+            &ball_shape,
+        ))
+        .insert(BallNocking)
+        .insert(CheckpointRecord::default())
+        .insert(BallRestDetector::default())
+        .insert(AirborneFrames::default())
+        .insert(LocalTimeScale::default())
+        .id();
+    // 鉄球なら属性を付与
+    if let BallType::Metal = ball_type {
+        commands.entity(ball_ent).insert(MetalBall);
+    }
+    ball_ent
+}
+
 fn spawn_ball(mut commands: Commands, mut event_listener: EventReader<SpawnBallEvent>) {
     for ev in event_listener.iter() {
-        let ball_shape = shapes::Circle {
-            radius: ev.ball_type.radius(),
-            ..Default::default()
-        };
-        let pos = ev.pos;
-        let ball_ent = commands
-            .spawn_bundle(GeometryBuilder::build_as(
-                &ball_shape,
-                DrawMode::Outlined {
-                    fill_mode: FillMode::color(ev.ball_type.color()),
-                    outline_mode: StrokeMode::new(Color::DARK_GRAY, 2.0),
-                },
-                Transform {
-                    translation: pos.extend(11.0),
-                    ..Default::default()
-                },
-            ))
-            .insert(Ball::new(ev.ball_type))
-            .insert_bundle(BasicPhysicsBundle::new(
-                pos,
-                Vec2::ZERO,
-                Vec2::ZERO,
-                PhysicMaterial::new(ev.ball_type.restitution(), ev.ball_type.density(), 0.0),
-                &ball_shape,
-            ))
-            .insert(BallNocking)
-            .id();
-        // 鉄球なら属性を付与
-        if let BallType::Metal = ev.ball_type {
-            commands.entity(ball_ent).insert(MetalBall);
-        }
+        spawn_ball_entity(&mut commands, ev.ball_type, ev.pos);
     }
 }
 
@@ -75,12 +95,53 @@ fn reflect_ball_pos(mut query: Query<(&Position, &mut Transform), MarkerMovingBa
     }
 }
 
+/// 止まってしまったボールを取り除く. ステージか設定で無効にされている場合は何もしない.
+#[allow(clippy::type_complexity)]
+fn retire_resting_ball(
+    stage_info: Res<StageInfo>,
+    settings: Res<GameSettings>,
+    mut query: Query<
+        (&Position, &Velocity, &mut BallRestDetector, Entity),
+        (MarkerMovingBall, Without<GoalinBall>),
+    >,
+    mut ball_lost_event_writer: EventWriter<BallLostEvent>,
+) {
+    if !stage_info.auto_retire || !settings.auto_retire {
+        return;
+    }
+    for (pos, vel, mut detector, ent) in query.iter_mut() {
+        if detector.update(pos.0, vel.0) {
+            ball_lost_event_writer.send(BallLostEvent {
+                ball: ent,
+                pos: pos.0,
+                cause: BallLostCause::Retired,
+            });
+        }
+    }
+}
+
+/// ブロックに触れずに飛び続けている時間を数え, 最長記録を更新する.
+/// ブロックに触れたときのリセットは当たり判定で行う.
+fn count_airborne_frames(
+    mut query: Query<&mut AirborneFrames, (MarkerMovingBall, Without<GoalinBall>)>,
+    mut longest_airborne: ResMut<LongestAirborne>,
+) {
+    for mut airborne in query.iter_mut() {
+        airborne.0 += 1;
+        longest_airborne.0 = longest_airborne.0.max(airborne.0);
+    }
+}
+
 pub struct BallPlugin;
 impl Plugin for BallPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(spawn_ball));
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(reflect_ball_pos.after("move_pos")),
+            SystemSet::on_update(AppState::Game)
+                .with_system(spawn_ball.after("launcher:launch").label("ball:spawn")),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(reflect_ball_pos.after("move_pos").label("ball:reflect_pos")),
         );
         app.add_system_set(
             SystemSet::on_update(AppState::Game).with_system(
@@ -91,7 +152,24 @@ impl Plugin for BallPlugin {
             ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(launch_ball.after("fix_ball")),
+            SystemSet::on_update(AppState::Game)
+                .with_system(launch_ball.after("fix_ball").label("ball:launch")),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                retire_resting_ball
+                    .after("move_pos")
+                    .after("collision:out_of_field")
+                    .label("ball:retire"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                count_airborne_frames
+                    .after("time_scale:apply_zones")
+                    .before("collision:ball_lost")
+                    .label("ball:count_airborne"),
+            ),
         );
     }
 }
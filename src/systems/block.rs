@@ -9,19 +9,33 @@ use crate::{
             SlideStrategy,
         },
         block_attach::BlockAttachment,
+        control::{is_controllable, ActiveControlGroup, ControlGroup},
+        hazard::HazardBlock,
+        input::ActionInput,
+        suspend::StageObjectId,
+        time_scale::TimeScale,
     },
     AppState,
 };
 
 use super::block_attach::fan::spawn_fan;
 use super::block_attach::magnet::spawn_magnet;
+use super::goal::spawn_attached_goal;
 
 /// キューに入っているブロックを追加する（開始時実行）
 fn set_block(mut commands: Commands, mut event_listener: EventReader<SpawnBlockEvent>) {
     for (idx, ev) in event_listener.iter().enumerate() {
         // ブロックが重なったときに変な表示にならないようにz座標に微妙な差をつける
         let z_offset = idx as f32 / 1000.0;
-        let color = Color::from(&ev.block_type);
+        // 危険なブロックはその種類の色で塗る
+        let color = ev
+            .block_attachment
+            .iter()
+            .find_map(|com| match com {
+                BlockAttachment::Hazard(kind) => Some(kind.color()),
+                _ => None,
+            })
+            .unwrap_or_else(|| Color::from(&ev.block_type));
         let shape_bundle = match ev.block_type {
             BlockType::Wall { shape } => GeometryBuilder::build_as(
                 &shape,
@@ -60,6 +74,7 @@ fn set_block(mut commands: Commands, mut event_listener: EventReader<SpawnBlockE
                 },
             ),
         };
+        let block_trans = shape_bundle.transform;
         let ent = commands
             .spawn_bundle(shape_bundle)
             .insert(Block)
@@ -69,6 +84,8 @@ fn set_block(mut commands: Commands, mut event_listener: EventReader<SpawnBlockE
             .insert(ev.material)
             .insert(ev.rotate_strategy.clone())
             .insert(ev.slide_strategy.clone())
+            .insert(ControlGroup(ev.control_group))
+            .insert(StageObjectId::Block(idx))
             .id();
         // ブロックにくっつけるものを追加.
         for com in ev.block_attachment.iter() {
@@ -88,6 +105,12 @@ fn set_block(mut commands: Commands, mut event_listener: EventReader<SpawnBlockE
                         spawn_magnet(&mut commands, ent, &shape, magnet);
                     }
                 }
+                BlockAttachment::Goal(goal) => {
+                    spawn_attached_goal(&mut commands, ent, &block_trans, goal, idx);
+                }
+                BlockAttachment::Hazard(kind) => {
+                    commands.entity(ent).insert(HazardBlock(*kind));
+                }
             }
         }
         // commands.spawn_bundle(GeometryBuilder::build_as(
@@ -104,25 +127,35 @@ fn set_block(mut commands: Commands, mut event_listener: EventReader<SpawnBlockE
     }
 }
 
-/// 回せるブロックと常に回るブロックを回す
+/// 回せるブロックと常に回るブロックを回す. 手動の場合は入力に応じた回転の量をかける.
+/// 常に回るブロックは時間の進む速さに従い, 手動の回転はスローモーション中も変わらない
+#[allow(clippy::type_complexity)]
 fn rotate_block(
-    key_in: Res<Input<KeyCode>>,
-    mut block_query: Query<(&mut Transform, &mut BlockTransform, &RotateStrategy), With<Block>>,
+    action_in: Res<ActionInput>,
+    time_scale: Res<TimeScale>,
+    active_group: Option<Res<ActiveControlGroup>>,
+    mut block_query: Query<
+        (
+            &mut Transform,
+            &mut BlockTransform,
+            &RotateStrategy,
+            &ControlGroup,
+        ),
+        With<Block>,
+    >,
 ) {
-    for (mut trans, mut block_trans, strategy) in block_query.iter_mut() {
+    for (mut trans, mut block_trans, strategy, group) in block_query.iter_mut() {
         // ひとつ前のパラメータとして現在の値を保存
         block_trans.prev_angle = block_trans.angle;
         match strategy {
             RotateStrategy::NoRotate => {}
             RotateStrategy::Manual(angle) => {
-                if key_in.pressed(KeyCode::Left) {
-                    block_trans.angle += angle;
-                } else if key_in.pressed(KeyCode::Right) {
-                    block_trans.angle -= angle;
-                };
+                if is_controllable(&active_group, group) {
+                    block_trans.angle += angle * action_in.rotate_amount();
+                }
             }
             RotateStrategy::Auto(angle) => {
-                block_trans.angle += angle;
+                block_trans.angle += angle * time_scale.0;
             }
         }
         trans.rotation = Quat::from_rotation_z(block_trans.angle);
@@ -130,30 +163,33 @@ fn rotate_block(
 }
 
 /// ブロックの移動処理を行う. オフセットを加えるのもここで行う.
+/// 軌道に沿って動くゴールも同じ仕組みで動かす.
 fn slide_block(
-    key_in: Res<Input<KeyCode>>,
-    mut block_query: Query<
-        (
-            &mut Transform,
-            &mut BlockTransform,
-            &SlideStrategy,
-            &BlockOriginalPos,
-        ),
-        With<Block>,
-    >,
+    action_in: Res<ActionInput>,
+    time_scale: Res<TimeScale>,
+    active_group: Option<Res<ActiveControlGroup>>,
+    mut block_query: Query<(
+        &mut Transform,
+        &mut BlockTransform,
+        &SlideStrategy,
+        &BlockOriginalPos,
+        Option<&ControlGroup>,
+    )>,
 ) {
-    for (mut trans, mut block_trans, strategy, original_pos) in block_query.iter_mut() {
+    for (mut trans, mut block_trans, strategy, original_pos, group) in block_query.iter_mut() {
         // ひとつ前のパラメータとして現在の値を保存
         block_trans.prev_param = block_trans.pos_param;
         block_trans.prev_offset = block_trans.offset;
+        // 操作中でないグループのものは手動では動かない. グループを持たないもの（軌道に沿って動くゴール）はグループ0とする
+        let amount = if is_controllable(&active_group, group.unwrap_or(&ControlGroup(0))) {
+            action_in.rotate_amount()
+        } else {
+            0.0
+        };
         let path = match strategy {
             SlideStrategy::NoSlide => &BlockSlidePath::NoPath,
             SlideStrategy::Manual { speed, path } => {
-                if key_in.pressed(KeyCode::Left) {
-                    block_trans.pos_param += speed;
-                } else if key_in.pressed(KeyCode::Right) {
-                    block_trans.pos_param -= speed;
-                };
+                block_trans.pos_param += speed * amount;
                 if block_trans.pos_param > 1.0 {
                     block_trans.pos_param = 1.0;
                 } else if block_trans.pos_param < -1.0 {
@@ -162,15 +198,11 @@ fn slide_block(
                 path
             }
             SlideStrategy::AutoWrap { speed, path } => {
-                if key_in.pressed(KeyCode::Left) {
-                    block_trans.pos_param += speed;
-                } else if key_in.pressed(KeyCode::Right) {
-                    block_trans.pos_param -= speed;
-                };
+                block_trans.pos_param += speed * amount;
                 path
             }
             SlideStrategy::Auto { speed, path } => {
-                block_trans.pos_param += speed;
+                block_trans.pos_param += speed * time_scale.0;
                 path
             }
         };
@@ -198,8 +230,14 @@ impl Plugin for BlockPlugin {
                 .with_system(set_block)
                 .after("spawn_stage_entities"),
         );
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(rotate_block));
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(slide_block));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(rotate_block.after("ball:spawn").label("block:rotate")),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(slide_block.after("block:rotate").label("block:slide")),
+        );
         // app.add_system_set(SystemSet::on_update(AppState::Game).with_system(temp));
     }
 }
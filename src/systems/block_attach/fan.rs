@@ -11,9 +11,10 @@ use crate::{
         block_attach::fan::{Fan, WindVisualEffect},
         block_attach::utils::EdgeDirection,
         physics::{force::Force, material::Volume, position::Position, velocity::Velocity},
+        time_scale::{LocalTimeScale, TimeScale},
         timer::CountDownTimer,
     },
-    systems::utils::calc_edge_points_of_rectangle,
+    systems::{physics::motion_dynamics::effective_scale, utils::calc_edge_points_of_rectangle},
     AppState,
 };
 
@@ -124,7 +125,14 @@ fn update_wind_visual_effect(
 /// 動いている送風機とボールの間に障害物がなければ力を加える
 fn generate_wind(
     fan_query: Query<(&Fan, &BlockTransform, &GlobalTransform, &BlockType)>,
-    mut ball_query: Query<(&Ball, &Position, &Volume, &mut Force)>,
+    mut ball_query: Query<(
+        &Ball,
+        &Position,
+        &Volume,
+        &mut Force,
+        Option<&LocalTimeScale>,
+    )>,
+    time_scale: Res<TimeScale>,
 ) {
     for (fan, block_trans, block_glb_trans, block_type) in fan_query.iter() {
         if fan.active {
@@ -139,7 +147,7 @@ fn generate_wind(
                     shape.extents,
                 );
 
-                for (_, ball_pos, volume, mut force) in ball_query.iter_mut() {
+                for (_, ball_pos, volume, mut force, local) in ball_query.iter_mut() {
                     let ball_pos = ball_pos.0;
                     if (p2 - p1).dot(ball_pos - p1) > 0.0
                         && (p1 - p2).dot(ball_pos - p2) > 0.0
@@ -147,7 +155,10 @@ fn generate_wind(
                     {
                         let dir_unit = (p1 - p2).perp().normalize();
                         // TODO: また, 障害物を挟んだ場合風が届かないようにしたい.
-                        force.0 += dir_unit * fan.pressure * volume.0;
+                        force.0 += dir_unit
+                            * fan.pressure
+                            * volume.0
+                            * effective_scale(&time_scale, local);
                         // force.0 = force.0.clamp_length_max(15.0);
                     }
                 }
@@ -161,12 +172,27 @@ impl Plugin for FanPlugin {
     fn build(&self, app: &mut App) {
         // app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(temp));
         app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(set_wind_vfx_duration));
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(generate_wind));
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(spawn_wind_visual_effect),
+            SystemSet::on_update(AppState::Game).with_system(
+                generate_wind
+                    .after("collision:switch_and_ball")
+                    .before("execute_force")
+                    .label("fan:generate_wind"),
+            ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(update_wind_visual_effect),
+            SystemSet::on_update(AppState::Game).with_system(
+                spawn_wind_visual_effect
+                    .after("hazard:update_fragment")
+                    .label("fan:spawn_wind_vfx"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                update_wind_visual_effect
+                    .after("fan:spawn_wind_vfx")
+                    .label("fan:update_wind_vfx"),
+            ),
         );
     }
 }
@@ -10,8 +10,9 @@ use crate::{
         block::{BlockTransform, BlockType},
         block_attach::{magnet::Magnet, utils::EdgeDirection},
         physics::{force::Force, position::Position},
+        time_scale::{LocalTimeScale, TimeScale},
     },
-    systems::utils::calc_edge_points_of_rectangle,
+    systems::{physics::motion_dynamics::effective_scale, utils::calc_edge_points_of_rectangle},
     AppState,
 };
 
@@ -54,7 +55,8 @@ pub fn spawn_magnet(commands: &mut Commands, block_ent: Entity, rect: &Rectangle
 /// 磁石とボールの間に力を加える
 fn magnet_force(
     magnet_query: Query<(&Magnet, &BlockTransform, &GlobalTransform, &BlockType)>,
-    mut ball_query: Query<(&Ball, &Position, &mut Force), With<MetalBall>>,
+    mut ball_query: Query<(&Ball, &Position, &mut Force, Option<&LocalTimeScale>), With<MetalBall>>,
+    time_scale: Res<TimeScale>,
 ) {
     for (magnet, block_trans, block_glb_trans, block_type) in magnet_query.iter() {
         if magnet.active {
@@ -68,14 +70,17 @@ fn magnet_force(
                     shape.extents,
                 );
 
-                for (_, ball_pos, mut force) in ball_query.iter_mut() {
+                for (_, ball_pos, mut force, local) in ball_query.iter_mut() {
                     let ball_pos = ball_pos.0;
                     if (p2 - p1).dot(ball_pos - p1) > 0.0
                         && (p1 - p2).dot(ball_pos - p2) > 0.0
                         && (ball_pos - p1).perp_dot(p2 - p1) > 0.0
                     {
                         let dir_unit = (p2 - p1).perp().normalize();
-                        force.0 += dir_unit * magnet.flux_density * magnet.flux_density;
+                        force.0 += dir_unit
+                            * magnet.flux_density
+                            * magnet.flux_density
+                            * effective_scale(&time_scale, local);
                     }
                 }
             }
@@ -86,6 +91,13 @@ fn magnet_force(
 pub(super) struct MagnetPlugin;
 impl Plugin for MagnetPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(magnet_force));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                magnet_force
+                    .after("fan:generate_wind")
+                    .before("execute_force")
+                    .label("magnet:force"),
+            ),
+        );
     }
 }
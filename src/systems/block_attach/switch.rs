@@ -4,9 +4,11 @@ use bevy_prototype_lyon::{prelude::*, shapes::Rectangle};
 use crate::{
     components::{
         block_attach::{
-            switch::{SwitchReceiver, SwitchTile, SwitchType},
+            switch::{SwitchPressedOnce, SwitchReceiver, SwitchTile, SwitchType},
             updater::{Updater, UpdaterType, UpdaterVec},
         },
+        rewind::RewoundSwitch,
+        suspend::StageObjectId,
         timer::CountDownTimer,
     },
     events::switch::SpawnSwitchEvent,
@@ -14,7 +16,7 @@ use crate::{
 };
 
 fn spawn_switch(mut commands: Commands, mut event_reader: EventReader<SpawnSwitchEvent>) {
-    for ev in event_reader.iter() {
+    for (idx, ev) in event_reader.iter().enumerate() {
         let color = if ev.component.active {
             // 押されているなら濃い色にする
             Color::DARK_GREEN
@@ -36,7 +38,8 @@ fn spawn_switch(mut commands: Commands, mut event_reader: EventReader<SpawnSwitc
                     ..Default::default()
                 },
             ))
-            .insert(ev.component.clone());
+            .insert(ev.component.clone())
+            .insert(StageObjectId::Switch(idx));
     }
 }
 
@@ -49,7 +52,7 @@ fn deactivate_switch(mut query: Query<(&mut SwitchTile, &CountDownTimer)>) {
     }
 }
 
-/// スイッチの状態が変化したときの処理
+/// スイッチの状態が変化したときの処理. 色は押されているかで決める（巻き戻しで戻った場合も同じ）
 fn switch_state_changed(
     mut commands: Commands,
     mut query: Query<(&mut SwitchTile, &mut DrawMode, Entity), Changed<SwitchTile>>,
@@ -57,28 +60,23 @@ fn switch_state_changed(
     for (switch, mut draw_mode, ent) in query.iter_mut() {
         if switch.just_active {
             // info!("switch {:?} is just active", ent);
+            commands.entity(ent).insert(SwitchPressedOnce);
             if let Some(count) = switch.auto_reverse {
                 commands
                     .entity(ent)
                     .insert(CountDownTimer::new_will_not_be_removed(count));
             }
-
-            if let DrawMode::Outlined {
-                ref mut fill_mode,
-                outline_mode: _,
-            } = *draw_mode
-            {
-                fill_mode.color = Color::DARK_GREEN;
-            }
-        } else if !switch.active {
-            // info!("switch {:?} is not active", ent);
-            if let DrawMode::Outlined {
-                ref mut fill_mode,
-                outline_mode: _,
-            } = *draw_mode
-            {
-                fill_mode.color = Color::YELLOW_GREEN;
-            }
+        }
+        if let DrawMode::Outlined {
+            ref mut fill_mode,
+            outline_mode: _,
+        } = *draw_mode
+        {
+            fill_mode.color = if switch.active {
+                Color::DARK_GREEN
+            } else {
+                Color::YELLOW_GREEN
+            };
         }
     }
 }
@@ -94,7 +92,7 @@ fn off_one_frame_flag(mut query: Query<&mut SwitchTile>) {
 
 fn execute_change_by_switch(
     mut commands: Commands,
-    switch_query: Query<&SwitchTile, Changed<SwitchTile>>,
+    switch_query: Query<&SwitchTile, (Changed<SwitchTile>, Without<RewoundSwitch>)>,
     mut receiver_query: Query<(&SwitchReceiver, Option<&mut UpdaterVec>, Entity)>,
 ) {
     for switch in switch_query.iter() {
@@ -186,17 +184,28 @@ impl Plugin for SwitchPlugin {
                 .with_system(spawn_switch)
                 .after("spawn_stage_entities"),
         );
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(switch_state_changed));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                switch_state_changed
+                    .after("switch:execute_change")
+                    .label("switch:state_changed"),
+            ),
+        );
         app.add_system_set(
             SystemSet::on_update(AppState::Game).with_system(
                 deactivate_switch
                     .after("count_down_update")
+                    .after("updater:auto_remove")
                     .label("switch:deactivate_switch"),
             ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(execute_change_by_switch.after("collision:switch_and_ball")),
+            SystemSet::on_update(AppState::Game).with_system(
+                execute_change_by_switch
+                    .after("collision:switch_and_ball")
+                    .after("goal:execute_goaled")
+                    .label("switch:execute_change"),
+            ),
         );
         app.add_system_to_stage(CoreStage::Last, off_one_frame_flag);
     }
@@ -4,24 +4,38 @@ use crate::{
     components::{
         block::BlockTransform,
         block_attach::updater::{UpdaterType, UpdaterVec},
+        time_scale::TimeScale,
     },
     AppState,
 };
 
-fn update(mut block_query: Query<(&mut BlockTransform, &mut UpdaterVec)>) {
+/// 時間の進む速さに応じた数だけrangeを進め, 最後に取り出した値で計算する
+fn update(
+    time_scale: Res<TimeScale>,
+    mut block_query: Query<(&mut BlockTransform, &mut UpdaterVec)>,
+) {
     for (mut block_trans, mut updater_vec) in block_query.iter_mut() {
         for updater in updater_vec.0.iter_mut() {
+            updater.carry += time_scale.0;
+            let mut current_count = None;
+            while updater.carry >= 1.0 {
+                updater.carry -= 1.0;
+                if let Some(count) = updater.current_range.pop() {
+                    current_count = Some(count);
+                }
+            }
+            let current_count = if let Some(count) = current_count {
+                count
+            } else {
+                continue;
+            };
             match updater.updater_type {
                 UpdaterType::None => {}
                 UpdaterType::BlockPos { func } => {
-                    if let Some(current_count) = updater.current_range.pop() {
-                        block_trans.offset = func(current_count);
-                    }
+                    block_trans.offset = func(current_count);
                 }
                 UpdaterType::BlockAngle { func } => {
-                    if let Some(current_count) = updater.current_range.pop() {
-                        block_trans.angle = func(current_count);
-                    }
+                    block_trans.angle = func(current_count);
                 }
             }
         }
@@ -43,7 +57,8 @@ pub struct UpdaterPlugin;
 impl Plugin for UpdaterPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(update.before("updater:auto_remove")),
+            SystemSet::on_update(AppState::Game)
+                .with_system(update.after("block:slide").before("updater:auto_remove")),
         );
         app.add_system_set(
             SystemSet::on_update(AppState::Game)
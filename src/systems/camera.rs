@@ -0,0 +1,212 @@
+//! 画面より大きいフィールドのためのカメラの追従, 全体表示とミニマップ
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::camera::{ScalingMode, Viewport},
+};
+
+use crate::{
+    components::{
+        ball::{Ball, BallNocking},
+        camera::{hud_layer, CameraView, FieldCamera, MinimapCamera},
+        input::{ActionInput, InputAction},
+        launcher::Launcher,
+        screen::ScreenLayout,
+    },
+    stages::structs::StageInfo,
+    systems::field::{FIELD_HEIGHT, FIELD_WIDTH},
+    AppState, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/// カメラが目標に近づく割合（1フレームあたり）
+const CAMERA_FOLLOW_RATE: f32 = 0.1;
+/// ミニマップの幅（論理的な画面での大きさ）
+const MINIMAP_WIDTH: f32 = 240.0;
+/// ミニマップと画面の端との間隔
+const MINIMAP_MARGIN: f32 = 10.0;
+
+type FieldCameraFilter = (With<FieldCamera>, Without<MinimapCamera>);
+
+/// 論理的な画面が必ず収まるように映すカメラ. 後から描くカメラは先に描いたものを消さないようにする
+fn screen_camera(priority: isize) -> Camera2dBundle {
+    let mut bundle = Camera2dBundle::default();
+    bundle.camera.priority = priority;
+    bundle.projection.scaling_mode = ScalingMode::Auto {
+        min_width: SCREEN_WIDTH,
+        min_height: SCREEN_HEIGHT,
+    };
+    if priority > 0 {
+        bundle.camera_2d.clear_color = ClearColorConfig::None;
+    }
+    bundle
+}
+
+/// フィールド, 画面に固定するもの, ミニマップを映す3つのカメラを用意する
+pub fn spawn_cameras(commands: &mut Commands) {
+    commands
+        .spawn_bundle(screen_camera(0))
+        .insert(UiCameraConfig { show_ui: false })
+        .insert(FieldCamera);
+    commands.spawn_bundle(screen_camera(1)).insert(hud_layer());
+    let mut minimap = screen_camera(2);
+    minimap.camera.is_active = false;
+    commands
+        .spawn_bundle(minimap)
+        .insert(UiCameraConfig { show_ui: false })
+        .insert(MinimapCamera);
+}
+
+/// フィールドが標準の大きさより大きいか
+fn is_large_field(stage_info: &StageInfo) -> bool {
+    stage_info.field_size.x > FIELD_WIDTH || stage_info.field_size.y > FIELD_HEIGHT
+}
+
+/// 全体表示のときの拡大率. フィールド全体が標準のフィールドの範囲に収まるようにする
+pub fn overview_zoom(field_size: Vec2) -> f32 {
+    (field_size.x / FIELD_WIDTH)
+        .max(field_size.y / FIELD_HEIGHT)
+        .max(1.0)
+}
+
+/// カメラの中心が動ける範囲の半分. 標準の大きさのフィールドなら動かない
+fn camera_range(stage_info: &StageInfo, zoom: f32) -> Vec2 {
+    ((stage_info.field_size - Vec2::new(FIELD_WIDTH, FIELD_HEIGHT) * zoom) / 2.0).max(Vec2::ZERO)
+}
+
+/// ステージ開始時にカメラをランチャーの位置に合わせ, 大きいフィールドならミニマップを出す
+fn init_camera(
+    mut commands: Commands,
+    stage_info: Res<StageInfo>,
+    mut field_camera_query: Query<(&mut Transform, &mut OrthographicProjection), FieldCameraFilter>,
+    mut minimap_query: Query<(&mut Camera, &mut OrthographicProjection), With<MinimapCamera>>,
+) {
+    commands.insert_resource(CameraView::default());
+    let range = camera_range(&stage_info, 1.0);
+    let start = stage_info.launcher.pos.clamp(-range, range);
+    for (mut trans, mut projection) in field_camera_query.iter_mut() {
+        trans.translation = start.extend(trans.translation.z);
+        projection.scale = 1.0;
+    }
+    for (mut camera, mut projection) in minimap_query.iter_mut() {
+        camera.is_active = is_large_field(&stage_info);
+        projection.scaling_mode = ScalingMode::Auto {
+            min_width: stage_info.field_size.x,
+            min_height: stage_info.field_size.y,
+        };
+    }
+}
+
+/// キーで全体表示を切り替え, 発射されたボールを追いかける対象にする
+fn update_camera_view(
+    action_in: Res<ActionInput>,
+    mut view: ResMut<CameraView>,
+    launched_ball_query: Query<Entity, (With<Ball>, Without<BallNocking>)>,
+    removed_nocking: RemovedComponents<BallNocking>,
+) {
+    if action_in.just_pressed(InputAction::Overview) {
+        view.overview = !view.overview;
+    }
+    // 発射されたボールはBallNockingが外れるので, 最後に外れたものを追いかける
+    if let Some(ent) = removed_nocking
+        .iter()
+        .rfind(|ent| launched_ball_query.contains(*ent))
+    {
+        view.target = Some(ent);
+    }
+    // 追いかけていたボールがなくなったら残っているボールに移る
+    if view
+        .target
+        .is_none_or(|ent| !launched_ball_query.contains(ent))
+    {
+        view.target = launched_ball_query.iter().next();
+    }
+}
+
+/// 目標（番えている間はランチャー, それ以外は追いかけているボール）に向かってカメラをなめらかに動かす
+#[allow(clippy::type_complexity)]
+fn follow_target(
+    stage_info: Res<StageInfo>,
+    view: Res<CameraView>,
+    nocking_query: Query<(), With<BallNocking>>,
+    ball_query: Query<&Transform, (With<Ball>, Without<FieldCamera>)>,
+    launcher_query: Query<&Transform, (With<Launcher>, Without<FieldCamera>)>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<FieldCamera>>,
+) {
+    let launcher_pos = launcher_query
+        .get_single()
+        .map_or(Vec2::ZERO, |trans| trans.translation.truncate());
+    let ball_pos = view
+        .target
+        .and_then(|ent| ball_query.get(ent).ok())
+        .map(|trans| trans.translation.truncate());
+    let (target, target_zoom) = if view.overview {
+        (Vec2::ZERO, overview_zoom(stage_info.field_size))
+    } else if !nocking_query.is_empty() {
+        (launcher_pos, 1.0)
+    } else {
+        (ball_pos.unwrap_or(launcher_pos), 1.0)
+    };
+    for (mut trans, mut projection) in camera_query.iter_mut() {
+        projection.scale += (target_zoom - projection.scale) * CAMERA_FOLLOW_RATE;
+        let range = camera_range(&stage_info, projection.scale);
+        let current = trans.translation.truncate();
+        let next = current + (target.clamp(-range, range) - current) * CAMERA_FOLLOW_RATE;
+        trans.translation = next.extend(trans.translation.z);
+    }
+}
+
+/// ミニマップを画面の右上に, フィールドの縦横比に合わせて置く
+fn place_minimap(
+    stage_info: Res<StageInfo>,
+    layout: Res<ScreenLayout>,
+    windows: Res<Windows>,
+    mut minimap_query: Query<&mut Camera, With<MinimapCamera>>,
+) {
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+    let size = Vec2::new(
+        MINIMAP_WIDTH,
+        MINIMAP_WIDTH * stage_info.field_size.y / stage_info.field_size.x,
+    );
+    let top_left = Vec2::new(SCREEN_WIDTH - MINIMAP_MARGIN - size.x, MINIMAP_MARGIN);
+    // 論理的な画面での位置をウィンドウの物理ピクセルに直す
+    let to_physical = |v: Vec2| (v * window.scale_factor() as f32).as_uvec2();
+    for mut camera in minimap_query.iter_mut() {
+        camera.viewport = Some(Viewport {
+            physical_position: to_physical(layout.offset + top_left * layout.scale),
+            physical_size: to_physical(size * layout.scale).max(UVec2::ONE),
+            ..default()
+        });
+    }
+}
+
+/// ゲームを抜けるときにカメラを元に戻す
+fn reset_camera(
+    mut field_camera_query: Query<(&mut Transform, &mut OrthographicProjection), FieldCameraFilter>,
+    mut minimap_query: Query<&mut Camera, With<MinimapCamera>>,
+) {
+    for (mut trans, mut projection) in field_camera_query.iter_mut() {
+        trans.translation = Vec2::ZERO.extend(trans.translation.z);
+        projection.scale = 1.0;
+    }
+    for mut camera in minimap_query.iter_mut() {
+        camera.is_active = false;
+    }
+}
+
+pub struct CameraPlugin;
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(init_camera));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(update_camera_view.label("update_camera_view"))
+                .with_system(follow_target.after("update_camera_view"))
+                .with_system(place_minimap),
+        );
+        app.add_system_set(SystemSet::on_exit(AppState::Game).with_system(reset_camera));
+    }
+}
@@ -5,12 +5,15 @@ use bevy_prototype_lyon::{prelude::RectangleOrigin, shapes::Rectangle};
 
 use crate::{
     components::{
-        ball::{Ball, BallNocking, GoalinBall},
+        ball::{AirborneFrames, Ball, BallNocking, GoalinBall},
         block::{
             Block, BlockOriginalPos, BlockSlidePath, BlockTransform, BlockType, SlideStrategy,
         },
         block_attach::switch::SwitchTile,
+        game::{BounceCount, NextGoalOrder, ReachedGoals},
         goal::GoalHole,
+        hazard::{BallLostCause, HazardBlock, HazardZone},
+        item::{Checkpoint, CheckpointRecord, CollectedItem, CollectibleItem},
         physics::{
             force::Force,
             material::{PhysicMaterial, Volume},
@@ -18,14 +21,22 @@ use crate::{
             velocity::Velocity,
         },
     },
+    events::{goal::BallGoaledEvent, hazard::BallLostEvent, item::CollectItemEvent},
+    stages::structs::StageInfo,
     AppState,
 };
 
+/// 跳ね返りとして数える法線方向の最低速度
+const BOUNCE_SPEED: f32 = 1.0;
+
+/// フィールドの外側にこの距離以上出たボールは失ったものとする
+const OUT_OF_FIELD_MARGIN: f32 = 100.0;
+
 /// 直交座標系に水平な矩形が, ある点を含んでいるか？
 /// center: 矩形の中心
 /// extents: 矩形の大きさ（width, height）
 /// p: 判定したい点
-fn rect_contains_point(center: Vec2, extents: Vec2, p: Vec2) -> bool {
+pub(super) fn rect_contains_point(center: Vec2, extents: Vec2, p: Vec2) -> bool {
     let leftbottom = center - extents / 2.0;
     ((leftbottom.x)..(leftbottom.x + extents.x)).contains(&p.x)
         && ((leftbottom.y)..(leftbottom.y + extents.y)).contains(&p.y)
@@ -153,6 +164,8 @@ fn block_ball_collision(
             &Velocity,
             &mut Force,
             &Volume,
+            &mut AirborneFrames,
+            Entity,
         ),
         Without<GoalinBall>,
     >,
@@ -163,14 +176,26 @@ fn block_ball_collision(
             &BlockOriginalPos,
             &PhysicMaterial,
             Option<&SlideStrategy>,
+            Option<&HazardBlock>,
         ),
         With<Block>,
     >,
+    mut ball_lost_event_writer: EventWriter<BallLostEvent>,
+    mut bounce_count: ResMut<BounceCount>,
 ) {
-    for (ball_trans, ball, ball_material, mut ball_pos, ball_vel, mut force, volume) in
-        ball_query.iter_mut()
+    for (
+        ball_trans,
+        ball,
+        ball_material,
+        mut ball_pos,
+        ball_vel,
+        mut force,
+        volume,
+        mut airborne,
+        ball_ent,
+    ) in ball_query.iter_mut()
     {
-        for (block_trans, block_type, block_original_pos, block_material, slide_strategy) in
+        for (block_trans, block_type, block_original_pos, block_material, slide_strategy, hazard) in
             block_query.iter()
         {
             // 移動軌道を取得
@@ -195,6 +220,16 @@ fn block_ball_collision(
                     continue;
                 }
             } {
+                // 危険なブロックに触れたら跳ね返らずに壊れる
+                if let Some(HazardBlock(kind)) = hazard {
+                    ball_lost_event_writer.send(BallLostEvent {
+                        ball: ball_ent,
+                        pos: ball_pos.0,
+                        cause: BallLostCause::Hazard(*kind),
+                    });
+                    break;
+                }
+                airborne.0 = 0;
                 // 局所座標を画面座標に修正
                 let collide_normal = Vec2::from_angle(block_trans.angle).rotate(lc_collide_normal);
                 ball_pos.0 += collide_normal * penetrate_depth;
@@ -237,6 +272,10 @@ fn block_ball_collision(
                 let impulsive_force =
                     (1.0 + restitution) * ball_weight * (-prev_vel).project_onto(collide_normal);
                 force.0 += impulsive_force;
+                // 乗っているだけの接触は跳ね返りとして数えない
+                if (-prev_vel).dot(collide_normal) > BOUNCE_SPEED {
+                    bounce_count.0 += 1;
+                }
             }
         }
     }
@@ -343,23 +382,149 @@ fn collision_between_goal_and_ball(ball: (&Ball, &Transform), goal: &GoalHole) -
     }
 }
 
+/// ボールがゴールの中心付近に入り, ゴールの条件を満たしていればゴールさせる.
+/// チェックポイントがある場合はすべて通過したボールのみゴールできる.
+#[allow(clippy::type_complexity)]
 fn goal_and_ball_collision(
     mut commands: Commands,
-    mut ball_query: Query<(&Transform, &Ball, &mut Velocity, Entity), Without<GoalinBall>>,
-    goal_query: Query<(&Transform, &GoalHole)>,
+    mut ball_query: Query<
+        (&Transform, &Ball, &mut Velocity, &CheckpointRecord, Entity),
+        Without<GoalinBall>,
+    >,
+    mut goal_query: Query<&mut GoalHole>,
+    checkpoint_query: Query<&Checkpoint>,
+    mut next_goal_order: ResMut<NextGoalOrder>,
+    mut reached_goals: ResMut<ReachedGoals>,
+    mut goaled_event_writer: EventWriter<BallGoaledEvent>,
 ) {
-    for (ball_trans, ball, mut velocity, ball_ent) in ball_query.iter_mut() {
-        for (_, goal) in goal_query.iter() {
-            if let Some(diff_vec) = collision_between_goal_and_ball((ball, ball_trans), goal) {
-                if diff_vec.length() < goal.radius * 0.9 {
+    let checkpoint_num = checkpoint_query.iter().count();
+    for (ball_trans, ball, mut velocity, checkpoint_record, ball_ent) in ball_query.iter_mut() {
+        if checkpoint_record.0.len() < checkpoint_num {
+            continue;
+        }
+        for mut goal in goal_query.iter_mut() {
+            if let Some(diff_vec) = collision_between_goal_and_ball((ball, ball_trans), &goal) {
+                if diff_vec.length() < goal.radius * 0.9
+                    && goal.accepts(ball.ball_type, velocity.0.length(), next_goal_order.0)
+                {
                     velocity.0 = Vec2::ZERO;
                     commands.entity(ball_ent).insert(GoalinBall(goal.score));
+                    goaled_event_writer.send(BallGoaledEvent {
+                        ball: ball_ent,
+                        score: goal.score,
+                    });
+                    goal.count += 1;
+                    if goal.order.is_some() {
+                        next_goal_order.0 += 1;
+                    }
+                    if let Some(name) = goal.name {
+                        reached_goals.0.push(name);
+                    }
+                    // 一つのボールが複数のゴールに入らないようにする
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 円形の領域にボールが触れているか
+fn ball_touches_circle(ball: (&Ball, &Transform), center: Vec2, radius: f32) -> bool {
+    let ball_radius = ball.0.ball_type.radius();
+    let diff = ball.1.translation.truncate() - center;
+    diff.length_squared() < (ball_radius + radius) * (ball_radius + radius)
+}
+
+/// アイテムとボールの当たり判定. 触れたアイテムは取得され見えなくなる.
+fn item_and_ball_collision(
+    mut commands: Commands,
+    ball_query: Query<(&Transform, &Ball), Without<GoalinBall>>,
+    item_query: Query<(&Transform, &CollectibleItem, Entity), Without<CollectedItem>>,
+    mut collect_item_event_writer: EventWriter<CollectItemEvent>,
+) {
+    // 同じフレームで複数のボールが同じアイテムを取得しないようにする
+    let mut collected = Vec::<Entity>::new();
+    for (ball_trans, ball) in ball_query.iter() {
+        for (item_trans, item, item_ent) in item_query.iter() {
+            if collected.contains(&item_ent) {
+                continue;
+            }
+            let item_pos = item_trans.translation.truncate();
+            if ball_touches_circle((ball, ball_trans), item_pos, item.item_type.radius()) {
+                collected.push(item_ent);
+                commands
+                    .entity(item_ent)
+                    .insert(CollectedItem)
+                    .insert(Visibility { is_visible: false });
+                collect_item_event_writer.send(CollectItemEvent {
+                    item_type: item.item_type,
+                });
+            }
+        }
+    }
+}
+
+/// チェックポイントとボールの当たり判定. ボールの中心が入れば通過とする.
+#[allow(clippy::type_complexity)]
+fn checkpoint_and_ball_collision(
+    mut ball_query: Query<(&Transform, &mut CheckpointRecord), (With<Ball>, Without<GoalinBall>)>,
+    mut checkpoint_query: Query<(&Transform, &mut Checkpoint)>,
+) {
+    for (ball_trans, mut record) in ball_query.iter_mut() {
+        for (checkpoint_trans, mut checkpoint) in checkpoint_query.iter_mut() {
+            let diff = ball_trans.translation.truncate() - checkpoint_trans.translation.truncate();
+            if diff.length() < checkpoint.radius && !record.0.contains(&checkpoint.id) {
+                record.0.push(checkpoint.id);
+                if !checkpoint.passed {
+                    checkpoint.passed = true;
                 }
             }
         }
     }
 }
 
+/// 危険地帯とボールの当たり判定. ボールの中心が入れば壊れる.
+#[allow(clippy::type_complexity)]
+fn hazard_zone_and_ball_collision(
+    ball_query: Query<(&Transform, Entity), (With<Ball>, Without<GoalinBall>)>,
+    hazard_query: Query<(&Transform, &HazardZone)>,
+    mut ball_lost_event_writer: EventWriter<BallLostEvent>,
+) {
+    for (ball_trans, ball_ent) in ball_query.iter() {
+        let ball_pos = ball_trans.translation.truncate();
+        for (hazard_trans, hazard) in hazard_query.iter() {
+            let hazard_pos = hazard_trans.translation.truncate();
+            if rect_contains_point(hazard_pos, hazard.extents, ball_pos) {
+                ball_lost_event_writer.send(BallLostEvent {
+                    ball: ball_ent,
+                    pos: ball_pos,
+                    cause: BallLostCause::Hazard(hazard.kind),
+                });
+                break;
+            }
+        }
+    }
+}
+
+/// フィールドから大きく外れたボールは失ったものとする
+#[allow(clippy::type_complexity)]
+fn out_of_field_check(
+    stage_info: Res<StageInfo>,
+    ball_query: Query<(&Position, Entity), (With<Ball>, Without<GoalinBall>)>,
+    mut ball_lost_event_writer: EventWriter<BallLostEvent>,
+) {
+    let field_extents = stage_info.field_size + Vec2::splat(OUT_OF_FIELD_MARGIN * 2.0);
+    for (pos, ball_ent) in ball_query.iter() {
+        if !rect_contains_point(Vec2::ZERO, field_extents, pos.0) {
+            ball_lost_event_writer.send(BallLostEvent {
+                ball: ball_ent,
+                pos: pos.0,
+                cause: BallLostCause::OutOfField,
+            });
+        }
+    }
+}
+
 /// スイッチとボールの当たり判定
 #[allow(clippy::type_complexity)]
 fn switch_and_ball_collision(
@@ -400,20 +565,67 @@ pub struct CollisionPlugin;
 impl Plugin for CollisionPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(block_ball_collision.before("execute_force")),
+            SystemSet::on_update(AppState::Game).with_system(
+                block_ball_collision
+                    .after("ball:count_airborne")
+                    .before("execute_force")
+                    .label("collision:ball_lost")
+                    .label("collision:block_and_ball"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                hazard_zone_and_ball_collision
+                    .after("collision:block_and_ball")
+                    .before("execute_force")
+                    .label("collision:ball_lost")
+                    .label("collision:hazard_and_ball"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                out_of_field_check
+                    .after("ball:reflect_pos")
+                    .label("collision:ball_lost")
+                    .label("collision:out_of_field"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                balls_collision
+                    .after("collision:hazard_and_ball")
+                    .before("execute_force")
+                    .label("collision:balls"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                goal_and_ball_collision
+                    .before("execute_force")
+                    .after("goal:sync_goal_pos")
+                    .label("collision:goal_and_ball"),
+            ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(balls_collision.before("execute_force")),
+            SystemSet::on_update(AppState::Game).with_system(
+                item_and_ball_collision
+                    .after("collision:goal_and_ball")
+                    .before("execute_force")
+                    .label("collision:item_and_ball"),
+            ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(goal_and_ball_collision.before("execute_force")),
+            SystemSet::on_update(AppState::Game).with_system(
+                checkpoint_and_ball_collision
+                    .after("collision:balls")
+                    .before("execute_force")
+                    .before("collision:goal_and_ball"),
+            ),
         );
         app.add_system_set(
             SystemSet::on_update(AppState::Game).with_system(
                 switch_and_ball_collision
+                    .after("collision:item_and_ball")
                     .before("execute_force")
                     .label("collision:switch_and_ball"),
             ),
@@ -0,0 +1,123 @@
+//! 操作グループの切り替えと, 操作できるものの強調表示
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    components::{
+        block::{Block, RotateStrategy, SlideStrategy},
+        control::{is_controllable, ActiveControlGroup, ControlGroup},
+        input::{ActionInput, InputAction},
+        launcher::Launcher,
+    },
+    stages::structs::{BlockShapeInfo, StageInfo},
+    AppState,
+};
+
+/// グループ操作のステージなら, 手動で動くものが属するグループを集めて最初のグループを操作対象にする
+fn init_control_groups(mut commands: Commands, stage_info: Res<StageInfo>) {
+    if !stage_info.grouped_control {
+        commands.remove_resource::<ActiveControlGroup>();
+        return;
+    }
+    let block_groups = stage_info
+        .blocks
+        .iter()
+        .filter(|block| match &block.block_shape_info {
+            BlockShapeInfo::Wall { .. } => false,
+            BlockShapeInfo::Rect {
+                rotate_strategy,
+                slide_strategy,
+                ..
+            }
+            | BlockShapeInfo::Ellipse {
+                rotate_strategy,
+                slide_strategy,
+                ..
+            } => is_manual(rotate_strategy, slide_strategy),
+        })
+        .map(|block| block.control_group);
+    let groups = block_groups
+        .chain([stage_info.launcher.control_group])
+        .collect();
+    commands.insert_resource(ActiveControlGroup::new(groups));
+}
+
+fn is_manual(rotate_strategy: &RotateStrategy, slide_strategy: &SlideStrategy) -> bool {
+    matches!(rotate_strategy, RotateStrategy::Manual(_))
+        || matches!(
+            slide_strategy,
+            SlideStrategy::Manual { .. } | SlideStrategy::AutoWrap { .. }
+        )
+}
+
+/// 操作するグループを切り替える
+fn cycle_control_group(action_in: Res<ActionInput>, active: Option<ResMut<ActiveControlGroup>>) {
+    if let Some(mut active) = active {
+        if action_in.just_pressed(InputAction::CycleGroup) {
+            active.cycle();
+        }
+    }
+}
+
+/// 操作中のグループに属するブロックとランチャーの縁取りを目立たせる
+#[allow(clippy::type_complexity)]
+fn highlight_control_group(
+    active: Option<Res<ActiveControlGroup>>,
+    mut block_query: Query<
+        (
+            &ControlGroup,
+            &RotateStrategy,
+            &SlideStrategy,
+            &mut DrawMode,
+        ),
+        (With<Block>, Without<Launcher>),
+    >,
+    mut launcher_query: Query<(&ControlGroup, &mut DrawMode), With<Launcher>>,
+    added_query: Query<(), Added<ControlGroup>>,
+) {
+    let changed = active.as_ref().is_some_and(|active| active.is_changed());
+    if active.is_none() || (!changed && added_query.is_empty()) {
+        return;
+    }
+    let outline = |group: &ControlGroup, width: f32| {
+        if is_controllable(&active, group) {
+            StrokeMode::new(Color::YELLOW, width + 2.0)
+        } else {
+            StrokeMode::new(Color::DARK_GRAY, width)
+        }
+    };
+    for (group, rotate_strategy, slide_strategy, mut draw_mode) in block_query.iter_mut() {
+        if !is_manual(rotate_strategy, slide_strategy) {
+            continue;
+        }
+        if let DrawMode::Outlined { outline_mode, .. } = &mut *draw_mode {
+            *outline_mode = outline(group, 3.0);
+        }
+    }
+    for (group, mut draw_mode) in launcher_query.iter_mut() {
+        if let DrawMode::Outlined { outline_mode, .. } = &mut *draw_mode {
+            *outline_mode = outline(group, 2.0);
+        }
+    }
+}
+
+pub struct ControlGroupPlugin;
+impl Plugin for ControlGroupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(init_control_groups));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(
+                    cycle_control_group
+                        .after("slow_motion:update")
+                        .before("highlight_control_group")
+                        .label("control:cycle_group"),
+                )
+                .with_system(
+                    highlight_control_group
+                        .after("return_to_title")
+                        .label("highlight_control_group"),
+                ),
+        );
+    }
+}
@@ -0,0 +1,594 @@
+//! ステージエディタ. マウスでものを置いて動かし, その場でテストプレイしてファイルに保存する
+use std::f32::consts::PI;
+
+use bevy::{input::mouse::MouseWheel, prelude::*};
+use bevy_prototype_lyon::{entity::ShapeBundle, prelude::*};
+
+use crate::{
+    components::{
+        ball::BallType,
+        block::{BlockSlidePath, SlideStrategy},
+        camera::FieldCamera,
+        editor::{
+            next_attachment, next_rotate_strategy, next_slide_strategy, EditorDrag, EditorHelp,
+            EditorPanel, EditorSelection, EditorSession, EditorShape, EditorStage,
+        },
+        game::GameRule,
+        input::{ActionInput, InputAction},
+        screen::ScreenLayout,
+        settings::GameSettings,
+    },
+    stages::file::{AttachmentFile, BlockFile, BlockShapeFile, GoalFile, StageFile, SwitchFile},
+    AppState, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+use super::{
+    camera::overview_zoom,
+    field::{FIELD_HEIGHT, FIELD_WIDTH},
+    launcher::construct_launcher_shape,
+    utils::save_ron_file,
+};
+
+/// エディタで編集するステージを保存するファイル
+const EDITOR_STAGE_FILE: &str = "save/editor_stage.ron";
+/// ものを動かしたときに位置を揃える間隔
+const GRID: f32 = 10.0;
+/// ホイール1目盛りで回す角度
+const ROTATE_STEP: f32 = PI / 12.0;
+/// ホイール1目盛りで大きさを変える倍率
+const RESIZE_STEP: f32 = 1.1;
+/// 一度に変える移動の速さ, 軌道の幅, 軌道の向き
+const SLIDE_SPEED_STEP: f32 = 0.005;
+const SLIDE_WIDTH_STEP: f32 = 20.0;
+const SLIDE_THETA_STEP: f32 = PI / 12.0;
+/// フィールドの大きさを一度に変える量と上限（標準の大きさの倍率）
+const FIELD_SIZE_STEP: f32 = 0.25;
+const FIELD_SIZE_MAX: f32 = 4.0;
+
+const HELP_TEXT: &str = "click: select  drag: move  wheel: rotate  shift+wheel: resize\n\
+1: rect  2: ellipse  3: goal  4: switch  Del: remove  E: shape  Tab: rotate  S: slide  A: attachment\n\
+Up/Down: speed, score, target  Left/Right: slide speed  ,/.: slide width  D: slide direction\n\
+N/M: add normal/metal ball  X: remove ball  PgUp/PgDn: field size  Ctrl+S: save  T: test play  B: menu";
+
+/// エディタに入ったときに, 編集中のステージがなければファイルから読み込み, パネルを出す
+fn enter_editor(
+    mut commands: Commands,
+    editor_stage: Option<Res<EditorStage>>,
+    asset_server: Res<AssetServer>,
+) {
+    if editor_stage.is_none() {
+        commands.insert_resource(EditorStage {
+            stage: StageFile::load(EDITOR_STAGE_FILE).unwrap_or_default(),
+            modified: false,
+        });
+    }
+    // テストプレイから戻ってきたときもここで選択を入れ直して描き直させる
+    commands.remove_resource::<EditorSession>();
+    commands.insert_resource(EditorSelection::default());
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 18.0,
+        color: Color::WHITE,
+    };
+    let text_bundle = |text: &str, position: UiRect<Val>| TextBundle {
+        text: Text::from_section(text, text_style.clone()),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position,
+            ..default()
+        },
+        ..default()
+    };
+    commands
+        .spawn_bundle(text_bundle(
+            "",
+            UiRect {
+                left: Val::Px(10.0),
+                top: Val::Px(5.0),
+                ..default()
+            },
+        ))
+        .insert(EditorPanel);
+    commands
+        .spawn_bundle(text_bundle(
+            HELP_TEXT,
+            UiRect {
+                left: Val::Px(10.0),
+                bottom: Val::Px(5.0),
+                ..default()
+            },
+        ))
+        .insert(EditorHelp);
+}
+
+/// フィールド全体が見えるようにカメラを引く
+fn fit_editor_camera(
+    editor_stage: Res<EditorStage>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<FieldCamera>>,
+) {
+    if !editor_stage.is_changed() {
+        return;
+    }
+    for (mut trans, mut projection) in camera_query.iter_mut() {
+        trans.translation = Vec2::ZERO.extend(trans.translation.z);
+        projection.scale = overview_zoom(editor_stage.stage.field_size);
+    }
+}
+
+/// マウスカーソルの位置をフィールドの座標で返す
+fn cursor_position(
+    windows: &Windows,
+    layout: &ScreenLayout,
+    camera: (&Transform, &OrthographicProjection),
+) -> Option<Vec2> {
+    let cursor = windows.get_primary()?.cursor_position()?;
+    let screen =
+        (cursor - layout.offset) / layout.scale - Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
+    let (trans, projection) = camera;
+    Some(trans.translation.truncate() + screen * projection.scale)
+}
+
+/// 位置を格子に揃える
+fn snap(pos: Vec2) -> Vec2 {
+    (pos / GRID).round() * GRID
+}
+
+/// クリックで選び, ドラッグで動かし, ホイールで回す. Shiftを押しながらホイールを回すと大きさを変える
+#[allow(clippy::too_many_arguments)]
+fn edit_with_mouse(
+    mut commands: Commands,
+    mouse_in: Res<Input<MouseButton>>,
+    key_in: Res<Input<KeyCode>>,
+    mut wheel_events: EventReader<MouseWheel>,
+    windows: Res<Windows>,
+    layout: Res<ScreenLayout>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<FieldCamera>>,
+    drag: Option<Res<EditorDrag>>,
+    mut editor_stage: ResMut<EditorStage>,
+    mut selection: ResMut<EditorSelection>,
+) {
+    let cursor = camera_query
+        .get_single()
+        .ok()
+        .and_then(|camera| cursor_position(&windows, &layout, camera));
+    if let Some(cursor) = cursor {
+        if mouse_in.just_pressed(MouseButton::Left) {
+            if let Some(picked) = editor_stage.pick(cursor) {
+                *selection = picked;
+                if let Some(pos) = editor_stage.position(picked) {
+                    commands.insert_resource(EditorDrag(pos - cursor));
+                }
+            }
+        } else if let Some(drag) = drag {
+            let pos = snap(cursor + drag.0);
+            if editor_stage.position(*selection) != Some(pos) {
+                editor_stage.set_position(*selection, pos);
+            }
+        }
+    }
+    if mouse_in.just_released(MouseButton::Left) {
+        commands.remove_resource::<EditorDrag>();
+    }
+    let resizing = key_in.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    for ev in wheel_events.iter() {
+        let notch = ev.y.signum();
+        if notch == 0.0 {
+            continue;
+        }
+        if resizing {
+            editor_stage.resize(*selection, RESIZE_STEP.powf(notch));
+        } else {
+            editor_stage.rotate(*selection, ROTATE_STEP * notch);
+        }
+    }
+}
+
+/// キーでものを追加, 削除し, 設定を切り替える. 選択が変わったらドラッグはやめる
+fn edit_with_keys(
+    mut commands: Commands,
+    key_in: Res<Input<KeyCode>>,
+    mut editor_stage: ResMut<EditorStage>,
+    mut selection: ResMut<EditorSelection>,
+) {
+    // Ctrl+Sは保存なので移動の方法の切り替えはしない
+    if key_in.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        if key_in.just_pressed(KeyCode::S) {
+            save_ron_file(EDITOR_STAGE_FILE, &editor_stage.stage);
+            editor_stage.modified = false;
+        }
+        return;
+    }
+    // ものの追加. 追加したものを選択する
+    let added = if key_in.just_pressed(KeyCode::Key1) {
+        editor_stage.stage.blocks.push(BlockFile::default());
+        Some(EditorSelection::Block(editor_stage.stage.blocks.len() - 1))
+    } else if key_in.just_pressed(KeyCode::Key2) {
+        editor_stage.stage.blocks.push(BlockFile {
+            shape: BlockShapeFile::Ellipse {
+                radii: Vec2::splat(40.0),
+            },
+            ..default()
+        });
+        Some(EditorSelection::Block(editor_stage.stage.blocks.len() - 1))
+    } else if key_in.just_pressed(KeyCode::Key3) {
+        editor_stage.stage.goals.push(GoalFile::default());
+        Some(EditorSelection::Goal(editor_stage.stage.goals.len() - 1))
+    } else if key_in.just_pressed(KeyCode::Key4) {
+        editor_stage.stage.switches.push(SwitchFile::default());
+        Some(EditorSelection::Switch(
+            editor_stage.stage.switches.len() - 1,
+        ))
+    } else {
+        None
+    };
+    if let Some(added) = added {
+        editor_stage.modified = true;
+        *selection = added;
+        commands.remove_resource::<EditorDrag>();
+        return;
+    }
+    if key_in.any_just_pressed([KeyCode::Delete, KeyCode::Back]) {
+        *selection = editor_stage.remove(*selection);
+        commands.remove_resource::<EditorDrag>();
+        return;
+    }
+
+    // 選択中のブロックの設定
+    if key_in.any_just_pressed([KeyCode::E, KeyCode::Tab, KeyCode::S, KeyCode::A]) {
+        if let Some(block) = editor_stage.block_mut(*selection) {
+            if key_in.just_pressed(KeyCode::E) {
+                block.shape = match block.shape {
+                    BlockShapeFile::Rect { extents, .. } => BlockShapeFile::Ellipse {
+                        radii: extents / 2.0,
+                    },
+                    BlockShapeFile::Ellipse { radii } => BlockShapeFile::Rect {
+                        extents: radii * 2.0,
+                        origin: Vec2::ZERO,
+                    },
+                };
+            }
+            if key_in.just_pressed(KeyCode::Tab) {
+                block.rotate_strategy = next_rotate_strategy(&block.rotate_strategy);
+            }
+            if key_in.just_pressed(KeyCode::S) {
+                block.slide_strategy = next_slide_strategy(&block.slide_strategy);
+            }
+            if key_in.just_pressed(KeyCode::A) {
+                block.attachment = next_attachment(&block.attachment);
+            }
+        }
+    }
+    for (key, step) in [(KeyCode::Up, 1), (KeyCode::Down, -1)] {
+        if key_in.just_pressed(key) {
+            editor_stage.adjust_value(*selection, step);
+        }
+    }
+    for (key, speed, width, theta) in [
+        (KeyCode::Right, SLIDE_SPEED_STEP, 0.0, 0.0),
+        (KeyCode::Left, -SLIDE_SPEED_STEP, 0.0, 0.0),
+        (KeyCode::Period, 0.0, SLIDE_WIDTH_STEP, 0.0),
+        (KeyCode::Comma, 0.0, -SLIDE_WIDTH_STEP, 0.0),
+        (KeyCode::D, 0.0, 0.0, SLIDE_THETA_STEP),
+    ] {
+        if key_in.just_pressed(key) {
+            editor_stage.adjust_slide(*selection, speed, width, theta);
+        }
+    }
+
+    // ステージ全体の設定
+    if key_in.just_pressed(KeyCode::N) {
+        editor_stage.stage.balls.push(BallType::Normal);
+        editor_stage.modified = true;
+    } else if key_in.just_pressed(KeyCode::M) {
+        editor_stage.stage.balls.push(BallType::Metal);
+        editor_stage.modified = true;
+    } else if key_in.just_pressed(KeyCode::X) && editor_stage.stage.balls.len() > 1 {
+        editor_stage.stage.balls.pop();
+        editor_stage.modified = true;
+    }
+    let standard_size = Vec2::new(FIELD_WIDTH, FIELD_HEIGHT);
+    for (key, step) in [(KeyCode::PageUp, 1.0), (KeyCode::PageDown, -1.0)] {
+        if key_in.just_pressed(key) {
+            let field_size = &mut editor_stage.stage.field_size;
+            *field_size = (*field_size + standard_size * FIELD_SIZE_STEP * step)
+                .clamp(standard_size, standard_size * FIELD_SIZE_MAX);
+            editor_stage.modified = true;
+        }
+    }
+}
+
+/// 選択中のものは黄色く縁取る
+fn outline(selected: bool) -> StrokeMode {
+    if selected {
+        StrokeMode::new(Color::YELLOW, 5.0)
+    } else {
+        StrokeMode::new(Color::DARK_GRAY, 3.0)
+    }
+}
+
+/// エディタでのブロックの見た目. 危険なブロックはその種類の色で塗る
+fn block_shape_bundle(block: &BlockFile, z: f32, selected: bool) -> ShapeBundle {
+    let color = match &block.attachment {
+        Some(AttachmentFile::Hazard(kind)) => kind.color(),
+        _ => match block.shape {
+            BlockShapeFile::Rect { .. } => Color::CYAN,
+            BlockShapeFile::Ellipse { .. } => Color::PINK,
+        },
+    };
+    let draw_mode = DrawMode::Outlined {
+        fill_mode: FillMode::color(color),
+        outline_mode: outline(selected),
+    };
+    let transform = Transform {
+        translation: block.pos.extend(z),
+        rotation: Quat::from_rotation_z(block.default_angle),
+        ..default()
+    };
+    match block.shape {
+        BlockShapeFile::Rect { extents, origin } => GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents,
+                origin: RectangleOrigin::CustomCenter(origin),
+            },
+            draw_mode,
+            transform,
+        ),
+        BlockShapeFile::Ellipse { radii } => GeometryBuilder::build_as(
+            &shapes::Ellipse {
+                radii,
+                center: Vec2::ZERO,
+            },
+            draw_mode,
+            transform,
+        ),
+    }
+}
+
+/// ステージか選択が変わったら, エディタの図形をすべて描き直す
+fn draw_editor_stage(
+    mut commands: Commands,
+    editor_stage: Res<EditorStage>,
+    selection: Res<EditorSelection>,
+    shape_query: Query<Entity, With<EditorShape>>,
+) {
+    if !editor_stage.is_changed() && !selection.is_changed() {
+        return;
+    }
+    for ent in shape_query.iter() {
+        commands.entity(ent).despawn();
+    }
+    let stage = &editor_stage.stage;
+    let mut spawn = |bundle: ShapeBundle| {
+        commands.spawn_bundle(bundle).insert(EditorShape);
+    };
+    spawn(GeometryBuilder::build_as(
+        &shapes::Rectangle {
+            extents: stage.field_size,
+            origin: RectangleOrigin::Center,
+        },
+        DrawMode::Outlined {
+            fill_mode: FillMode::color(Color::LIME_GREEN),
+            outline_mode: StrokeMode::new(Color::BLACK, 10.0),
+        },
+        Transform::from_xyz(0.0, 0.0, 5.0),
+    ));
+    for (idx, block) in stage.blocks.iter().enumerate() {
+        let selected = *selection == EditorSelection::Block(idx);
+        // ブロックが重なったときに変な表示にならないようにz座標に微妙な差をつける
+        spawn(block_shape_bundle(
+            block,
+            12.0 + idx as f32 / 1000.0,
+            selected,
+        ));
+        // 移動するブロックはその軌道を線で示す
+        if let SlideStrategy::Manual { path, .. }
+        | SlideStrategy::AutoWrap { path, .. }
+        | SlideStrategy::Auto { path, .. } = &block.slide_strategy
+        {
+            if let BlockSlidePath::StandardLine { theta, width } = *path {
+                let delta = Vec2::from_angle(theta) * width;
+                spawn(GeometryBuilder::build_as(
+                    &shapes::Line(block.pos - delta, block.pos + delta),
+                    DrawMode::Stroke(StrokeMode::new(Color::WHITE, 2.0)),
+                    Transform::from_xyz(0.0, 0.0, 13.0),
+                ));
+            }
+        }
+    }
+    for (idx, goal) in stage.goals.iter().enumerate() {
+        let selected = *selection == EditorSelection::Goal(idx);
+        spawn(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius: goal.radius,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::GRAY),
+                outline_mode: if selected {
+                    outline(true)
+                } else {
+                    StrokeMode::new(Color::BLUE, 8.0)
+                },
+            },
+            Transform::from_translation(goal.pos.extend(8.0)),
+        ));
+    }
+    for (idx, switch) in stage.switches.iter().enumerate() {
+        let selected = *selection == EditorSelection::Switch(idx);
+        spawn(GeometryBuilder::build_as(
+            &shapes::Rectangle {
+                extents: switch.extents,
+                origin: RectangleOrigin::Center,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::DARK_GREEN),
+                outline_mode: outline(selected),
+            },
+            Transform::from_translation(switch.pos.extend(9.0)),
+        ));
+    }
+    spawn(GeometryBuilder::build_as(
+        &construct_launcher_shape(),
+        DrawMode::Outlined {
+            fill_mode: FillMode::color(Color::BLUE),
+            outline_mode: outline(*selection == EditorSelection::Launcher),
+        },
+        Transform {
+            translation: stage.launcher.pos.extend(15.0),
+            rotation: Quat::from_rotation_z(stage.launcher.default_angle),
+            ..default()
+        },
+    ));
+}
+
+/// 選択中のものの設定の説明
+fn selection_description(stage: &StageFile, selection: EditorSelection) -> String {
+    match selection {
+        EditorSelection::Launcher => {
+            let launcher = &stage.launcher;
+            format!(
+                "launcher  pos: ({:.0}, {:.0})  angle: {:.0}  speed: {:.3}",
+                launcher.pos.x,
+                launcher.pos.y,
+                launcher.default_angle.to_degrees(),
+                launcher.rotate_speed
+            )
+        }
+        EditorSelection::Block(idx) => {
+            let block = &stage.blocks[idx];
+            let shape = match block.shape {
+                BlockShapeFile::Rect { extents, .. } => {
+                    format!("rect {:.0}x{:.0}", extents.x, extents.y)
+                }
+                BlockShapeFile::Ellipse { radii } => {
+                    format!("ellipse {:.0}x{:.0}", radii.x * 2.0, radii.y * 2.0)
+                }
+            };
+            format!(
+                "block {}  {}  pos: ({:.0}, {:.0})  angle: {:.0}\nrotate: {:?}\nslide: {:?}\nattachment: {:?}",
+                idx,
+                shape,
+                block.pos.x,
+                block.pos.y,
+                block.default_angle.to_degrees(),
+                block.rotate_strategy,
+                block.slide_strategy,
+                block.attachment
+            )
+        }
+        EditorSelection::Goal(idx) => {
+            let goal = &stage.goals[idx];
+            format!(
+                "goal {}  pos: ({:.0}, {:.0})  radius: {:.0}  score: {}",
+                idx, goal.pos.x, goal.pos.y, goal.radius, goal.score
+            )
+        }
+        EditorSelection::Switch(idx) => {
+            let switch = &stage.switches[idx];
+            format!(
+                "switch {}  pos: ({:.0}, {:.0})  size: {:.0}x{:.0}  target: {}",
+                idx,
+                switch.pos.x,
+                switch.pos.y,
+                switch.extents.x,
+                switch.extents.y,
+                switch.target_id
+            )
+        }
+    }
+}
+
+/// ステージ全体と選択中のものの設定をパネルに表示する
+fn update_editor_panel(
+    editor_stage: Res<EditorStage>,
+    selection: Res<EditorSelection>,
+    mut panel_query: Query<&mut Text, With<EditorPanel>>,
+) {
+    if !editor_stage.is_changed() && !selection.is_changed() {
+        return;
+    }
+    let stage = &editor_stage.stage;
+    let metal = stage
+        .balls
+        .iter()
+        .filter(|ball_type| **ball_type == BallType::Metal)
+        .count();
+    let text = format!(
+        "{}{}  field: {:.0}x{:.0}  balls: {} normal, {} metal\n{}",
+        stage.title,
+        if editor_stage.modified { " *" } else { "" },
+        stage.field_size.x,
+        stage.field_size.y,
+        stage.balls.len() - metal,
+        metal,
+        selection_description(stage, *selection)
+    );
+    for mut panel in panel_query.iter_mut() {
+        panel.sections[0].value = text.clone();
+    }
+}
+
+/// 編集中のステージをそのまま遊ぶ. ルールは前回選んだものを使う
+fn start_test_play(
+    mut commands: Commands,
+    key_in: Res<Input<KeyCode>>,
+    editor_stage: Res<EditorStage>,
+    settings: Res<GameSettings>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if key_in.any_just_pressed([KeyCode::T, KeyCode::F5]) {
+        commands.insert_resource(editor_stage.stage.to_stage_info());
+        commands.insert_resource(GameRule::from(settings.rule));
+        commands.insert_resource(EditorSession);
+        app_state.set(AppState::Loading).unwrap();
+    }
+}
+
+/// メニューに戻る. 保存していない変更も次にエディタに入ったときのために残しておく.
+/// テストプレイと同時に押されたときはテストプレイを優先する
+fn back_to_menu(action_in: Res<ActionInput>, mut app_state: ResMut<State<AppState>>) {
+    if action_in.just_pressed(InputAction::Back) {
+        // テストプレイへの切り替えが先に予約されていれば失敗するので, そのままにする
+        let _ = app_state.set(AppState::Menu);
+    }
+}
+
+/// エディタを抜けるときに図形とパネルを消してカメラを元に戻す
+#[allow(clippy::type_complexity)]
+fn exit_editor(
+    mut commands: Commands,
+    editor_query: Query<Entity, Or<(With<EditorShape>, With<EditorPanel>, With<EditorHelp>)>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<FieldCamera>>,
+) {
+    for ent in editor_query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    commands.remove_resource::<EditorDrag>();
+    for (mut trans, mut projection) in camera_query.iter_mut() {
+        trans.translation = Vec2::ZERO.extend(trans.translation.z);
+        projection.scale = 1.0;
+    }
+}
+
+pub struct EditorPlugin;
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Editor).with_system(enter_editor));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Editor)
+                .with_system(edit_with_mouse.label("editor_edit").label("editor_mouse"))
+                // ドラッグをやめたのがマウスの操作に反映されるのは次のフレームなので, マウスより後に動かす
+                .with_system(edit_with_keys.label("editor_edit").after("editor_mouse"))
+                .with_system(fit_editor_camera.after("editor_edit"))
+                .with_system(draw_editor_stage.after("editor_edit"))
+                .with_system(update_editor_panel.after("editor_edit"))
+                .with_system(
+                    start_test_play
+                        .label("editor_test_play")
+                        .after("editor_edit"),
+                )
+                .with_system(back_to_menu.after("editor_test_play")),
+        );
+        app.add_system_set(SystemSet::on_exit(AppState::Editor).with_system(exit_editor));
+    }
+}
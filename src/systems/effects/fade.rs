@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::components::camera::hud_layer;
 use crate::components::effects::fade::FadeEffect;
 use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
 
@@ -21,6 +22,7 @@ pub fn register_fade(commands: &mut Commands, delta: f32, color: Color) {
             transform: Transform::from_translation(Vec3::new(0.0, 0.0, 99.9)),
             ..Default::default()
         })
+        .insert(hud_layer())
         .insert(FadeEffect {
             alpha: init_alpha,
             target_alpha: color.a(),
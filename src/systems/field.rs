@@ -1,27 +1,30 @@
 use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::*;
 
-use crate::AppState;
+use crate::{components::field::Field, stages::structs::StageInfo, AppState};
 
 pub const FIELD_WIDTH: f32 = 960.0;
 pub const FIELD_HEIGHT: f32 = 540.0;
 
-fn set_field(mut commands: Commands, server: Res<AssetServer>) {
+/// ステージで指定された大きさのフィールドを置く
+fn set_field(mut commands: Commands, stage_info: Res<StageInfo>) {
     let field_shape = shapes::Rectangle {
-        extents: Vec2::new(FIELD_WIDTH, FIELD_HEIGHT),
+        extents: stage_info.field_size,
         origin: RectangleOrigin::Center,
     };
-    commands.spawn_bundle(GeometryBuilder::build_as(
-        &field_shape,
-        DrawMode::Outlined {
-            fill_mode: FillMode::color(Color::LIME_GREEN),
-            outline_mode: StrokeMode::new(Color::BLACK, 10.0),
-        },
-        Transform {
-            translation: Vec3::new(0.0, 0.0, 5.0),
-            ..Default::default()
-        },
-    ));
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &field_shape,
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::LIME_GREEN),
+                outline_mode: StrokeMode::new(Color::BLACK, 10.0),
+            },
+            Transform {
+                translation: Vec3::new(0.0, 0.0, 5.0),
+                ..Default::default()
+            },
+        ))
+        .insert(Field);
 }
 
 pub struct FieldPlugin;
@@ -2,16 +2,36 @@ use bevy::prelude::*;
 
 use crate::{
     components::{
+        ball::{Ball, BallNocking, GoalinBall},
+        block_attach::switch::{SwitchPressedOnce, SwitchTile},
         game::{
-            GameRule, GoaledBall, InitialBallNum, NowGameOver, OperationAmount, PassedTime,
-            ResultInfoStorage, Score,
+            stroke_term, BounceCount, CollectedItems, CourseProgress, GameRule, GoaledBall,
+            InitialBallNum, LongestAirborne, LostBall, MaxBallSpeed, NextGoalOrder, NowGameOver,
+            OperationAmount, PassedTime, ReachedGoals, ResultInfoStorage, Score, StrokeCount,
+            WinCondition, WinConditionMet,
         },
+        goal::GoalHole,
         info::{RemainingTime, WaitForResultDisplay},
+        input::{ActionInput, InputAction},
+        item::Checkpoint,
+        launcher::{BallMagazine, LauncherState},
+        physics::velocity::Velocity,
+        rewind::RewindCount,
+        settings::GameSettings,
+        time_scale::SlowMotion,
         timer::CountDownTimer,
     },
     events::{
-        ball::SetBallEvent, block::SpawnBlockEvent, game::GameOverEvent, goal::SpawnGoalEvent,
-        launcher::SpawnLauncherEvent, switch::SpawnSwitchEvent, ToSpawnEvent,
+        ball::{LaunchBallEvent, SetBallEvent},
+        block::SpawnBlockEvent,
+        game::{GameOverEvent, SuspendGameEvent},
+        goal::SpawnGoalEvent,
+        hazard::SpawnHazardEvent,
+        item::{SpawnCheckpointEvent, SpawnItemEvent},
+        launcher::SpawnLauncherEvent,
+        switch::SpawnSwitchEvent,
+        time_scale::SpawnTimeScaleZoneEvent,
+        ToSpawnEvent,
     },
     stages::structs::StageInfo,
     AppState,
@@ -26,6 +46,7 @@ fn init_game(mut commands: Commands, entities: Query<Entity>) {
 }
 
 /// ステージ情報から様々なものを出現させる
+#[allow(clippy::too_many_arguments)]
 fn spawn_stage_entities(
     mut commands: Commands,
     stage_info: Option<Res<StageInfo>>,
@@ -34,6 +55,10 @@ fn spawn_stage_entities(
     mut goal_event_writer: EventWriter<SpawnGoalEvent>,
     mut ball_event_writer: EventWriter<SetBallEvent>,
     mut switch_event_writer: EventWriter<SpawnSwitchEvent>,
+    mut item_event_writer: EventWriter<SpawnItemEvent>,
+    mut checkpoint_event_writer: EventWriter<SpawnCheckpointEvent>,
+    mut hazard_event_writer: EventWriter<SpawnHazardEvent>,
+    mut time_scale_zone_event_writer: EventWriter<SpawnTimeScaleZoneEvent>,
 ) {
     // info!("spawn stage entities");
     let stage_info = stage_info.unwrap().clone();
@@ -43,6 +68,10 @@ fn spawn_stage_entities(
     let ball_list = stage_info.balls;
     let switch_list = stage_info.switches;
     let gravity = stage_info.gravity;
+    let item_list = stage_info.items;
+    let checkpoint_list = stage_info.checkpoints;
+    let hazard_list = stage_info.hazards;
+    let time_scale_zone_list = stage_info.time_scale_zones;
     commands.insert_resource(InitialBallNum(ball_list.len() as u32));
 
     // 残り時間タイマー用意
@@ -65,6 +94,18 @@ fn spawn_stage_entities(
     for switch in switch_list {
         switch_event_writer.send(switch.to_spawn_event());
     }
+    for item in item_list {
+        item_event_writer.send(item.to_spawn_event());
+    }
+    for checkpoint in checkpoint_list {
+        checkpoint_event_writer.send(checkpoint.to_spawn_event());
+    }
+    for hazard in hazard_list {
+        hazard_event_writer.send(hazard.to_spawn_event());
+    }
+    for zone in time_scale_zone_list {
+        time_scale_zone_event_writer.send(zone.to_spawn_event());
+    }
     if let Some(gravity) = gravity {
         commands.spawn().insert(gravity);
         info!("gravity spawn    ");
@@ -73,34 +114,109 @@ fn spawn_stage_entities(
 
 /// スコアに関わる値を更新する
 fn update_score_resources(
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
     mut operation_amount: ResMut<OperationAmount>,
     mut passed_time: ResMut<PassedTime>,
 ) {
-    if key_in.any_pressed([KeyCode::Left, KeyCode::Right]) {
+    if action_in.any_pressed([InputAction::RotateLeft, InputAction::RotateRight]) {
         operation_amount.0 += 1;
     }
     passed_time.0 += 1;
 }
 
-/// ルールによって異なる条件を満たしたらゲームオーバーイベントを送る
+/// 発射されたボールの最高速度を記録する
+fn track_max_ball_speed(
+    ball_query: Query<&Velocity, (With<Ball>, Without<BallNocking>)>,
+    mut max_ball_speed: ResMut<MaxBallSpeed>,
+) {
+    for vel in ball_query.iter() {
+        max_ball_speed.0 = max_ball_speed.0.max(vel.0.length());
+    }
+}
+
+/// ボールを発射するたびに打数を数える
+fn count_strokes(
+    mut launch_ball_event_reader: EventReader<LaunchBallEvent>,
+    mut stroke_count: ResMut<StrokeCount>,
+) {
+    for _ in launch_ball_event_reader.iter() {
+        stroke_count.0 += 1;
+    }
+}
+
+/// ステージ固有のクリア条件をすべて満たしたかを調べる. 一度満たしたら以降はそのまま.
+fn check_win_condition(
+    stage_info: Res<StageInfo>,
+    score: Res<Score>,
+    reached_goals: Res<ReachedGoals>,
+    longest_airborne: Res<LongestAirborne>,
+    switch_query: Query<Option<&SwitchPressedOnce>, With<SwitchTile>>,
+    mut win_condition_met: ResMut<WinConditionMet>,
+) {
+    if win_condition_met.0 || stage_info.win_conditions.is_empty() {
+        return;
+    }
+    win_condition_met.0 = stage_info
+        .win_conditions
+        .iter()
+        .all(|condition| match *condition {
+            WinCondition::ReachGoal(name) => reached_goals.0.contains(&name),
+            // スイッチが一つもなければ押しようがないので満たさない
+            WinCondition::HitAllSwitches => {
+                !switch_query.is_empty() && switch_query.iter().all(|pressed| pressed.is_some())
+            }
+            WinCondition::KeepAirborne(frames) => longest_airborne.0 >= frames,
+            WinCondition::ScoreAtLeast(target) => score.0 >= target,
+        });
+}
+
+/// ルールによって異なる条件を満たしたらゲームオーバーイベントを送る.
+/// ステージ固有のクリア条件がある場合はルールの代わりにそれを使う.
+/// 番号付きゴールがある場合はすべて順番通りに入れるまでクリアとしない.
+/// すべてのゴールが閉じたらそれ以上得点できないので終了する.
+/// ボールをすべて使い切り, 場に残っているボールもなくなった場合も終了する.
+#[allow(clippy::too_many_arguments)]
 fn game_over_check(
     rule: Res<GameRule>,
     timer_query: Query<&CountDownTimer, With<RemainingTime>>,
+    goal_query: Query<&GoalHole>,
+    goal_in_ball_query: Query<&GoalinBall>,
+    ball_query: Query<&Ball>,
+    magazine_query: Query<&BallMagazine>,
+    launcher_query: Query<&LauncherState>,
     goaled_ball: Res<GoaledBall>,
     init_ball_num: Res<InitialBallNum>,
+    next_goal_order: Res<NextGoalOrder>,
+    (stage_info, win_condition_met): (Res<StageInfo>, Res<WinConditionMet>),
     mut game_over_event_writer: EventWriter<GameOverEvent>,
     is_gameover: Option<Res<NowGameOver>>,
 ) {
     if is_gameover.is_none() {
         if let Ok(timer) = timer_query.get_single() {
-            if timer.is_finished()
-                || match *rule {
+            // ゴールに吸い込まれている途中のボールの得点が入るまでは待つ
+            let all_goals_closed = !goal_query.is_empty()
+                && goal_query.iter().all(|goal| goal.is_closed())
+                && goal_in_ball_query.is_empty();
+            // 番えている途中のボールはまだ出現していないことがあるのでランチャーの状態も見る
+            let all_balls_used = magazine_query.iter().all(|mag| mag.balls.is_empty())
+                && ball_query.is_empty()
+                && launcher_query
+                    .iter()
+                    .all(|state| matches!(state, LauncherState::Waiting));
+            let sequence_completed = !goal_query
+                .iter()
+                .any(|goal| goal.is_pending_in_order(next_goal_order.0));
+            let cleared = if stage_info.win_conditions.is_empty() {
+                match *rule {
                     GameRule::BallScore => init_ball_num.0 == goaled_ball.0,
-                    GameRule::LittleOperation => goaled_ball.0 != 0,
-                    GameRule::TimeAttack => goaled_ball.0 != 0,
+                    GameRule::LittleOperation => goaled_ball.0 != 0 && sequence_completed,
+                    GameRule::TimeAttack => goaled_ball.0 != 0 && sequence_completed,
+                    GameRule::Strokes => goaled_ball.0 != 0 && sequence_completed,
                 }
-            {
+            } else {
+                win_condition_met.0
+            };
+            if timer.is_finished() || all_goals_closed || all_balls_used || cleared {
                 info!("send game over event");
                 game_over_event_writer.send(GameOverEvent);
             }
@@ -109,12 +225,24 @@ fn game_over_check(
 }
 
 /// ゲームオーバーイベントを受け取ったらスコアを計算し保存する.
+/// 打数ルールでゴールできなかった場合はパーの2倍の打数とする.
+/// コースモードの場合は合計打数に加算する.
+#[allow(clippy::too_many_arguments)]
 fn save_result_score(
     mut commands: Commands,
     rule: Res<GameRule>,
+    stage_info: Res<StageInfo>,
     score: Res<Score>,
     operation_amount: Res<OperationAmount>,
     passed_time: Res<PassedTime>,
+    collected_items: Res<CollectedItems>,
+    lost_ball: Res<LostBall>,
+    stroke_count: Res<StrokeCount>,
+    (goaled_ball, win_condition_met): (Res<GoaledBall>, Res<WinConditionMet>),
+    (bounce_count, max_ball_speed): (Res<BounceCount>, Res<MaxBallSpeed>),
+    (rewind_count, slow_motion): (Res<RewindCount>, Res<SlowMotion>),
+    mut course: Option<ResMut<CourseProgress>>,
+    checkpoint_query: Query<&Checkpoint>,
     mut game_over_event_reader: EventReader<GameOverEvent>,
 ) {
     for _ in game_over_event_reader.iter() {
@@ -122,9 +250,61 @@ fn save_result_score(
             GameRule::BallScore => score.0,
             GameRule::LittleOperation => operation_amount.0,
             GameRule::TimeAttack => passed_time.0,
+            GameRule::Strokes => stroke_count.0,
+        };
+        let result_score = rule.apply_lost_ball_penalty(result_score, lost_ball.0);
+        let result_score = rule.apply_rewind_penalty(result_score, rewind_count.0);
+        // ステージ固有のクリア条件がある場合はそれを満たしたときのみクリアとする
+        let holed_out = if stage_info.win_conditions.is_empty() {
+            goaled_ball.0 != 0
+        } else {
+            win_condition_met.0
+        };
+        let (result_score, par, headline) = if let GameRule::Strokes = *rule {
+            let par = stage_info.par;
+            if holed_out {
+                (
+                    result_score,
+                    Some(par),
+                    Some(stroke_term(result_score, par)),
+                )
+            } else {
+                (par * 2, Some(par), Some("did not finish".to_string()))
+            }
+        } else {
+            (result_score, None, None)
         };
+        let course_total = course.as_mut().map(|course| {
+            course.total_strokes += result_score;
+            course.total_par += stage_info.par;
+            info!(
+                "course {}: {} strokes (par {})",
+                course.title, course.total_strokes, course.total_par
+            );
+            (course.total_strokes, course.total_par)
+        });
+        let stars =
+            stage_info
+                .star_thresholds()
+                .stars(holed_out, passed_time.0, operation_amount.0);
         commands.insert_resource(ResultInfoStorage {
             score: result_score,
+            stars,
+            passed_time: passed_time.0,
+            operations: operation_amount.0,
+            balls_used: stroke_count.0,
+            bounces: bounce_count.0,
+            max_speed: max_ball_speed.0,
+            goals: goaled_ball.0,
+            collected_items: (!stage_info.items.is_empty()).then_some(collected_items.0),
+            passed_checkpoints: (!checkpoint_query.is_empty())
+                .then(|| checkpoint_query.iter().filter(|cp| cp.passed).count() as u32),
+            lost_balls: lost_ball.0,
+            rewinds: rewind_count.0,
+            slow_motion: slow_motion.used,
+            par,
+            headline,
+            course_total,
         });
         info!("result score: {}", result_score);
     }
@@ -148,52 +328,81 @@ fn game_over(
     }
 }
 
+/// コースモードで次のステージがあればそこへ進み, なければメニューに戻る
 fn return_to_title_after_gameover(
+    mut commands: Commands,
     is_gameover: Option<Res<NowGameOver>>,
     timer_query: Query<&WaitForResultDisplay>,
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
+    course: Option<ResMut<CourseProgress>>,
     mut app_state: ResMut<State<AppState>>,
 ) {
     // ゲームオーバーでかつ待ちタイマーが存在しなければメニューに戻る受付をする
     if is_gameover.is_none() || !timer_query.is_empty() {
         return;
     }
-    if key_in.just_pressed(KeyCode::Z) {
-        app_state.set(AppState::BackToMenu).unwrap();
+    if action_in.just_pressed(InputAction::Action) {
+        match course {
+            Some(mut course) if course.has_next() => {
+                course.current += 1;
+                commands.insert_resource(course.current_stage());
+                app_state.set(AppState::Loading).unwrap();
+            }
+            _ => app_state.set(AppState::BackToMenu).unwrap(),
+        }
     }
 }
 
-/// ステージを最初からやり直す
+/// ステージを最初からやり直す. 同じフレームで一時停止などが先に決まっていれば何もしない
 fn retry(
     mut commands: Commands,
     is_gameover: Option<Res<NowGameOver>>,
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
     timer_query: Query<Entity, (With<CountDownTimer>, With<RemainingTime>)>,
     mut app_state: ResMut<State<AppState>>,
 ) {
-    if key_in.just_pressed(KeyCode::R) && is_gameover.is_none() {
+    if action_in.just_pressed(InputAction::Retry)
+        && is_gameover.is_none()
+        && app_state.set(AppState::Loading).is_ok()
+    {
         // タイマー削除する
         for ent in timer_query.iter() {
             commands.entity(ent).despawn();
         }
-        app_state.set(AppState::Loading).unwrap();
     }
 }
 
-/// メニューに戻る
+/// メニューに戻る. 途中の状態は保存しておき, メニューから再開できる.
+/// 同じフレームで一時停止などが先に決まっていれば何もしない
 fn return_to_title_immediately(
     mut commands: Commands,
     is_gameover: Option<Res<NowGameOver>>,
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
     timer_query: Query<Entity, (With<CountDownTimer>, With<RemainingTime>)>,
+    mut suspend_event_writer: EventWriter<SuspendGameEvent>,
     mut app_state: ResMut<State<AppState>>,
 ) {
-    if key_in.just_pressed(KeyCode::B) && is_gameover.is_none() {
+    if action_in.just_pressed(InputAction::Back)
+        && is_gameover.is_none()
+        && app_state.set(AppState::BackToMenu).is_ok()
+    {
+        suspend_event_writer.send(SuspendGameEvent);
         // タイマー削除する
         for ent in timer_query.iter() {
             commands.entity(ent).despawn();
         }
-        app_state.set(AppState::BackToMenu).unwrap();
+    }
+}
+
+/// メニューに戻るときにコースを終える. コースの間だけ使う打数ルールから, メニューで選んでいたルールに戻す
+fn end_course(
+    mut commands: Commands,
+    course: Option<Res<CourseProgress>>,
+    settings: Res<GameSettings>,
+) {
+    if course.is_some() {
+        commands.remove_resource::<CourseProgress>();
+        commands.insert_resource(GameRule::from(settings.rule));
     }
 }
 
@@ -223,12 +432,38 @@ impl Plugin for GameManagePlugin {
                 .label("spawn_stage_entities"),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(update_score_resources),
+            SystemSet::on_update(AppState::Game).with_system(
+                track_max_ball_speed
+                    .after("item:checkpoint_passed")
+                    .label("game:track_max_ball_speed"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                count_strokes
+                    .after("game:track_max_ball_speed")
+                    .label("game:count_strokes"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                update_score_resources
+                    .after("game:count_strokes")
+                    .label("game:update_score"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                check_win_condition
+                    .after("game:update_score")
+                    .label("check_win_condition"),
+            ),
         );
         app.add_system_set(
             SystemSet::on_update(AppState::Game).with_system(
                 game_over_check
                     .label("gameover_check")
+                    .after("check_win_condition")
                     .after("count_down_update"),
             ),
         );
@@ -240,19 +475,38 @@ impl Plugin for GameManagePlugin {
             ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(game_over.after("save_score").after("count_down_update")),
+            SystemSet::on_update(AppState::Game).with_system(
+                game_over
+                    .after("save_score")
+                    .after("count_down_update")
+                    .label("game_over"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                return_to_title_after_gameover
+                    .after("info_board:result_score")
+                    .label("return_to_title_after_gameover"),
+            ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(return_to_title_after_gameover),
+            SystemSet::on_update(AppState::Game)
+                .with_system(retry.after("return_to_title_after_gameover").label("retry")),
         );
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(retry));
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(return_to_title_immediately),
+            SystemSet::on_update(AppState::Game).with_system(
+                return_to_title_immediately
+                    .after("retry")
+                    .label("return_to_title"),
+            ),
         );
         app.add_system_set(
             SystemSet::on_exit(AppState::Game)
                 .with_system(deconstruct_objects.label("deconstruct")),
         );
+        app.add_system_set(
+            SystemSet::on_enter(AppState::BackToMenu)
+                .with_system(end_course.after("return_to_title_after_gameover")),
+        );
     }
 }
@@ -1,44 +1,146 @@
 use bevy::prelude::*;
-use bevy_prototype_lyon::prelude::*;
+use bevy_prototype_lyon::{entity::ShapeBundle, prelude::*};
 
 use crate::{
     components::{
         ball::GoalinBall,
+        block::{BlockOriginalPos, BlockTransform},
         game::{GoaledBall, Score},
         goal::GoalHole,
         physics::{position::Position, velocity::Velocity},
+        suspend::StageObjectId,
     },
     events::goal::SpawnGoalEvent,
     AppState,
 };
 
-fn spawn_goal(mut commands: Commands, mut event_listener: EventReader<SpawnGoalEvent>) {
-    for ev in event_listener.iter() {
-        let goal_shape = shapes::Circle {
-            radius: ev.radius,
+/// ゴールの見た目を作る. 受け入れるボールが限定されていればその色で塗る.
+fn goal_shape_bundle(goal: &GoalHole, translation: Vec3) -> ShapeBundle {
+    let goal_shape = shapes::Circle {
+        radius: goal.radius,
+        ..Default::default()
+    };
+    let outline_color = match goal.score {
+        1 => Color::BLUE,
+        2 => Color::YELLOW_GREEN,
+        3 => Color::RED,
+        _ => Color::DARK_GRAY,
+    };
+    GeometryBuilder::build_as(
+        &goal_shape,
+        DrawMode::Outlined {
+            fill_mode: FillMode::color(goal_fill_color(goal)),
+            outline_mode: StrokeMode::new(outline_color, 8.0),
+        },
+        Transform {
+            translation,
             ..Default::default()
-        };
-        let outline_color = match ev.score {
-            1 => Color::BLUE,
-            2 => Color::YELLOW_GREEN,
-            3 => Color::RED,
-            _ => Color::DARK_GRAY,
-        };
+        },
+    )
+}
+
+/// ゴールの状態に応じた塗りつぶし色
+fn goal_fill_color(goal: &GoalHole) -> Color {
+    if goal.is_closed() {
+        return Color::BLACK;
+    }
+    match &goal.accept {
+        Some(accept) if accept.len() == 1 => accept[0].color(),
+        _ => Color::GRAY,
+    }
+}
+
+fn spawn_goal(mut commands: Commands, mut event_listener: EventReader<SpawnGoalEvent>) {
+    for (idx, ev) in event_listener.iter().enumerate() {
+        let goal = &ev.component;
+        let mut goal_bundle = goal_shape_bundle(goal, goal.pos.extend(8.0));
+        // 出現したフレームの当たり判定が原点で行われないように, 伝播を待たずに位置を入れておく
+        goal_bundle.global_transform = GlobalTransform::from(goal_bundle.transform);
         commands
-            .spawn_bundle(GeometryBuilder::build_as(
-                &goal_shape,
-                DrawMode::Outlined {
-                    fill_mode: FillMode::color(Color::GRAY),
-                    outline_mode: StrokeMode::new(outline_color, 8.0),
-                },
-                Transform {
-                    translation: ev.pos.extend(8.0),
-                    ..Default::default()
-                },
-            ))
-            .insert(GoalHole::from(ev))
-            .insert(Position(ev.pos))
-            .insert(Velocity(Vec2::new(0.0, 0.0)));
+            .spawn_bundle(goal_bundle)
+            .insert(goal.clone())
+            .insert(BlockOriginalPos(goal.pos))
+            .insert(BlockTransform::new(0.0, 0.0))
+            .insert(ev.slide_strategy.clone())
+            .insert(Position(goal.pos))
+            .insert(Velocity(Vec2::new(0.0, 0.0)))
+            .insert(StageObjectId::Goal(idx));
+    }
+}
+
+/// ブロック出現時にブロックの子としてゴールを出現させるときに使う関数.
+/// `goal.pos`はブロックから見た位置で, `block_trans`は出現時のブロックの位置と向き
+pub fn spawn_attached_goal(
+    commands: &mut Commands,
+    block_ent: Entity,
+    block_trans: &Transform,
+    goal: &GoalHole,
+    block_idx: usize,
+) {
+    // ブロックより奥に描画されるようにする
+    let mut goal_bundle = goal_shape_bundle(goal, goal.pos.extend(-4.0));
+    // 出現したフレームから当たり判定がブロックに付いた位置で行われるように, 親の位置を反映しておく
+    goal_bundle.global_transform =
+        GlobalTransform::from(*block_trans).mul_transform(goal_bundle.transform);
+    let goal = GoalHole {
+        pos: goal_bundle.global_transform.translation().truncate(),
+        ..goal.clone()
+    };
+    commands.entity(block_ent).with_children(|parent| {
+        parent
+            .spawn_bundle(goal_bundle)
+            .insert(goal)
+            .insert(StageObjectId::BlockGoal(block_idx));
+    });
+}
+
+/// 番号付きゴールに番号を表示する
+fn spawn_goal_order_label(
+    mut commands: Commands,
+    goal_query: Query<(&GoalHole, Entity), Added<GoalHole>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (goal, ent) in goal_query.iter() {
+        if let Some(order) = goal.order {
+            commands.entity(ent).with_children(|parent| {
+                parent.spawn_bundle(Text2dBundle {
+                    text: Text::from_section(
+                        order.to_string(),
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: goal.radius,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_alignment(TextAlignment::CENTER),
+                    transform: Transform::from_translation(Vec3::Z),
+                    ..default()
+                });
+            });
+        }
+    }
+}
+
+/// 動くゴールやブロックに付いたゴールの位置を当たり判定用に更新する
+fn sync_goal_pos(mut goal_query: Query<(&mut GoalHole, &GlobalTransform)>) {
+    for (mut goal, glb_trans) in goal_query.iter_mut() {
+        let pos = glb_trans.translation().truncate();
+        if goal.pos != pos {
+            goal.pos = pos;
+        }
+    }
+}
+
+/// ゴールの状態が変わったら塗りつぶし色を変える
+fn update_goal_appearance(mut goal_query: Query<(&GoalHole, &mut DrawMode), Changed<GoalHole>>) {
+    for (goal, mut draw_mode) in goal_query.iter_mut() {
+        if let DrawMode::Outlined {
+            ref mut fill_mode,
+            outline_mode: _,
+        } = *draw_mode
+        {
+            fill_mode.color = goal_fill_color(goal);
+        }
     }
 }
 
@@ -68,7 +170,82 @@ impl Plugin for GoalPlugin {
                 .after("spawn_stage_entities"),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(execute_goaled_in_ball),
+            SystemSet::on_update(AppState::Game).with_system(
+                spawn_goal_order_label
+                    .after("goal:update_appearance")
+                    .label("goal:spawn_order_label"),
+            ),
         );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                sync_goal_pos
+                    .after("switch:deactivate_switch")
+                    .label("goal:sync_goal_pos"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                update_goal_appearance
+                    .after("highlight_control_group")
+                    .label("goal:update_appearance"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                execute_goaled_in_ball
+                    .after("item:execute_effect")
+                    .label("goal:execute_goaled"),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::{
+        components::block_attach::BlockAttachment,
+        stages::file::{BlockFile, GoalFile, StageFile},
+        systems::simulate::{headless_app, start_game},
+    };
+
+    #[test]
+    fn goals_are_in_place_from_first_frame() {
+        let mut stage_info = StageFile {
+            goals: vec![GoalFile {
+                pos: Vec2::new(-200.0, 150.0),
+                ..Default::default()
+            }],
+            blocks: vec![BlockFile {
+                pos: Vec2::new(200.0, 100.0),
+                default_angle: FRAC_PI_2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+        .to_stage_info();
+        // ブロックの右に付けたゴールは, ブロックが90度回っているので上に来る
+        stage_info.blocks[4]
+            .block_attachment
+            .push(BlockAttachment::Goal(GoalHole::new(
+                Vec2::new(60.0, 0.0),
+                20.0,
+                1,
+            )));
+        let mut app = headless_app(&stage_info);
+        start_game(&mut app);
+
+        let mut goal_pos = app
+            .world
+            .query::<&GoalHole>()
+            .iter(&app.world)
+            .map(|goal| goal.pos)
+            .collect::<Vec<_>>();
+        goal_pos.sort_by(|a, b| a.x.total_cmp(&b.x));
+        assert_eq!(goal_pos.len(), 2);
+        assert!(goal_pos[0].distance(Vec2::new(-200.0, 150.0)) < 1e-3);
+        assert!(goal_pos[1].distance(Vec2::new(200.0, 160.0)) < 1e-3);
     }
 }
@@ -0,0 +1,199 @@
+//! ボールを壊す危険物と, ボールを失ったときの処理
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    components::{
+        ball::GoalinBall,
+        game::LostBall,
+        hazard::{BallFragment, HazardZone},
+        physics::{position::Position, velocity::Velocity},
+        timer::CountDownTimer,
+    },
+    events::{
+        goal::BallGoaledEvent,
+        hazard::{BallLostEvent, SpawnHazardEvent},
+    },
+    AppState,
+};
+
+/// ボールが壊れたときに飛び散る破片の数
+const FRAGMENT_NUM: u32 = 8;
+
+fn spawn_hazard_zone(mut commands: Commands, mut event_listener: EventReader<SpawnHazardEvent>) {
+    for ev in event_listener.iter() {
+        let zone_shape = shapes::Rectangle {
+            extents: ev.extents,
+            origin: RectangleOrigin::Center,
+        };
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &zone_shape,
+                DrawMode::Outlined {
+                    fill_mode: FillMode::color(ev.kind.color()),
+                    outline_mode: StrokeMode::new(Color::DARK_GRAY, 2.0),
+                },
+                Transform {
+                    translation: ev.pos.extend(7.0),
+                    ..Default::default()
+                },
+            ))
+            .insert(HazardZone {
+                kind: ev.kind,
+                extents: ev.extents,
+            });
+    }
+}
+
+/// 失ったボールを削除し, 破片を飛び散らせる.
+/// 同じフレームでゴールにも入ったボールはゴールを優先し, 失わない
+fn destroy_lost_ball(
+    mut commands: Commands,
+    mut event_reader: EventReader<BallLostEvent>,
+    mut goaled_event_reader: EventReader<BallGoaledEvent>,
+    goal_in_ball_query: Query<(), With<GoalinBall>>,
+    mut lost_ball: ResMut<LostBall>,
+) {
+    // このフレームで入ったボールにはまだGoalinBallが付いていないので, イベントから調べる
+    let goaled = goaled_event_reader
+        .iter()
+        .map(|ev| ev.ball)
+        .collect::<Vec<Entity>>();
+    // 同じフレームで複数の危険物に触れた場合に二重に数えないようにする
+    let mut destroyed = Vec::<Entity>::new();
+    for ev in event_reader.iter() {
+        // ゴールに吸い込まれているボールは失わない
+        if destroyed.contains(&ev.ball)
+            || goaled.contains(&ev.ball)
+            || goal_in_ball_query.contains(ev.ball)
+        {
+            continue;
+        }
+        destroyed.push(ev.ball);
+        commands.entity(ev.ball).despawn();
+        if ev.cause.is_penalized() {
+            lost_ball.0 += 1;
+        }
+        info!("lost ball: {} ({:?})", lost_ball.0, ev.cause);
+
+        let fragment_shape = shapes::Circle {
+            radius: 4.0,
+            ..Default::default()
+        };
+        for i in 0..FRAGMENT_NUM {
+            let angle = std::f32::consts::TAU * i as f32 / FRAGMENT_NUM as f32;
+            commands
+                .spawn_bundle(GeometryBuilder::build_as(
+                    &fragment_shape,
+                    DrawMode::Fill(FillMode::color(ev.cause.color())),
+                    Transform {
+                        translation: ev.pos.extend(50.0),
+                        ..Default::default()
+                    },
+                ))
+                .insert(BallFragment)
+                .insert(Position(ev.pos))
+                .insert(Velocity(Vec2::from_angle(angle).rotate(Vec2::X) * 4.0))
+                .insert(CountDownTimer::new(30));
+        }
+    }
+}
+
+/// 破片を更新する. タイマーにより削除は自動的に行われる
+fn update_fragment(mut query: Query<(&mut Transform, &Position), With<BallFragment>>) {
+    for (mut trans, pos) in query.iter_mut() {
+        trans.translation = pos.0.extend(50.0);
+        trans.scale *= 0.95;
+    }
+}
+
+pub struct HazardPlugin;
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Game)
+                .with_system(spawn_hazard_zone)
+                .after("spawn_stage_entities"),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                destroy_lost_ball
+                    .after("collision:ball_lost")
+                    .after("ball:retire")
+                    .after("collision:goal_and_ball")
+                    .after("collision:item_and_ball")
+                    .label("hazard:destroy_lost_ball"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                update_fragment
+                    .after("info_board:update_balls")
+                    .label("hazard:update_fragment"),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+    use crate::{
+        components::{
+            ball::{BallNocking, BallType},
+            game::GoaledBall,
+            hazard::BallLostCause,
+        },
+        stages::file::{GoalFile, StageFile},
+        systems::{
+            ball::spawn_ball_entity,
+            simulate::{headless_app, start_game},
+        },
+    };
+
+    #[test]
+    fn ball_goaled_and_lost_in_same_frame_is_goaled() {
+        let stage_file = StageFile {
+            time: 600,
+            balls: vec![BallType::Normal, BallType::Normal],
+            ..Default::default()
+        };
+        let goal_pos = stage_file.launcher.pos + Vec2::new(300.0, 0.0);
+        let stage_info = StageFile {
+            goals: vec![GoalFile {
+                pos: goal_pos,
+                ..Default::default()
+            }],
+            ..stage_file
+        }
+        .to_stage_info();
+        let mut app = headless_app(&stage_info);
+        start_game(&mut app);
+
+        // ゴールの中心に止まっているボールを置き, 同じフレームで失ったことにする
+        let mut queue = CommandQueue::default();
+        let ball = {
+            let mut commands = Commands::new(&mut queue, &app.world);
+            let ball = spawn_ball_entity(&mut commands, BallType::Normal, goal_pos);
+            commands.entity(ball).remove::<BallNocking>();
+            ball
+        };
+        queue.apply(&mut app.world);
+        app.world.send_event(BallLostEvent {
+            ball,
+            pos: goal_pos,
+            cause: BallLostCause::OutOfField,
+        });
+        app.update();
+        assert!(app.world.get::<GoalinBall>(ball).is_some());
+
+        for _ in 0..60 {
+            app.update();
+        }
+        assert!(app.world.get_entity(ball).is_none());
+        assert_eq!(app.world.resource::<GoaledBall>().0, 1);
+        assert_eq!(app.world.resource::<LostBall>().0, 0);
+    }
+}
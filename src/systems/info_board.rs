@@ -3,21 +3,49 @@ use bevy::prelude::*;
 
 use crate::{
     components::{
-        game::{NowGameOver, ResultInfoStorage},
-        info::{ConsumingBall, RemainingBall, RemainingTime, ResultText, WaitForResultDisplay},
+        game::{CourseProgress, NowGameOver, ResultInfoStorage},
+        info::{
+            ConsumingBall, MagazineUpdating, RemainingBall, RemainingTime, ResultText,
+            WaitForResultDisplay,
+        },
         launcher::BallMagazine,
+        settings::GameSettings,
         timer::CountDownTimer,
     },
     events::ball::SpawnBallEvent,
+    stages::structs::StageInfo,
     AppState,
 };
 
+/// 残りボール表示で選択中のボールを拡大する倍率
+const SELECTED_BALL_SCALE: f32 = 1.5;
+
+/// リザルト画面で1列に並べる項目数
+const RESULT_ROWS: usize = 9;
+
+/// 残りボール表示でidx番目のボールを置く位置
+pub fn remaining_ball_display_pos(idx: usize) -> Vec2 {
+    Vec2::new(-200.0 + idx as f32 * 40.0, -350.0)
+}
+
 /// フレーム数を秒数の文字列に変換
 fn frame_to_second(frame: u32) -> String {
     format!("{:>02}", frame / 60)
 }
 
-fn init_note_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn init_note_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+    stage_info: Res<StageInfo>,
+) {
+    if !settings.show_guide {
+        return;
+    }
+    let mut note = "R: retry\nB: back to title\nP: pause".to_string();
+    if stage_info.grouped_control {
+        note += "\nC: switch group";
+    }
     commands.spawn_bundle(TextBundle {
         style: Style {
             position_type: PositionType::Absolute,
@@ -28,7 +56,7 @@ fn init_note_text(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         },
         text: Text::from_section(
-            "R: retry\nB: back to title",
+            note,
             TextStyle {
                 font: asset_server.load("fonts/ume-tgs5.ttf"),
                 font_size: 20.0,
@@ -75,18 +103,40 @@ fn show_remaining_time(mut timer_text: Query<(&mut Text, &CountDownTimer), With<
     }
 }
 
-/// ボール出現時に箱の先頭のボールに更新中マーカーを付与し, 箱から取り出す
+/// ボール出現時に箱の選択中のボールに消費中マーカーを付与し, 箱から取り出す
 fn pop_ball_from_magazine(
     mut commands: Commands,
-    mut magazine_query: Query<&mut BallMagazine>,
+    mut magazine_query: Query<(&mut BallMagazine, Entity)>,
     mut spawn_ball_event_reader: EventReader<SpawnBallEvent>,
 ) {
     for _ in spawn_ball_event_reader.iter() {
-        let mut mag = magazine_query.single_mut();
-        // TODO: VecDequeを使うと計算量を減らせる
-        let (_, top_ball_ent) = mag.balls.remove(0);
-        // 1つ目に消費中マーカーをつける
-        commands.entity(top_ball_ent).insert(ConsumingBall);
+        let (mut mag, mag_ent) = magazine_query.single_mut();
+        if let Some((_, selected_ball_ent)) = mag.take_selected() {
+            commands.entity(selected_ball_ent).insert(ConsumingBall);
+            commands.entity(mag_ent).insert(MagazineUpdating);
+        }
+    }
+}
+
+/// 箱の中身か選択が変わったときに残りボール表示を詰め直し, 選択中のボールを強調する
+fn refresh_remaining_balls_display(
+    mut commands: Commands,
+    magazine_query: Query<(&BallMagazine, Entity), With<MagazineUpdating>>,
+    mut remaining_ball_query: Query<&mut Transform, (With<RemainingBall>, Without<ConsumingBall>)>,
+) {
+    for (mag, mag_ent) in magazine_query.iter() {
+        for (idx, (_, ball_ent)) in mag.balls.iter().enumerate() {
+            if let Ok(mut trans) = remaining_ball_query.get_mut(*ball_ent) {
+                let z_coord = trans.translation.z;
+                trans.translation = remaining_ball_display_pos(idx).extend(z_coord);
+                trans.scale = if idx == mag.selected {
+                    Vec3::splat(SELECTED_BALL_SCALE)
+                } else {
+                    Vec3::ONE
+                };
+            }
+        }
+        commands.entity(mag_ent).remove::<MagazineUpdating>();
     }
 }
 
@@ -111,21 +161,71 @@ fn spawn_result_score(
     wait_timer: Query<&CountDownTimer, With<WaitForResultDisplay>>,
     is_gameover: Option<Res<NowGameOver>>,
     result_info: Option<Res<ResultInfoStorage>>,
+    course: Option<Res<CourseProgress>>,
     asset_server: Res<AssetServer>,
 ) {
     if let Ok(wait_timer) = wait_timer.get_single() {
         if is_gameover.is_some() && wait_timer.is_finished() {
             if let Some(result_info) = result_info {
+                if let Some(headline) = &result_info.headline {
+                    commands
+                        .spawn_bundle(TextBundle {
+                            style: Style {
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    top: Val::Px(70.0),
+                                    left: Val::Px(40.0),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            text: Text::from_section(
+                                headline,
+                                TextStyle {
+                                    font: asset_server.load("fonts/ume-tgs5.ttf"),
+                                    font_size: 60.0,
+                                    color: Color::GOLD,
+                                },
+                            ),
+                            ..default()
+                        })
+                        .insert(ResultText);
+                }
+                commands
+                    .spawn_bundle(TextBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                top: Val::Px(70.0),
+                                right: Val::Px(40.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        text: Text::from_section(
+                            result_info.stars_text(),
+                            TextStyle {
+                                font: asset_server.load("fonts/ume-tgs5.ttf"),
+                                font_size: 60.0,
+                                color: Color::GOLD,
+                            },
+                        ),
+                        ..default()
+                    })
+                    .insert(ResultText);
                 let display_contents = result_info.to_vector();
                 // ゲームオーバー中にタイマーが終了したら演出を開始させる
-                for (title, value) in display_contents.into_iter() {
+                for (idx, (title, value)) in display_contents.into_iter().enumerate() {
+                    // 縦に入り切らない分は右の列に並べる
+                    let row = idx % RESULT_ROWS;
+                    let column = idx / RESULT_ROWS;
                     commands
                         .spawn_bundle(TextBundle {
                             style: Style {
                                 position_type: PositionType::Absolute,
                                 position: UiRect {
-                                    top: Val::Px(140.0),
-                                    left: Val::Px(40.0),
+                                    top: Val::Px(140.0 + row as f32 * 50.0),
+                                    left: Val::Px(40.0 + column as f32 * 480.0),
                                     ..default()
                                 },
                                 ..default()
@@ -155,7 +255,11 @@ fn spawn_result_score(
                         ..default()
                     },
                     text: Text::from_section(
-                        "press Z to back.",
+                        if course.is_some_and(|course| course.has_next()) {
+                            "press Z to next stage."
+                        } else {
+                            "press Z to back."
+                        },
                         TextStyle {
                             font: asset_server.load("fonts/ume-tgs5.ttf"),
                             font_size: 40.0,
@@ -173,17 +277,51 @@ pub struct InfoBoardPlugin;
 impl Plugin for InfoBoardPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(init_note_text));
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(init_timer_display));
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(show_remaining_time));
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(pop_ball_from_magazine),
+            SystemSet::on_update(AppState::Game).with_system(
+                init_timer_display
+                    .after("goal:spawn_order_label")
+                    .label("info_board:init_timer"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                show_remaining_time
+                    .after("info_board:init_timer")
+                    .label("info_board:show_time"),
+            ),
+        );
+        // 番えたボールはコマンドで出現するので, 同じフレームのうちに残りを減らすとボールを使い切ったと判定されてしまう.
+        // 残りを減らすのはゲームオーバーの判定の後にする
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                pop_ball_from_magazine
+                    .after("gameover_check")
+                    .label("info_board:pop_ball"),
+            ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(update_remaining_balls_info),
+            SystemSet::on_update(AppState::Game).with_system(
+                refresh_remaining_balls_display
+                    .after("info_board:show_time")
+                    .after("info_board:pop_ball")
+                    .label("info_board:refresh_balls"),
+            ),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(spawn_result_score.after("count_down_update")),
+            SystemSet::on_update(AppState::Game).with_system(
+                update_remaining_balls_info
+                    .after("info_board:refresh_balls")
+                    .label("info_board:update_balls"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                spawn_result_score
+                    .after("count_down_update")
+                    .after("game_over")
+                    .label("info_board:result_score"),
+            ),
         );
     }
 }
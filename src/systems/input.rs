@@ -0,0 +1,162 @@
+//! キーボードとゲームパッドの入力を操作に変換する
+use std::collections::HashSet;
+
+use bevy::{
+    input::{gamepad::gamepad_connection_system, InputSystem},
+    prelude::*,
+};
+
+use crate::{
+    components::{
+        input::{ActionInput, InputAction, InputBindings},
+        replay::ScriptPlayback,
+    },
+    stages::structs::StageInfo,
+    AppState,
+};
+
+use super::utils::{load_ron_file, save_ron_file};
+
+/// 操作の割り当てを保存するファイル
+const BINDINGS_FILE: &str = "save/bindings.ron";
+
+/// 割り当てを読み込む. ファイルがなければ編集できるように既定の割り当てを書き出す.
+/// ファイルに書かれていない操作には既定の割り当てを使う.
+fn load_bindings(mut commands: Commands) {
+    let mut bindings = load_ron_file::<InputBindings>(BINDINGS_FILE);
+    let default_bindings = InputBindings::default();
+    for (action, keys) in default_bindings.keys {
+        bindings.keys.entry(action).or_insert(keys);
+    }
+    for (action, buttons) in default_bindings.buttons {
+        bindings.buttons.entry(action).or_insert(buttons);
+    }
+    if !std::path::Path::new(BINDINGS_FILE).exists() {
+        save_ron_file(BINDINGS_FILE, &bindings);
+    }
+    commands.insert_resource(bindings);
+}
+
+/// キー, ボタン, スティックの状態から押されている操作を集めて`ActionInput`を更新する.
+/// 回転の量はステージの指定があればそちらの変化のしかたで計算する.
+/// 記録を再生している間は入力機器の代わりに記録から操作を読む.
+/// 状態が変わったら回転の加速をやり直し, メニューなどで押し続けていた分をゲームに持ち越さない.
+#[allow(clippy::too_many_arguments)]
+pub fn update_action_input(
+    bindings: Option<Res<InputBindings>>,
+    stage_info: Option<Res<StageInfo>>,
+    key_in: Res<Input<KeyCode>>,
+    button_in: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    playback: Option<ResMut<ScriptPlayback>>,
+    app_state: Option<Res<State<AppState>>>,
+    mut action_input: ResMut<ActionInput>,
+    mut last_state: Local<Option<AppState>>,
+) {
+    let bindings = if let Some(bindings) = bindings {
+        bindings
+    } else {
+        return;
+    };
+    let (pressed, digital, analog) = if let Some(mut playback) = playback {
+        let pressed = playback.next_actions();
+        let digital = digital_rotation(&pressed);
+        (pressed, digital, 0.0)
+    } else {
+        device_input(&bindings, &key_in, &button_in, &axes, &gamepads)
+    };
+    action_input.update(pressed);
+    if let Some(app_state) = app_state {
+        if last_state.as_ref() != Some(app_state.current()) {
+            *last_state = Some(app_state.current().clone());
+            action_input.reset_hold();
+        }
+    }
+    let curve = stage_info
+        .and_then(|info| info.control_curve)
+        .unwrap_or(bindings.curve);
+    action_input.update_rotation(digital, analog, &curve);
+}
+
+/// 押されている回転の操作から回転の向き（-1, 0, 1）を決める
+fn digital_rotation(pressed: &HashSet<InputAction>) -> f32 {
+    if pressed.contains(&InputAction::RotateLeft) {
+        1.0
+    } else if pressed.contains(&InputAction::RotateRight) {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+/// キー, ボタン, スティックから押されている操作と, デジタル入力とスティックそれぞれによる回転の量を集める
+fn device_input(
+    bindings: &InputBindings,
+    key_in: &Input<KeyCode>,
+    button_in: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+    gamepads: &Gamepads,
+) -> (HashSet<InputAction>, f32, f32) {
+    let mut pressed = bindings
+        .keys
+        .iter()
+        .filter(|(_, keys)| key_in.any_pressed(keys.iter().copied()))
+        .map(|(action, _)| *action)
+        .collect::<HashSet<InputAction>>();
+    for gamepad in gamepads.iter() {
+        for (action, buttons) in bindings.buttons.iter() {
+            if buttons
+                .iter()
+                .any(|button| button_in.pressed(GamepadButton::new(*gamepad, *button)))
+            {
+                pressed.insert(*action);
+            }
+        }
+    }
+    // スティックで押されたことになる操作はデジタル入力の回転には含めない
+    let digital = digital_rotation(&pressed);
+    let dead_zone = bindings.stick_dead_zone;
+    let mut analog = 0.0f32;
+    for gamepad in gamepads.iter() {
+        // 左スティックは方向キーと同じ操作として扱う
+        let stick = |axis_type| {
+            axes.get(GamepadAxis::new(*gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        let (x, y) = (
+            stick(GamepadAxisType::LeftStickX),
+            stick(GamepadAxisType::LeftStickY),
+        );
+        // 遊びを超えた分を0から1に伸ばして回転の量にする（左が正）
+        let scaled = (x.abs() - dead_zone).max(0.0) / (1.0 - dead_zone);
+        if scaled > analog.abs() {
+            analog = -x.signum() * scaled;
+        }
+        for (value, negative, positive) in [
+            (x, InputAction::RotateLeft, InputAction::RotateRight),
+            (y, InputAction::Down, InputAction::Up),
+        ] {
+            if value < -dead_zone {
+                pressed.insert(negative);
+            } else if value > dead_zone {
+                pressed.insert(positive);
+            }
+        }
+    }
+    (pressed, digital, analog)
+}
+
+pub struct InputPlugin;
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionInput>();
+        app.add_startup_system(load_bindings);
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            update_action_input
+                .after(InputSystem)
+                .after(gamepad_connection_system),
+        );
+    }
+}
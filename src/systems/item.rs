@@ -0,0 +1,145 @@
+//! アイテムとチェックポイントの出現と, 取得時の効果を扱う
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    components::{
+        game::{CollectedItems, InitialBallNum, Score},
+        info::{MagazineUpdating, RemainingTime},
+        item::{Checkpoint, CollectibleItem, ItemType},
+        launcher::BallMagazine,
+        suspend::StageObjectId,
+        timer::CountDownTimer,
+    },
+    events::item::{CollectItemEvent, SpawnCheckpointEvent, SpawnItemEvent},
+    AppState,
+};
+
+use super::launcher::spawn_remaining_ball;
+
+fn spawn_item(mut commands: Commands, mut event_listener: EventReader<SpawnItemEvent>) {
+    for (idx, ev) in event_listener.iter().enumerate() {
+        let item_shape = shapes::Circle {
+            radius: ev.item_type.radius(),
+            ..Default::default()
+        };
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &item_shape,
+                DrawMode::Outlined {
+                    fill_mode: FillMode::color(ev.item_type.color()),
+                    outline_mode: StrokeMode::new(Color::WHITE, 3.0),
+                },
+                Transform {
+                    translation: ev.pos.extend(9.0),
+                    ..Default::default()
+                },
+            ))
+            .insert(CollectibleItem {
+                item_type: ev.item_type,
+            })
+            .insert(StageObjectId::Item(idx));
+    }
+}
+
+fn spawn_checkpoint(mut commands: Commands, mut event_listener: EventReader<SpawnCheckpointEvent>) {
+    for (idx, ev) in event_listener.iter().enumerate() {
+        let checkpoint_shape = shapes::Circle {
+            radius: ev.radius,
+            ..Default::default()
+        };
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &checkpoint_shape,
+                DrawMode::Stroke(StrokeMode::new(Color::WHITE, 4.0)),
+                Transform {
+                    translation: ev.pos.extend(9.0),
+                    ..Default::default()
+                },
+            ))
+            .insert(Checkpoint {
+                id: idx as u32,
+                radius: ev.radius,
+                passed: false,
+            })
+            .insert(StageObjectId::Checkpoint(idx));
+    }
+}
+
+/// 通過されたチェックポイントの色を変える. 巻き戻しで通過前に戻ったら元の色にする
+fn checkpoint_passed(mut query: Query<(&Checkpoint, &mut DrawMode), Changed<Checkpoint>>) {
+    for (checkpoint, mut draw_mode) in query.iter_mut() {
+        let color = if checkpoint.passed {
+            Color::ORANGE
+        } else {
+            Color::WHITE
+        };
+        *draw_mode = DrawMode::Stroke(StrokeMode::new(color, 4.0));
+    }
+}
+
+/// 取得したアイテムの効果を発揮させる
+#[allow(clippy::too_many_arguments)]
+fn execute_item_effect(
+    mut commands: Commands,
+    mut event_reader: EventReader<CollectItemEvent>,
+    mut score: ResMut<Score>,
+    mut collected_items: ResMut<CollectedItems>,
+    mut init_ball_num: ResMut<InitialBallNum>,
+    mut timer_query: Query<&mut CountDownTimer, With<RemainingTime>>,
+    mut magazine_query: Query<(&mut BallMagazine, Entity)>,
+) {
+    for ev in event_reader.iter() {
+        collected_items.0 += 1;
+        match ev.item_type {
+            ItemType::Coin(point) => {
+                score.0 += point;
+            }
+            ItemType::BonusTime(frames) => {
+                if let Ok(mut timer) = timer_query.get_single_mut() {
+                    timer.extend(frames);
+                }
+            }
+            ItemType::ExtraBall(ball_type) => {
+                if let Ok((mut magazine, magazine_ent)) = magazine_query.get_single_mut() {
+                    let idx = magazine.balls.len();
+                    let ent = spawn_remaining_ball(&mut commands, ball_type, idx);
+                    magazine.balls.push((ball_type, ent));
+                    commands.entity(magazine_ent).insert(MagazineUpdating);
+                    init_ball_num.0 += 1;
+                }
+            }
+        }
+    }
+}
+
+pub struct ItemPlugin;
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Game)
+                .with_system(spawn_item)
+                .after("spawn_stage_entities"),
+        );
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Game)
+                .with_system(spawn_checkpoint)
+                .after("spawn_stage_entities"),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                checkpoint_passed
+                    .after("switch:state_changed")
+                    .label("item:checkpoint_passed"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                execute_item_effect
+                    .after("collision:item_and_ball")
+                    .after("hazard:destroy_lost_ball")
+                    .label("item:execute_effect"),
+            ),
+        );
+    }
+}
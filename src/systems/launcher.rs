@@ -1,9 +1,14 @@
 use crate::{
     components::{
         ball::BallType,
+        camera::hud_layer,
+        control::{is_controllable, ActiveControlGroup, ControlGroup},
         game::NowGameOver,
-        info::RemainingBall,
+        info::{MagazineUpdating, RemainingBall},
+        input::{ActionInput, InputAction},
         launcher::{BallMagazine, Launcher, LauncherState},
+        suspend::StageObjectId,
+        timer::FrameCounter,
     },
     events::{
         ball::{LaunchBallEvent, SetBallEvent, SpawnBallEvent},
@@ -14,8 +19,13 @@ use crate::{
 use bevy::prelude::*;
 use bevy_prototype_lyon::{prelude::*, shapes::Polygon};
 
+use super::info_board::remaining_ball_display_pos;
+
+/// Zキーをこのフレーム数押し続けると次に番えるボールを切り替える
+const SELECT_BALL_HOLD_FRAMES: u32 = 30;
+
 /// ランチャーの形の多角形を生成する
-fn construct_launcher_shape() -> Polygon {
+pub fn construct_launcher_shape() -> Polygon {
     const LAUNCHER_WIDTH: f32 = 50.0;
     shapes::Polygon {
         points: vec![
@@ -33,35 +43,41 @@ fn construct_launcher_shape() -> Polygon {
     }
 }
 
+/// 残りボール表示用のボールを出現させる
+pub fn spawn_remaining_ball(commands: &mut Commands, ball_type: BallType, idx: usize) -> Entity {
+    let ball_shape = shapes::Circle {
+        radius: 10.0,
+        ..Default::default()
+    };
+    let show_pos = remaining_ball_display_pos(idx);
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &ball_shape,
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(ball_type.color()),
+                outline_mode: StrokeMode::new(Color::DARK_GRAY, 1.0),
+            },
+            Transform {
+                translation: show_pos.extend(11.0),
+                ..Default::default()
+            },
+        ))
+        .insert(RemainingBall)
+        .insert(hud_layer())
+        .id()
+}
+
 fn spawn_ball_magazine(mut commands: Commands, mut ball_event_reader: EventReader<SetBallEvent>) {
     let mut balls = Vec::<(BallType, Entity)>::new();
     for (idx, ev) in ball_event_reader.iter().enumerate() {
-        let ball_shape = shapes::Circle {
-            radius: 10.0,
-            ..Default::default()
-        };
-        let show_pos = Vec2::new(-200.0 + idx as f32 * 40.0, -350.0);
-        let ent = commands
-            .spawn_bundle(GeometryBuilder::build_as(
-                &ball_shape,
-                DrawMode::Outlined {
-                    fill_mode: FillMode::color(ev.ball_type.color()),
-                    outline_mode: StrokeMode::new(Color::DARK_GRAY, 1.0),
-                },
-                Transform {
-                    translation: show_pos.extend(11.0),
-                    ..Default::default()
-                },
-            ))
-            .insert(RemainingBall)
-            .id();
+        let ent = spawn_remaining_ball(&mut commands, ev.ball_type, idx);
         balls.push((ev.ball_type, ent));
     }
-    commands.spawn().insert(BallMagazine { balls });
+    commands.spawn().insert(BallMagazine::new(balls)).insert(MagazineUpdating);
 }
 
 fn spawn_launcher(mut commands: Commands, mut event_listener: EventReader<SpawnLauncherEvent>) {
-    for ev in event_listener.iter() {
+    for (idx, ev) in event_listener.iter().enumerate() {
         let shape = construct_launcher_shape();
         commands
             .spawn_bundle(GeometryBuilder::build_as(
@@ -82,18 +98,23 @@ fn spawn_launcher(mut commands: Commands, mut event_listener: EventReader<SpawnL
                 min_angle: ev.min_angle,
                 max_angle: ev.max_angle,
             })
-            .insert(LauncherState::Waiting);
+            .insert(LauncherState::Waiting)
+            .insert(ControlGroup(ev.control_group))
+            .insert(StageObjectId::Launcher(idx));
     }
 }
 
-fn rotate_launcher(key_in: Res<Input<KeyCode>>, mut query: Query<(&mut Transform, &mut Launcher)>) {
-    for (mut trans, mut launcher) in query.iter_mut() {
-        let launcher_rotate_angle = launcher.rotate_speed;
-        if key_in.pressed(KeyCode::Right) {
-            launcher.angle -= launcher_rotate_angle;
-        } else if key_in.pressed(KeyCode::Left) {
-            launcher.angle += launcher_rotate_angle;
+fn rotate_launcher(
+    action_in: Res<ActionInput>,
+    active_group: Option<Res<ActiveControlGroup>>,
+    mut query: Query<(&mut Transform, &mut Launcher, &ControlGroup)>,
+) {
+    for (mut trans, mut launcher, group) in query.iter_mut() {
+        if !is_controllable(&active_group, group) {
+            continue;
         }
+        let launcher_rotate_angle = launcher.rotate_speed;
+        launcher.angle += launcher_rotate_angle * action_in.rotate_amount();
         if launcher.angle > launcher.max_angle {
             launcher.angle = launcher.max_angle;
         } else if launcher.angle < launcher.min_angle {
@@ -103,12 +124,15 @@ fn rotate_launcher(key_in: Res<Input<KeyCode>>, mut query: Query<(&mut Transform
     }
 }
 
+/// 待機状態でZキーを短く押して離すとボールを番える.
+/// 押し続けると一定フレームごとに番えるボールを切り替え, その場合は離しても番えない.
+#[allow(clippy::type_complexity)]
 fn nock_ball(
     mut commands: Commands,
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
     mut spawn_ball_event_writer: EventWriter<SpawnBallEvent>,
-    query: Query<(&Launcher, &LauncherState, &Transform, Entity)>,
-    magazine_query: Query<&BallMagazine>,
+    query: Query<(&Launcher, &LauncherState, &Transform, Option<&FrameCounter>, Entity)>,
+    mut magazine_query: Query<(&mut BallMagazine, Entity)>,
     is_gameover: Option<Res<NowGameOver>>,
 ) {
     if is_gameover.is_some() {
@@ -117,31 +141,52 @@ fn nock_ball(
     if query.is_empty() {
         return;
     }
-    if key_in.just_pressed(KeyCode::Z) {
-        for (_, state, launcher_trans, ent) in query.iter() {
-            if let LauncherState::Waiting = *state {
-                // 待機状態ならボールを一つ読み取ってボール出現イベントを送信
-                let magazine = magazine_query.single();
-                let ball_type = if let Some((ball_type, _)) = magazine.balls.get(0) {
-                    *ball_type
-                } else {
-                    // 残りボールが無い状態. NOTE: 効果音とか鳴らすようにするとよさそう
-                    continue;
-                };
-                commands
-                    .entity(ent)
-                    .remove::<LauncherState>()
-                    .insert(LauncherState::Nocking);
-                let pos = launcher_trans.translation.truncate();
-                spawn_ball_event_writer.send(SpawnBallEvent { ball_type, pos });
+    for (_, state, launcher_trans, hold_counter, ent) in query.iter() {
+        if let LauncherState::Nocking = *state {
+            continue;
+        }
+        if action_in.just_pressed(InputAction::Action) {
+            // 押した時点から長押しの計測を始める
+            commands.entity(ent).insert(FrameCounter::new());
+            continue;
+        }
+        let hold_counter = if let Some(counter) = hold_counter {
+            counter
+        } else {
+            continue;
+        };
+        let (mut magazine, magazine_ent) = magazine_query.single_mut();
+        if action_in.pressed(InputAction::Action) {
+            if hold_counter.count > 0 && hold_counter.count % SELECT_BALL_HOLD_FRAMES == 0 {
+                magazine.cycle_selection();
+                commands.entity(magazine_ent).insert(MagazineUpdating);
+            }
+        } else if action_in.just_released(InputAction::Action) {
+            commands.entity(ent).remove::<FrameCounter>();
+            if hold_counter.count >= SELECT_BALL_HOLD_FRAMES {
+                // 切り替え操作だったので番えない
+                continue;
             }
+            // 待機状態なら選択中のボールを読み取ってボール出現イベントを送信
+            let ball_type = if let Some(ball_type) = magazine.selected_ball() {
+                ball_type
+            } else {
+                // 残りボールが無い状態. NOTE: 効果音とか鳴らすようにするとよさそう
+                continue;
+            };
+            commands
+                .entity(ent)
+                .remove::<LauncherState>()
+                .insert(LauncherState::Nocking);
+            let pos = launcher_trans.translation.truncate();
+            spawn_ball_event_writer.send(SpawnBallEvent { ball_type, pos });
         }
     }
 }
 
 fn launch_ball(
     mut commands: Commands,
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
     mut launch_ball_event_writer: EventWriter<LaunchBallEvent>,
     query: Query<(&Launcher, &LauncherState, Entity)>,
     is_gameover: Option<Res<NowGameOver>>,
@@ -149,7 +194,7 @@ fn launch_ball(
     if is_gameover.is_some() {
         return;
     }
-    if key_in.just_pressed(KeyCode::Z) {
+    if action_in.just_pressed(InputAction::Action) {
         for (launcher, state, ent) in query.iter() {
             match *state {
                 LauncherState::Waiting => {}
@@ -180,8 +225,24 @@ impl Plugin for LauncherPlugin {
                 .with_system(spawn_ball_magazine)
                 .after("spawn_stage_entities"),
         );
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(rotate_launcher));
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(nock_ball));
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(launch_ball));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                rotate_launcher
+                    .after("control:cycle_group")
+                    .label("launcher:rotate"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                nock_ball
+                    .after("frame_counter_update")
+                    .after("launcher:rotate")
+                    .label("launcher:nock"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(launch_ball.after("launcher:nock").label("launcher:launch")),
+        );
     }
 }
@@ -1,8 +1,15 @@
 use bevy::prelude::*;
 
+use crate::stages::structs::StageInfo;
+use crate::stages::validate::{validate_stage, Severity};
 use crate::AppState;
 
-use crate::components::game::{GoaledBall, OperationAmount, PassedTime, Score};
+use crate::components::game::{
+    BounceCount, CollectedItems, GoaledBall, LongestAirborne, LostBall, MaxBallSpeed,
+    NextGoalOrder, OperationAmount, PassedTime, ReachedGoals, Score, StrokeCount, WinConditionMet,
+};
+use crate::components::rewind::{RewindBuffer, RewindCount};
+use crate::components::time_scale::SlowMotion;
 use crate::components::timer::CountDownTimer;
 
 #[derive(Component)]
@@ -20,6 +27,18 @@ pub fn stage_setup(mut commands: Commands, loading: Query<&Loading>) {
     commands.insert_resource(Score(0));
     commands.insert_resource(OperationAmount(0));
     commands.insert_resource(PassedTime(0));
+    commands.insert_resource(NextGoalOrder(1));
+    commands.insert_resource(CollectedItems(0));
+    commands.insert_resource(LostBall(0));
+    commands.insert_resource(StrokeCount(0));
+    commands.insert_resource(BounceCount(0));
+    commands.insert_resource(MaxBallSpeed(0.0));
+    commands.insert_resource(ReachedGoals(vec![]));
+    commands.insert_resource(LongestAirborne(0));
+    commands.insert_resource(WinConditionMet(false));
+    commands.insert_resource(RewindBuffer::default());
+    commands.insert_resource(RewindCount(0));
+    commands.insert_resource(SlowMotion::default());
 
     commands
         .spawn()
@@ -27,6 +46,21 @@ pub fn stage_setup(mut commands: Commands, loading: Query<&Loading>) {
         .insert(CountDownTimer::new(20));
 }
 
+/// デバッグビルドではステージの間違いをログに出す
+fn validate_on_load(stage_info: Option<Res<StageInfo>>) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    if let Some(stage_info) = stage_info {
+        for issue in validate_stage(&stage_info) {
+            match issue.severity {
+                Severity::Error => error!("{}: {}", stage_info.stage_title, issue),
+                Severity::Warning => warn!("{}: {}", stage_info.stage_title, issue),
+            }
+        }
+    }
+}
+
 /// 一瞬ロード待ちを入れる（リソースの追加消去を確実にするため）
 fn wait_for_a_moment(
     timer: Query<&CountDownTimer, With<Loading>>,
@@ -42,10 +76,14 @@ fn wait_for_a_moment(
 pub struct LoadStagePlugin;
 impl Plugin for LoadStagePlugin {
     fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Loading).with_system(validate_on_load));
         app.add_system_set(SystemSet::on_update(AppState::Loading).with_system(stage_setup));
         app.add_system_set(
-            SystemSet::on_update(AppState::Loading)
-                .with_system(wait_for_a_moment.after("count_down_update")),
+            SystemSet::on_update(AppState::Loading).with_system(
+                wait_for_a_moment
+                    .after("count_down_update")
+                    .after("return_to_title"),
+            ),
         );
     }
 }
@@ -1,32 +1,48 @@
 use std::collections::HashMap;
+use std::sync::{mpsc, Mutex};
 
 use bevy::app::AppExit;
 use bevy::prelude::*;
 
-use crate::components::game::GameRule;
+use crate::components::game::{CourseProgress, GameRule};
+use crate::components::input::{ActionInput, InputAction};
 use crate::components::main_menu::menu::{
-    ChangeMenuLayerEvent, CurrentOption, MenuLayerOptionEntities, MenuLayerPos, MenuOptionResource,
-    OptionText,
+    ChangeMenuLayerEvent, CurrentOption, DisabledOption, MenuLayerOptionEntities, MenuLayerPos,
+    MenuOptionResource, MenuOptionSets, OptionText,
 };
-use crate::stages::select_stage;
+use crate::components::progress::CampaignProgress;
+use crate::components::settings::GameSettings;
+use crate::stages::{campaign, course_vec, generate::daily_stage, structs::StageInfo};
 use crate::{AppState, SCREEN_HEIGHT, SCREEN_WIDTH};
 
-use super::menu_contents::menu_options_settings;
+use crate::systems::suspend::{load_suspended_game, resume_suspended_game};
+
+use super::menu_contents::{menu_options_settings, SETTINGS_LAYER, WORLD_LAYER_OFFSET};
 
 /// メニュー状態に入ったときに存在したエンティティを保持しておく
 /// （抜けるときにここに入っていないエンティティを削除する）
 struct ResidentEntities(Vec<Entity>);
 
+/// デイリーチャレンジのステージを作っている途中. その日初めてなら解けるか確かめるのに時間がかかるので別スレッドで作る
+struct DailyGeneration(Mutex<mpsc::Receiver<Option<StageInfo>>>);
+
 /// シーン進入時の初期化システム
 fn init_menu_scene(
     mut commands: Commands,
     entities: Query<Entity>,
     asset_server: Res<AssetServer>,
+    progress: Res<CampaignProgress>,
+    settings: Res<GameSettings>,
 ) {
     // 最初に存在しているentityをすべて保存しておく.
     commands.insert_resource(ResidentEntities(entities.iter().collect::<Vec<Entity>>()));
 
-    init_option2(&mut commands, &asset_server);
+    let suspended_title = load_suspended_game().map(|saved| saved.stage_title);
+    init_option2(
+        &mut commands,
+        &asset_server,
+        menu_options_settings(&progress, &settings, suspended_title),
+    );
 }
 
 fn text_style_from_pos(left: f32, top: f32) -> Style {
@@ -41,9 +57,12 @@ fn text_style_from_pos(left: f32, top: f32) -> Style {
     }
 }
 
-/// メニューの初期化
-fn init_option2(commands: &mut Commands, asset_server: &Res<AssetServer>) {
-    let menu = menu_options_settings();
+/// メニューの初期化. 一時停止メニューなど別の選択肢セットでも使う.
+pub fn init_option2(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    menu: MenuOptionSets,
+) {
     // レイヤーにおける設定保存用ハッシュマップ. 最初にすべて0で初期化しておく
     let mut layer_choice_table = HashMap::<u32, u32>::new();
     // レイヤー内のエンティティ保存用ハッシュマップ. 最初にすべて空ベクトルで初期化しておく
@@ -65,7 +84,22 @@ fn init_option2(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 text: Text::from_section(option.name, text_style.clone()),
                 ..default()
             };
-            let text_width = 30.0 * option.name.len() as f32;
+            let mut text_width = 30.0 * option.name.len() as f32;
+            if option.setting.is_some() {
+                // 値の表示分の幅をとっておく
+                text_width += 30.0 * 6.0;
+            }
+            if let Some(note) = &option.note {
+                text_bundle.text.sections.push(TextSection::new(
+                    format!("({}) ", note),
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::GRAY,
+                        ..text_style.clone()
+                    },
+                ));
+                text_width += 12.0 * (note.len() + 3) as f32;
+            }
 
             if text_width_sum + text_width > SCREEN_WIDTH * 0.8 {
                 // 次を置いたら画面外に出てしまうなら更新してからスタイルを設定
@@ -81,29 +115,38 @@ fn init_option2(commands: &mut Commands, asset_server: &Res<AssetServer>) {
                 .insert(MenuLayerPos(option_set.layer_id))
                 .insert(OptionText)
                 .id();
+            if let Some(key) = option.setting {
+                commands.entity(ent).insert(key);
+            }
+            if option.disabled {
+                commands.entity(ent).insert(DisabledOption);
+            }
             // レイヤー番号のところの配列にエンティティを追加
             layer_option_entities
                 .get_mut(&option_set.layer_id)
                 .unwrap()
                 .push(ent);
-            layer_choice_table.insert(option_set.layer_id, 0u32);
+            layer_choice_table.insert(option_set.layer_id, option_set.initial_choice);
         }
     }
+    // 最初のレイヤーも指定された選択肢を選んだ状態で始める
+    let initial_choice = layer_choice_table.get(&0).copied().unwrap_or(0);
     commands.insert_resource(MenuOptionResource {
         current_layer: 0,
+        current_option_num: initial_choice,
         layer_choice_table,
         ..default()
     });
     commands
-        .entity(layer_option_entities[&0u32][0])
+        .entity(layer_option_entities[&0u32][initial_choice as usize])
         .insert(CurrentOption);
     commands.insert_resource(MenuLayerOptionEntities(layer_option_entities));
 }
 
 /// 方向キーで選択肢を選ぶ処理
-fn select_options(
+pub fn select_options(
     mut commands: Commands,
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
     layer_option_entities: Res<MenuLayerOptionEntities>,
     mut menu_res: ResMut<MenuOptionResource>,
 ) {
@@ -111,9 +154,9 @@ fn select_options(
     let option_num = option_entities.len() as u32;
 
     let prev_num = menu_res.current_option_num;
-    if key_in.just_pressed(KeyCode::Left) {
+    if action_in.just_pressed(InputAction::RotateLeft) {
         menu_res.current_option_num = (prev_num + option_num - 1) % option_num;
-    } else if key_in.just_pressed(KeyCode::Right) {
+    } else if action_in.just_pressed(InputAction::RotateRight) {
         menu_res.current_option_num = (prev_num + 1) % option_num;
     }
     // 変更があったときのみ処理
@@ -128,7 +171,7 @@ fn select_options(
 
 /// メニュー階層変更イベントが送られた際の処理
 /// OptionEntitiesの変更等
-fn layer_changed(
+pub fn layer_changed(
     mut commands: Commands,
     mut event_reader: EventReader<ChangeMenuLayerEvent>,
     current_option_query: Query<Entity, With<CurrentOption>>,
@@ -177,45 +220,77 @@ fn back_to_upper_layer(
 }
 
 /// キャンセルコマンド処理. Xキーでひとつ上の階層に戻る
-fn cancel_select_option(
-    key_in: Res<Input<KeyCode>>,
+pub fn cancel_select_option(
+    action_in: Res<ActionInput>,
     mut event_writer: EventWriter<ChangeMenuLayerEvent>,
     mut menu_res: ResMut<MenuOptionResource>,
 ) {
-    if key_in.just_pressed(KeyCode::X) {
+    if action_in.just_pressed(InputAction::Cancel) {
         back_to_upper_layer(&mut event_writer, &mut menu_res)
     }
 }
 
 /// 決定キーが押されたときのレイヤーと選択肢位置から処理を行う
+#[allow(clippy::too_many_arguments)]
 fn each_option_processing(
     mut commands: Commands,
     mut app_state: ResMut<State<AppState>>,
-    key_in: Res<Input<KeyCode>>,
+    action_in: Res<ActionInput>,
     menu_res: Res<MenuOptionResource>,
+    disabled_query: Query<(), (With<CurrentOption>, With<DisabledOption>)>,
     mut event_writer: EventWriter<ChangeMenuLayerEvent>,
     mut app_exit_events: EventWriter<AppExit>,
+    mut settings: ResMut<GameSettings>,
+    daily: Option<Res<DailyGeneration>>,
 ) {
-    if key_in.just_pressed(KeyCode::Z) {
+    // 選べない選択肢では何もしない
+    if action_in.just_pressed(InputAction::Action) && disabled_query.is_empty() {
         let layer = menu_res.current_layer;
         let pos = menu_res.current_option_num;
         match layer {
             0 => match pos {
-                0 => event_writer.send(ChangeMenuLayerEvent::move_to(1)),
-                1 => event_writer.send(ChangeMenuLayerEvent::move_to(2)),
-                2 => {
+                0 => resume_suspended_game(&mut commands, &mut app_state),
+                1 => event_writer.send(ChangeMenuLayerEvent::move_to(1)),
+                2 => event_writer.send(ChangeMenuLayerEvent::move_to(2)),
+                3 => event_writer.send(ChangeMenuLayerEvent::move_to(3)),
+                // 作っている途中なら何もしない
+                4 if daily.is_none() => {
+                    let (sender, receiver) = mpsc::channel();
+                    std::thread::spawn(move || {
+                        let _ = sender.send(daily_stage());
+                    });
+                    commands.insert_resource(DailyGeneration(Mutex::new(receiver)));
+                }
+                5 => event_writer.send(ChangeMenuLayerEvent::move_to(SETTINGS_LAYER)),
+                6 => app_state.set(AppState::Editor).unwrap(),
+                7 => {
                     app_exit_events.send_default();
                 }
                 _ => {}
             },
-            1 => {
-                let stage_idx = pos as usize;
-                let stage_info = select_stage(stage_idx);
+            1 => event_writer.send(ChangeMenuLayerEvent::move_to(WORLD_LAYER_OFFSET + pos)),
+            layer if layer >= WORLD_LAYER_OFFSET => {
+                let world_idx = (layer - WORLD_LAYER_OFFSET) as usize;
+                let stage_info = campaign()[world_idx].stages[pos as usize]();
+                let stage_rule = stage_info.rule;
                 // ステージ情報をリソースとして挟む
                 commands.insert_resource(stage_info);
-                // ゲームルールを追加
+                // ゲームルールを追加. ステージでルールが決められていればそちらを使う
                 let rule_num = menu_res.layer_choice_table[&2];
-                commands.insert_resource(GameRule::from(rule_num));
+                // 選んだルールは次回起動時にも使う. 変わったときだけ保存されるようにする
+                if settings.rule != rule_num {
+                    settings.rule = rule_num;
+                }
+                commands.insert_resource(stage_rule.unwrap_or_else(|| GameRule::from(rule_num)));
+                app_state.set(AppState::Loading).unwrap();
+            }
+            3 => {
+                // コースは常に打数ルールで遊ぶ
+                let course = course_vec().swap_remove(pos as usize);
+                let course = CourseProgress::new(course.title, course.stages);
+                commands.insert_resource(course.current_stage());
+                commands.insert_resource(course);
+                commands.insert_resource(GameRule::Strokes);
                 app_state.set(AppState::Loading).unwrap();
             }
             _ => {}
@@ -223,9 +298,60 @@ fn each_option_processing(
     }
 }
 
+/// デイリーチャレンジのステージができたら打数ルールで遊ぶ. 作っている間はメニューの補足に表示する
+fn receive_daily_stage(
+    mut commands: Commands,
+    mut app_state: ResMut<State<AppState>>,
+    daily: Option<Res<DailyGeneration>>,
+    layer_option_entities: Res<MenuLayerOptionEntities>,
+    mut text_query: Query<&mut Text>,
+    mut pending: Local<Option<StageInfo>>,
+) {
+    let daily = if let Some(daily) = daily {
+        daily
+    } else {
+        // メニューを抜けて作り直すことになったら持ち越した分は使わない
+        *pending = None;
+        return;
+    };
+    let received = pending.take().map(Some).or_else(|| {
+        daily
+            .0
+            .lock()
+            .ok()
+            .and_then(|receiver| receiver.try_recv().ok())
+    });
+    let note = match received {
+        None if daily.is_added() => "generating...".to_string(),
+        None => return,
+        Some(Some(stage_info)) => {
+            // ほかの状態遷移と重なったら次のフレームでやり直す
+            if app_state.set(AppState::Loading).is_err() {
+                *pending = Some(stage_info);
+                return;
+            }
+            commands.insert_resource(stage_info);
+            commands.insert_resource(GameRule::Strokes);
+            commands.remove_resource::<DailyGeneration>();
+            return;
+        }
+        Some(None) => {
+            commands.remove_resource::<DailyGeneration>();
+            "no stage today".to_string()
+        }
+    };
+    // メインメニューのDailyの補足を書き換える
+    let ent = layer_option_entities.0[&0][4];
+    if let Ok(mut text) = text_query.get_mut(ent) {
+        if let Some(section) = text.sections.get_mut(1) {
+            section.value = format!("({}) ", note);
+        }
+    }
+}
+
 // NOTE: 0.8でVisibilityの意味が変化したようです
 /// 現在のレイヤーの選択肢を表示させる.
-fn show_current_layer(
+pub fn show_current_layer(
     mut query: Query<(&mut Visibility, &MenuLayerPos)>,
     menu_res: Res<MenuOptionResource>,
 ) {
@@ -235,15 +361,28 @@ fn show_current_layer(
 }
 
 /// 現在選択されているオプションにのみ緑色を表示しほかは白色に戻す処理
-fn text_color(
-    mut text_query: Query<&mut Text, (With<OptionText>, Without<CurrentOption>)>,
-    mut current_query: Query<&mut Text, With<CurrentOption>>,
+#[allow(clippy::type_complexity)]
+pub fn text_color(
+    mut text_query: Query<
+        (&mut Text, Option<&DisabledOption>),
+        (With<OptionText>, Without<CurrentOption>),
+    >,
+    mut current_query: Query<(&mut Text, Option<&DisabledOption>), With<CurrentOption>>,
 ) {
-    for mut text in text_query.iter_mut() {
-        text.sections[0].style.color = Color::WHITE;
+    // 選べないオプションは暗くする
+    for (mut text, disabled) in text_query.iter_mut() {
+        text.sections[0].style.color = if disabled.is_some() {
+            Color::DARK_GRAY
+        } else {
+            Color::WHITE
+        };
     }
-    for mut text in current_query.iter_mut() {
-        text.sections[0].style.color = Color::GREEN;
+    for (mut text, disabled) in current_query.iter_mut() {
+        text.sections[0].style.color = if disabled.is_some() {
+            Color::DARK_GREEN
+        } else {
+            Color::GREEN
+        };
     }
 }
 
@@ -260,6 +399,7 @@ fn deconstruct_menu(
     }
     commands.remove_resource::<MenuLayerOptionEntities>();
     commands.remove_resource::<MenuOptionResource>();
+    commands.remove_resource::<DailyGeneration>();
 }
 
 pub struct MainMenuPlugin;
@@ -272,6 +412,7 @@ impl Plugin for MainMenuPlugin {
         app.add_system_set(
             SystemSet::on_update(AppState::Menu).with_system(each_option_processing),
         );
+        app.add_system_set(SystemSet::on_update(AppState::Menu).with_system(receive_daily_stage));
         app.add_system_set(SystemSet::on_update(AppState::Menu).with_system(show_current_layer));
         app.add_system_set(SystemSet::on_update(AppState::Menu).with_system(text_color));
         app.add_system_set(
@@ -3,36 +3,190 @@
 use itertools::Itertools;
 
 use crate::{
-    components::main_menu::menu::{MenuOption, MenuOptionSet, MenuOptionSets},
-    stages::stage_title_vec,
+    components::{
+        game::GAME_RULE_NUM,
+        main_menu::menu::{MenuOption, MenuOptionSet, MenuOptionSets},
+        progress::CampaignProgress,
+        settings::{GameSettings, SettingKey},
+    },
+    stages::{campaign, course_title_vec, generate::daily_seed, structs::StageInfo, CampaignWorld},
 };
 
-pub fn menu_options_settings() -> MenuOptionSets {
+/// ワールド内のステージ選択レイヤーの番号はこの値にワールド番号を足したものとする
+pub const WORLD_LAYER_OFFSET: u32 = 10;
+
+/// 解放条件を満たしていなければ選べない選択肢にする
+fn campaign_option(
+    name: &'static str,
+    unlocked: bool,
+    requirement: impl FnOnce() -> String,
+) -> MenuOption {
+    if unlocked {
+        MenuOption::new(name)
+    } else {
+        MenuOption::locked(name, requirement())
+    }
+}
+
+/// ステージが選択中のルールを使わない場合, その内容を表す補足
+fn stage_rule_note(stage_info: &StageInfo) -> Option<String> {
+    let mut notes = vec![];
+    if let Some(rule) = stage_info.rule {
+        notes.push(format!("rule: {}", rule.name()));
+    }
+    if !stage_info.win_conditions.is_empty() {
+        let conditions = stage_info
+            .win_conditions
+            .iter()
+            .map(|condition| condition.description())
+            .join(", ");
+        notes.push(format!("win: {}", conditions));
+    }
+    (!notes.is_empty()).then(|| notes.join(" / "))
+}
+
+/// ワールド内のステージを並べたレイヤー
+fn world_option_set(
+    world_idx: usize,
+    world: &CampaignWorld,
+    progress: &CampaignProgress,
+) -> MenuOptionSet {
+    let options = world
+        .stages
+        .iter()
+        .enumerate()
+        .map(|(idx, generator)| {
+            let stage_info = generator();
+            let condition = world.stage_unlock_condition(idx);
+            match stage_rule_note(&stage_info) {
+                Some(note) if condition.is_satisfied(progress) => {
+                    MenuOption::with_note(stage_info.stage_title, note)
+                }
+                _ => campaign_option(
+                    stage_info.stage_title,
+                    condition.is_satisfied(progress),
+                    || condition.requirement_text(),
+                ),
+            }
+        })
+        .collect_vec();
+    MenuOptionSet {
+        options,
+        layer_id: WORLD_LAYER_OFFSET + world_idx as u32,
+        ..Default::default()
+    }
+}
+
+/// 設定画面のレイヤー番号
+pub const SETTINGS_LAYER: u32 = 4;
+
+/// 設定項目を並べたレイヤー. タイトルメニューと一時停止メニューで共通
+fn settings_option_set() -> MenuOptionSet {
+    MenuOptionSet {
+        options: [
+            SettingKey::WindowScale,
+            SettingKey::Fullscreen,
+            SettingKey::Volume,
+            SettingKey::AutoRetire,
+            SettingKey::ShowGuide,
+        ]
+        .into_iter()
+        .map(MenuOption::setting)
+        .collect_vec(),
+        layer_id: SETTINGS_LAYER,
+        ..Default::default()
+    }
+}
+
+/// ゲーム中の一時停止メニュー
+pub fn pause_menu_options() -> MenuOptionSets {
+    let pause_option = MenuOptionSet {
+        options: vec![
+            MenuOption::new("Resume"),
+            MenuOption::new("Retry"),
+            MenuOption::new("Settings"),
+            MenuOption::new("Quit"),
+        ],
+        layer_id: 0,
+        ..Default::default()
+    };
+    MenuOptionSets {
+        option_set: vec![pause_option, settings_option_set()],
+    }
+}
+
+pub fn menu_options_settings(
+    progress: &CampaignProgress,
+    settings: &GameSettings,
+    suspended_title: Option<String>,
+) -> MenuOptionSets {
+    // 中断したゲームがあれば最初から再開を選んでおく
+    let initial_choice = if suspended_title.is_some() { 0 } else { 1 };
+    let continue_option = match suspended_title {
+        Some(title) => MenuOption::with_note("Continue", title),
+        None => MenuOption::locked("Continue", "no suspended game".to_string()),
+    };
     let main_option = MenuOptionSet {
         options: vec![
+            continue_option,
             MenuOption::new("Start"),
             MenuOption::new("Set Rule"),
+            MenuOption::new("Course"),
+            // 日付から作るステージ. 日付を補足に出す
+            MenuOption::with_note("Daily", daily_seed().0),
+            MenuOption::new("Settings"),
+            MenuOption::new("Editor"),
             MenuOption::new("Exit"),
         ],
         layer_id: 0,
+        initial_choice,
     };
-    let stage_options = stage_title_vec()
-        .into_iter()
-        .map(MenuOption::new)
+    let worlds = campaign();
+    let world_options = worlds
+        .iter()
+        .map(|world| {
+            campaign_option(world.title, world.unlock.is_satisfied(progress), || {
+                world.unlock.requirement_text()
+            })
+        })
         .collect_vec();
-    let stage_option = MenuOptionSet {
-        options: stage_options,
+    let world_option = MenuOptionSet {
+        options: world_options,
         layer_id: 1,
+        ..Default::default()
     };
     let set_rule_option = MenuOptionSet {
         options: vec![
             MenuOption::new("BallScore"),
             MenuOption::new("LittleOperation"),
             MenuOption::new("TimeAttack"),
+            MenuOption::new("Strokes"),
         ],
         layer_id: 2,
+        // 前回選んだルールを選択した状態にしておく
+        initial_choice: settings.rule.min(GAME_RULE_NUM - 1),
     };
-    MenuOptionSets {
-        option_set: vec![main_option, stage_option, set_rule_option],
-    }
+    let course_options = course_title_vec()
+        .into_iter()
+        .map(MenuOption::new)
+        .collect_vec();
+    let course_option = MenuOptionSet {
+        options: course_options,
+        layer_id: 3,
+        ..Default::default()
+    };
+    let mut option_set = vec![
+        main_option,
+        world_option,
+        set_rule_option,
+        course_option,
+        settings_option_set(),
+    ];
+    option_set.extend(
+        worlds
+            .iter()
+            .enumerate()
+            .map(|(idx, world)| world_option_set(idx, world, progress)),
+    );
+    MenuOptionSets { option_set }
 }
@@ -1,16 +1,39 @@
+//! ゲームのシステム. 同じ操作から同じ結果になるように, ゲーム中のシステムはラベルで一列に並べてある.
+//! 1フレームの流れは次の通り. 新しいシステムを足すときは前後のラベルを指定して列に入れる.
+//! 1. タイマー, スローモーション, 操作（ランチャー, ブロック, スイッチの解除）
+//! 2. 当たり判定と力（"collision:*", 送風機, 磁石, 重力）
+//! 3. 移動（"execute_force" → "accelerate" → "fix_ball" → "move_pos"）
+//! 4. 移動の結果（ボールを失う, アイテム, ゴール, スイッチ）
+//! 5. 得点とゲームオーバーの判定, 画面の切り替え
+//! 6. 見た目の更新
+pub mod agent;
 pub mod ball;
 pub mod block;
 pub mod block_attach;
+pub mod camera;
 pub mod collision;
+pub mod control;
+pub mod editor;
 pub mod effects;
 pub mod field;
 pub mod game;
 pub mod goal;
+pub mod hazard;
 pub mod info_board;
+pub mod input;
+pub mod item;
 pub mod launcher;
 pub mod load;
 pub mod main_menu;
+pub mod pause;
 pub mod physics;
+pub mod progress;
+pub mod rewind;
+pub mod screen;
+pub mod settings;
 pub mod setup;
+pub mod simulate;
+pub mod suspend;
+pub mod time_scale;
 pub mod timer;
-mod utils;
+pub(crate) mod utils;
@@ -0,0 +1,143 @@
+//! ゲーム中の一時停止. Gameの上にPausedを積むことでゲームのシステムを止める.
+use bevy::prelude::*;
+
+use crate::{
+    components::{
+        game::NowGameOver,
+        info::RemainingTime,
+        input::{ActionInput, InputAction},
+        main_menu::menu::{
+            ChangeMenuLayerEvent, MenuLayerOptionEntities, MenuLayerPos, MenuOptionResource,
+        },
+        pause::PauseOverlay,
+        timer::{CountDownTimer, FrameCounter},
+    },
+    events::game::SuspendGameEvent,
+    AppState,
+};
+
+use super::main_menu::{
+    menu::{
+        cancel_select_option, init_option2, layer_changed, select_options, show_current_layer,
+        text_color,
+    },
+    menu_contents::{pause_menu_options, SETTINGS_LAYER},
+};
+
+/// Pキーで一時停止する. ゲームオーバー後は何もしない.
+/// 同じフレームでやり直しなどの画面の切り替えが先に決まっていれば, そちらを優先する
+fn pause_game(
+    action_in: Res<ActionInput>,
+    is_gameover: Option<Res<NowGameOver>>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if action_in.just_pressed(InputAction::Pause) && is_gameover.is_none() {
+        let _ = app_state.push(AppState::Paused);
+    }
+}
+
+/// タイマーはステージに関係なく動いているので, 一時停止の開始と終了で止めたり動かしたりする
+pub fn set_timers_paused(
+    timer_query: &mut Query<&mut CountDownTimer>,
+    counter_query: &mut Query<&mut FrameCounter>,
+    paused: bool,
+) {
+    for mut timer in timer_query.iter_mut() {
+        timer.set_paused(paused);
+    }
+    for mut counter in counter_query.iter_mut() {
+        counter.set_paused(paused);
+    }
+}
+
+fn enter_pause(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut timer_query: Query<&mut CountDownTimer>,
+    mut counter_query: Query<&mut FrameCounter>,
+) {
+    set_timers_paused(&mut timer_query, &mut counter_query, true);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..default()
+        })
+        .insert(PauseOverlay);
+    init_option2(&mut commands, &asset_server, pause_menu_options());
+}
+
+/// 決定キーが押されたときの処理. Pキーでも再開できる
+fn pause_option_processing(
+    mut commands: Commands,
+    mut action_in: ResMut<ActionInput>,
+    menu_res: Res<MenuOptionResource>,
+    mut event_writer: EventWriter<ChangeMenuLayerEvent>,
+    timer_query: Query<Entity, (With<CountDownTimer>, With<RemainingTime>)>,
+    mut suspend_event_writer: EventWriter<SuspendGameEvent>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if action_in.just_pressed(InputAction::Pause) {
+        app_state.pop().unwrap();
+    } else if action_in.just_pressed(InputAction::Action) && menu_res.current_layer == 0 {
+        match menu_res.current_option_num {
+            0 => {
+                // 再開したフレームでボールを番えてしまわないようにする
+                action_in.clear_just_pressed(InputAction::Action);
+                app_state.pop().unwrap();
+            }
+            1 => {
+                for ent in timer_query.iter() {
+                    commands.entity(ent).despawn();
+                }
+                app_state.replace(AppState::Loading).unwrap();
+            }
+            2 => event_writer.send(ChangeMenuLayerEvent::move_to(SETTINGS_LAYER)),
+            3 => {
+                suspend_event_writer.send(SuspendGameEvent);
+                for ent in timer_query.iter() {
+                    commands.entity(ent).despawn();
+                }
+                app_state.replace(AppState::BackToMenu).unwrap();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn exit_pause(
+    mut commands: Commands,
+    menu_query: Query<Entity, Or<(With<MenuLayerPos>, With<PauseOverlay>)>>,
+    mut timer_query: Query<&mut CountDownTimer>,
+    mut counter_query: Query<&mut FrameCounter>,
+) {
+    set_timers_paused(&mut timer_query, &mut counter_query, false);
+    for ent in menu_query.iter() {
+        commands.entity(ent).despawn();
+    }
+    commands.remove_resource::<MenuLayerOptionEntities>();
+    commands.remove_resource::<MenuOptionResource>();
+}
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(pause_game));
+        app.add_system_set(SystemSet::on_enter(AppState::Paused).with_system(enter_pause));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Paused)
+                .with_system(select_options)
+                .with_system(layer_changed)
+                .with_system(cancel_select_option)
+                .with_system(show_current_layer)
+                .with_system(text_color)
+                .with_system(pause_option_processing.label("pause:option_processing")),
+        );
+        app.add_system_set(SystemSet::on_exit(AppState::Paused).with_system(exit_pause));
+    }
+}
@@ -1,22 +1,35 @@
 use bevy::prelude::*;
 
 use crate::{
-    components::physics::{
-        force::{Force, Gravity},
-        material::{PhysicMaterial, Volume},
-        position::Position,
+    components::{
+        physics::{
+            force::{Force, Gravity},
+            material::{PhysicMaterial, Volume},
+            position::Position,
+        },
+        time_scale::{LocalTimeScale, TimeScale},
     },
     AppState,
 };
 
+use super::motion_dynamics::effective_scale;
+
 /// 重力を物体に加える
 fn gravity_effect(
-    mut query: Query<(&Position, &mut Force, &PhysicMaterial, &Volume)>,
+    mut query: Query<(
+        &Position,
+        &mut Force,
+        &PhysicMaterial,
+        &Volume,
+        Option<&LocalTimeScale>,
+    )>,
     gravity_query: Query<&Gravity>,
+    time_scale: Res<TimeScale>,
 ) {
     if let Ok(gravity) = gravity_query.get_single() {
-        for (pos, mut force, mat, vol) in query.iter_mut() {
-            force.0 += (gravity.field)(pos.0) * vol.0 * mat.density;
+        for (pos, mut force, mat, vol, local) in query.iter_mut() {
+            force.0 +=
+                (gravity.field)(pos.0) * vol.0 * mat.density * effective_scale(&time_scale, local);
         }
     }
 }
@@ -24,6 +37,13 @@ fn gravity_effect(
 pub(super) struct GravityPlugin;
 impl Plugin for GravityPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(gravity_effect));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                gravity_effect
+                    .after("magnet:force")
+                    .before("execute_force")
+                    .label("gravity:effect"),
+            ),
+        );
     }
 }
@@ -1,10 +1,13 @@
 use crate::{
-    components::physics::{
-        acceleration::Acceleration,
-        force::Force,
-        material::{PhysicMaterial, Volume},
-        position::Position,
-        velocity::Velocity,
+    components::{
+        physics::{
+            acceleration::Acceleration,
+            force::Force,
+            material::{PhysicMaterial, Volume},
+            position::Position,
+            velocity::Velocity,
+        },
+        time_scale::{LocalTimeScale, TimeScale},
     },
     AppState,
 };
@@ -30,15 +33,25 @@ pub fn execute_force(
     }
 }
 
+/// 全体の時間の進む速さとエンティティごとの速さを掛けたもの.
+/// 重力などの続けてはたらく力にも掛ける. 衝突による力は一瞬で速度を変えるものなので掛けない
+pub fn effective_scale(time_scale: &TimeScale, local: Option<&LocalTimeScale>) -> f32 {
+    time_scale.0 * local.map_or(1.0, |local| local.0)
+}
+
 pub fn accelerate(mut q: Query<(&Acceleration, &mut Velocity)>) {
     for (a, mut v) in q.iter_mut() {
         v.0 += a.0;
     }
 }
 
-pub fn move_position(mut q: Query<(&Velocity, &mut Position)>) {
-    for (v, mut p) in q.iter_mut() {
-        p.0 += v.0;
+/// 時間の進む速さの分だけ位置を変える. 速度そのものは変えないので, 元の速さに戻れば同じ速さで動く
+pub fn move_position(
+    time_scale: Res<TimeScale>,
+    mut q: Query<(&Velocity, &mut Position, Option<&LocalTimeScale>)>,
+) {
+    for (v, mut p, local) in q.iter_mut() {
+        p.0 += v.0 * effective_scale(&time_scale, local);
     }
 }
 
@@ -46,15 +59,20 @@ pub(super) struct MotionDynamicsPlugin;
 impl Plugin for MotionDynamicsPlugin {
     fn build(&self, app: &mut App) {
         app.add_system_set(
-            SystemSet::on_update(AppState::Game).with_system(execute_force.label("execute_force")),
+            SystemSet::on_update(AppState::Game)
+                .with_system(execute_force.after("gravity:effect").label("execute_force")),
         );
         app.add_system_set(
             SystemSet::on_update(AppState::Game)
                 .with_system(accelerate.after("execute_force").label("accelerate")),
         );
         app.add_system_set(
-            SystemSet::on_update(AppState::Game)
-                .with_system(move_position.after("accelerate").label("move_pos")),
+            SystemSet::on_update(AppState::Game).with_system(
+                move_position
+                    .after("accelerate")
+                    .after("ball:launch")
+                    .label("move_pos"),
+            ),
         );
     }
 }
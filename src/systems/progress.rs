@@ -0,0 +1,43 @@
+//! キャンペーンの進行状況の読み込みと保存
+use bevy::prelude::*;
+
+use crate::{
+    components::{editor::EditorSession, game::ResultInfoStorage, progress::CampaignProgress},
+    stages::structs::StageInfo,
+    AppState,
+};
+
+use super::utils::{load_ron_file, save_ron_file};
+
+/// 進行状況を保存するファイル
+const PROGRESS_FILE: &str = "save/progress.ron";
+
+/// 保存された進行状況を読み込む. ファイルがない, または読めない場合は最初から始める.
+fn load_progress(mut commands: Commands) {
+    commands.insert_resource(load_ron_file::<CampaignProgress>(PROGRESS_FILE));
+}
+
+/// 結果が出たらステージの記録を更新し, 更新があれば保存する. エディタでのテストプレイは記録しない
+fn record_stage_result(
+    result_info: Option<Res<ResultInfoStorage>>,
+    stage_info: Res<StageInfo>,
+    editor_session: Option<Res<EditorSession>>,
+    mut progress: ResMut<CampaignProgress>,
+) {
+    if editor_session.is_some() {
+        return;
+    }
+    if let Some(result_info) = result_info {
+        if result_info.is_added() && progress.update(stage_info.stage_title, result_info.stars) {
+            save_ron_file(PROGRESS_FILE, &*progress);
+        }
+    }
+}
+
+pub struct ProgressPlugin;
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_progress);
+        app.add_system_set(SystemSet::on_update(AppState::Game).with_system(record_stage_result));
+    }
+}
@@ -0,0 +1,571 @@
+//! ゲーム中の巻き戻し. 毎フレームの状態を記録し, 巻き戻しの入力を押している間はGameの上にRewindingを積んで
+//! 記録をさかのぼる. 離すとその時点から再開する.
+use std::marker::PhantomData;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    components::{
+        ball::{AirborneFrames, Ball, BallNocking, BallRestDetector, GoalinBall},
+        block::{BlockTransform, RotateStrategy, SlideStrategy},
+        block_attach::{
+            switch::{SwitchPressedOnce, SwitchTile},
+            updater::UpdaterVec,
+        },
+        game::{
+            CollectedItems, GameRule, GoaledBall, InitialBallNum, LostBall, NextGoalOrder,
+            NowGameOver, ReachedGoals, Score, StrokeCount,
+        },
+        goal::GoalHole,
+        info::{MagazineUpdating, RemainingTime},
+        input::{ActionInput, InputAction},
+        item::{Checkpoint, CheckpointRecord, CollectedItem, CollectibleItem, ItemType},
+        launcher::{BallMagazine, Launcher, LauncherState},
+        physics::{position::Position, velocity::Velocity},
+        rewind::{
+            BallSnapshot, BlockSnapshot, LauncherSnapshot, RewindBuffer, RewindCount,
+            RewindOverlay, RewoundSwitch, Snapshot, SwitchSnapshot, REWIND_STEP,
+        },
+        timer::{CountDownTimer, FrameCounter},
+    },
+    events::goal::BallGoaledEvent,
+    AppState,
+};
+
+use super::{ball::spawn_ball_entity, launcher::spawn_remaining_ball, pause::set_timers_paused};
+
+type BallQuery<'w, 's, T> =
+    Query<'w, 's, T, (With<Ball>, Without<BlockTransform>, Without<Launcher>)>;
+type LauncherQuery<'w, 's, T> = Query<'w, 's, T, (Without<Ball>, Without<BlockTransform>)>;
+
+/// 毎フレームの移動の後に状態を記録する. ゲームオーバー後は記録しない.
+/// ゴールに吸い込まれている途中のボールは入り終わったものとして得点に数え, 記録には残さない.
+/// 巻き戻しや再開で吸い込まれている途中に戻ると, 数えたゴールと得点が合わなくなるため
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn record_snapshot(
+    mut buffer: ResMut<RewindBuffer>,
+    ball_query: BallQuery<(
+        Entity,
+        &Ball,
+        &Position,
+        &Velocity,
+        Option<&BallNocking>,
+        &AirborneFrames,
+        &CheckpointRecord,
+    )>,
+    goal_in_ball_query: Query<(Entity, &GoalinBall)>,
+    mut goaled_event_reader: EventReader<BallGoaledEvent>,
+    block_query: Query<(
+        Entity,
+        &Transform,
+        &BlockTransform,
+        Option<&RotateStrategy>,
+        Option<&SlideStrategy>,
+        Option<&UpdaterVec>,
+    )>,
+    switch_query: Query<(
+        Entity,
+        &SwitchTile,
+        Option<&SwitchPressedOnce>,
+        Option<&CountDownTimer>,
+    )>,
+    launcher_query: LauncherQuery<(Entity, &Launcher, &LauncherState)>,
+    (goal_query, magazine_query): (Query<(Entity, &GoalHole)>, Query<&BallMagazine>),
+    (score, goaled_ball, lost_ball, stroke_count): (
+        Res<Score>,
+        Res<GoaledBall>,
+        Res<LostBall>,
+        Res<StrokeCount>,
+    ),
+    (next_goal_order, reached_goals): (Res<NextGoalOrder>, Res<ReachedGoals>),
+    item_query: Query<(Entity, Option<&CollectedItem>), With<CollectibleItem>>,
+    checkpoint_query: Query<(Entity, &Checkpoint)>,
+    collected_items: Res<CollectedItems>,
+    is_gameover: Option<Res<NowGameOver>>,
+) {
+    if is_gameover.is_some() {
+        return;
+    }
+    // このフレームで入ったボールにはまだGoalinBallが付いていないので, イベントから調べる
+    let absorbing = goal_in_ball_query
+        .iter()
+        .map(|(ent, goal_in)| (ent, goal_in.0))
+        .chain(goaled_event_reader.iter().map(|ev| (ev.ball, ev.score)))
+        .collect::<Vec<(Entity, u32)>>();
+    let balls = ball_query
+        .iter()
+        .filter(|(ent, ..)| !absorbing.iter().any(|(absorbing, _)| absorbing == ent))
+        .map(
+            |(entity, ball, pos, vel, nocking, airborne, checkpoints)| BallSnapshot {
+                entity: Some(entity),
+                ball_type: ball.ball_type,
+                pos: pos.0,
+                vel: vel.0,
+                nocking: nocking.is_some(),
+                airborne_frames: airborne.0,
+                checkpoints: checkpoints.0.clone(),
+            },
+        )
+        .collect();
+    let blocks = block_query
+        .iter()
+        .map(
+            |(entity, transform, block_transform, rotate, slide, updaters)| BlockSnapshot {
+                entity,
+                transform: *transform,
+                block_transform: block_transform.clone(),
+                rotate_strategy: rotate.cloned(),
+                slide_strategy: slide.cloned(),
+                updaters: updaters.cloned(),
+            },
+        )
+        .collect();
+    let switches = switch_query
+        .iter()
+        .map(|(entity, switch, pressed_once, timer)| SwitchSnapshot {
+            entity,
+            switch: switch.clone(),
+            pressed_once: pressed_once.is_some(),
+            timer: timer.map(|timer| timer.count()),
+        })
+        .collect();
+    let launchers = launcher_query
+        .iter()
+        .map(|(entity, launcher, state)| LauncherSnapshot {
+            entity,
+            angle: launcher.angle,
+            state: *state,
+        })
+        .collect();
+    let magazine = magazine_query
+        .get_single()
+        .map(|mag| {
+            (
+                mag.balls.iter().map(|(ball_type, _)| *ball_type).collect(),
+                mag.selected,
+            )
+        })
+        .unwrap_or_default();
+    buffer.push(Snapshot {
+        balls,
+        blocks,
+        switches,
+        launchers,
+        goal_counts: goal_query
+            .iter()
+            .map(|(entity, goal)| (entity, goal.count))
+            .collect(),
+        magazine,
+        score: score.0 + absorbing.iter().map(|(_, score)| score).sum::<u32>(),
+        goaled_balls: goaled_ball.0 + absorbing.len() as u32,
+        lost_balls: lost_ball.0,
+        strokes: stroke_count.0,
+        next_goal_order: next_goal_order.0,
+        reached_goals: reached_goals.0.clone(),
+        items: item_query
+            .iter()
+            .map(|(entity, collected)| (entity, collected.is_some()))
+            .collect(),
+        checkpoints: checkpoint_query
+            .iter()
+            .map(|(entity, checkpoint)| (entity, checkpoint.passed))
+            .collect(),
+        collected_items: collected_items.0,
+    });
+}
+
+/// 巻き戻しの入力で巻き戻しを始める. ルールで禁止されているとき, ゲームオーバー後, 記録がないときは何もしない.
+/// 同じフレームで一時停止などが先に決まっていれば, 巻き戻したことにはしない
+fn start_rewind(
+    action_in: Res<ActionInput>,
+    rule: Res<GameRule>,
+    buffer: Res<RewindBuffer>,
+    is_gameover: Option<Res<NowGameOver>>,
+    mut rewind_count: ResMut<RewindCount>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if action_in.just_pressed(InputAction::Rewind)
+        && rule.allows_rewind()
+        && is_gameover.is_none()
+        && !buffer.is_empty()
+        && app_state.push(AppState::Rewinding).is_ok()
+    {
+        rewind_count.0 += 1;
+    }
+}
+
+/// 巻き戻している間は制限時間などのタイマーを止め, 画面に表示を出す
+fn enter_rewind(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut timer_query: Query<&mut CountDownTimer>,
+    mut counter_query: Query<&mut FrameCounter>,
+) {
+    set_timers_paused(&mut timer_query, &mut counter_query, true);
+    commands
+        .spawn_bundle(TextBundle {
+            text: Text::from_section(
+                "<< REWIND",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 40.0,
+                    color: Color::ORANGE,
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(20.0),
+                    top: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        })
+        .insert(RewindOverlay);
+}
+
+fn exit_rewind(
+    mut commands: Commands,
+    mut timer_query: Query<&mut CountDownTimer>,
+    mut counter_query: Query<&mut FrameCounter>,
+    overlay_query: Query<Entity, With<RewindOverlay>>,
+) {
+    set_timers_paused(&mut timer_query, &mut counter_query, false);
+    for ent in overlay_query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
+
+/// 記録した状態を書き戻す先. 巻き戻しと中断したゲームの再開で使う
+#[derive(SystemParam)]
+pub struct SnapshotTargets<'w, 's> {
+    commands: Commands<'w, 's>,
+    ball_query: BallQuery<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Position,
+            &'static mut Velocity,
+            &'static mut Transform,
+        ),
+    >,
+    block_query: Query<'w, 's, (&'static mut Transform, &'static mut BlockTransform)>,
+    switch_query: Query<'w, 's, &'static mut SwitchTile>,
+    launcher_query: LauncherQuery<'w, 's, (&'static mut Launcher, &'static mut Transform)>,
+    goal_query: Query<'w, 's, &'static mut GoalHole>,
+    magazine_query: Query<'w, 's, (&'static mut BallMagazine, Entity)>,
+    item_query: Query<'w, 's, (&'static CollectibleItem, Option<&'static CollectedItem>)>,
+    checkpoint_query: Query<'w, 's, &'static mut Checkpoint>,
+    timer_query: Query<'w, 's, &'static mut CountDownTimer, With<RemainingTime>>,
+    counters: SnapshotCounters<'w, 's>,
+}
+
+/// 記録した状態を書き戻す先のうち, 数を数えるリソース
+#[derive(SystemParam)]
+pub struct SnapshotCounters<'w, 's> {
+    score: ResMut<'w, Score>,
+    goaled_ball: ResMut<'w, GoaledBall>,
+    lost_ball: ResMut<'w, LostBall>,
+    stroke_count: ResMut<'w, StrokeCount>,
+    next_goal_order: ResMut<'w, NextGoalOrder>,
+    reached_goals: ResMut<'w, ReachedGoals>,
+    collected_items: ResMut<'w, CollectedItems>,
+    init_ball_num: ResMut<'w, InitialBallNum>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> SnapshotTargets<'w, 's> {
+    /// 状態を書き戻す. 出し直したボールのエンティティは記録の中でも置き換える.
+    /// 巻き戻し中はpausedをtrueにして, 作り直したタイマーを巻き戻しが終わるまで止めておく
+    pub fn apply(&mut self, snapshot: Snapshot, buffer: &mut RewindBuffer, paused: bool) {
+        let commands = &mut self.commands;
+
+        // 記録にないボールは消し, 記録にあるが消えてしまったボールは出し直す
+        for (ent, ..) in self.ball_query.iter() {
+            if !snapshot.balls.iter().any(|ball| ball.entity == Some(ent)) {
+                commands.entity(ent).despawn();
+            }
+        }
+        for ball in snapshot.balls.iter() {
+            let existing = ball
+                .entity
+                .and_then(|ent| self.ball_query.get_mut(ent).ok());
+            let ent = if let Some((ent, mut pos, mut vel, mut trans)) = existing {
+                pos.0 = ball.pos;
+                vel.0 = ball.vel;
+                trans.translation = ball.pos.extend(trans.translation.z);
+                // ゴールに吸い込まれている途中だった場合は, 縮んだ大きさも戻す
+                trans.scale = Vec3::ONE;
+                ent
+            } else {
+                let ent = spawn_ball_entity(commands, ball.ball_type, ball.pos);
+                commands.entity(ent).insert(Velocity(ball.vel));
+                if let Some(old) = ball.entity {
+                    buffer.replace_entity(old, ent);
+                }
+                ent
+            };
+            let mut entity_commands = commands.entity(ent);
+            if ball.nocking {
+                entity_commands.insert(BallNocking);
+            } else {
+                entity_commands.remove::<BallNocking>();
+            }
+            // 記録にあるボールはゴールに入る前のものなので, 入ったことを取り消す
+            entity_commands
+                .remove::<GoalinBall>()
+                .insert(AirborneFrames(ball.airborne_frames))
+                .insert(CheckpointRecord(ball.checkpoints.clone()))
+                .insert(BallRestDetector::default());
+        }
+
+        for block in snapshot.blocks.into_iter() {
+            if let Ok((mut trans, mut block_trans)) = self.block_query.get_mut(block.entity) {
+                *trans = block.transform;
+                *block_trans = block.block_transform;
+            }
+            let mut entity_commands = commands.entity(block.entity);
+            if let Some(strategy) = block.rotate_strategy {
+                entity_commands.insert(strategy);
+            }
+            if let Some(strategy) = block.slide_strategy {
+                entity_commands.insert(strategy);
+            }
+            match block.updaters {
+                Some(updaters) => entity_commands.insert(updaters),
+                None => entity_commands.remove::<UpdaterVec>(),
+            };
+        }
+
+        for switch in snapshot.switches.into_iter() {
+            if let Ok(mut tile) = self.switch_query.get_mut(switch.entity) {
+                *tile = switch.switch;
+            }
+            let mut entity_commands = commands.entity(switch.entity);
+            if switch.pressed_once {
+                entity_commands.insert(SwitchPressedOnce);
+            } else {
+                entity_commands.remove::<SwitchPressedOnce>();
+            }
+            match switch.timer {
+                Some(count) => {
+                    let mut timer = CountDownTimer::new_will_not_be_removed(count);
+                    timer.set_paused(paused);
+                    entity_commands.insert(timer)
+                }
+                None => entity_commands.remove::<CountDownTimer>(),
+            };
+            // 巻き戻しで変わった押下状態をスイッチの発動とみなさないようにする
+            entity_commands.insert(RewoundSwitch);
+        }
+
+        for launcher in snapshot.launchers.iter() {
+            if let Ok((mut component, mut trans)) = self.launcher_query.get_mut(launcher.entity) {
+                component.angle = launcher.angle;
+                trans.rotation = Quat::from_rotation_z(launcher.angle);
+            }
+            // 長押しの途中だった場合は取り消す
+            commands
+                .entity(launcher.entity)
+                .insert(launcher.state)
+                .remove::<FrameCounter>();
+        }
+
+        for (entity, count) in snapshot.goal_counts.iter() {
+            if let Ok(mut goal) = self.goal_query.get_mut(*entity) {
+                goal.count = *count;
+            }
+        }
+
+        // 残りのボールが変わっていれば表示を作り直す
+        if let Ok((mut magazine, magazine_ent)) = self.magazine_query.get_single_mut() {
+            let (ball_types, selected) = snapshot.magazine;
+            let current_types = magazine
+                .balls
+                .iter()
+                .map(|(ball_type, _)| *ball_type)
+                .collect::<Vec<_>>();
+            if current_types != ball_types || magazine.selected != selected {
+                for (_, ent) in magazine.balls.iter() {
+                    commands.entity(*ent).despawn();
+                }
+                magazine.balls = ball_types
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, ball_type)| {
+                        (ball_type, spawn_remaining_ball(commands, ball_type, idx))
+                    })
+                    .collect();
+                magazine.selected = selected;
+                commands.entity(magazine_ent).insert(MagazineUpdating);
+            }
+        }
+
+        // 取得したかどうかが変わったアイテムは, 記録していない効果（延びた時間と増えたボールの数）も合わせる
+        let counters = &mut self.counters;
+        for (entity, collected) in snapshot.items.iter() {
+            let item_type = match self.item_query.get(*entity) {
+                Ok((item, current)) if current.is_some() != *collected => item.item_type,
+                _ => continue,
+            };
+            match item_type {
+                ItemType::Coin(_) => {}
+                ItemType::BonusTime(frames) => {
+                    for mut timer in self.timer_query.iter_mut() {
+                        if *collected {
+                            timer.extend(frames);
+                        } else {
+                            timer.shorten(frames);
+                        }
+                    }
+                }
+                ItemType::ExtraBall(_) => {
+                    counters.init_ball_num.0 = if *collected {
+                        counters.init_ball_num.0 + 1
+                    } else {
+                        counters.init_ball_num.0.saturating_sub(1)
+                    };
+                }
+            }
+            let mut entity_commands = commands.entity(*entity);
+            entity_commands.insert(Visibility {
+                is_visible: !*collected,
+            });
+            if *collected {
+                entity_commands.insert(CollectedItem);
+            } else {
+                entity_commands.remove::<CollectedItem>();
+            }
+        }
+
+        for (entity, passed) in snapshot.checkpoints.iter() {
+            if let Ok(mut checkpoint) = self.checkpoint_query.get_mut(*entity) {
+                if checkpoint.passed != *passed {
+                    checkpoint.passed = *passed;
+                }
+            }
+        }
+
+        counters.score.0 = snapshot.score;
+        counters.goaled_ball.0 = snapshot.goaled_balls;
+        counters.lost_ball.0 = snapshot.lost_balls;
+        counters.stroke_count.0 = snapshot.strokes;
+        counters.next_goal_order.0 = snapshot.next_goal_order;
+        counters.reached_goals.0 = snapshot.reached_goals;
+        counters.collected_items.0 = snapshot.collected_items;
+    }
+}
+
+/// 押している間は記録をさかのぼって状態を戻す. 離したらゲームに戻る
+fn rewind(
+    action_in: Res<ActionInput>,
+    mut app_state: ResMut<State<AppState>>,
+    mut buffer: ResMut<RewindBuffer>,
+    mut targets: SnapshotTargets,
+) {
+    if !action_in.pressed(InputAction::Rewind) {
+        app_state.pop().unwrap();
+        return;
+    }
+    if let Some(snapshot) = buffer.rewind(REWIND_STEP) {
+        targets.apply(snapshot, &mut buffer, true);
+    }
+    // 記録がなければこれ以上さかのぼれないので, 離すまでそのまま止めておく
+}
+
+/// 再開した最初のフレームが終わったら印を外す
+fn clear_rewound_switch(mut commands: Commands, query: Query<Entity, With<RewoundSwitch>>) {
+    for ent in query.iter() {
+        commands.entity(ent).remove::<RewoundSwitch>();
+    }
+}
+
+pub struct RewindPlugin;
+impl Plugin for RewindPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                // 吸い込み終わったボールを二重に数えないように, 得点を足すより前に記録する
+                .with_system(
+                    record_snapshot
+                        .after("move_pos")
+                        .before("goal:execute_goaled")
+                        .label("rewind:record"),
+                )
+                .with_system(start_rewind)
+                .with_system(clear_rewound_switch.after("switch:execute_change")),
+        );
+        app.add_system_set(SystemSet::on_enter(AppState::Rewinding).with_system(enter_rewind));
+        app.add_system_set(SystemSet::on_update(AppState::Rewinding).with_system(rewind));
+        app.add_system_set(SystemSet::on_exit(AppState::Rewinding).with_system(exit_rewind));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::{CommandQueue, SystemState};
+
+    use super::*;
+    use crate::{
+        components::ball::BallType,
+        stages::file::{GoalFile, StageFile},
+        systems::simulate::{headless_app, start_game},
+    };
+
+    #[test]
+    fn rewound_ball_is_no_longer_absorbed() {
+        let stage_file = StageFile {
+            time: 600,
+            balls: vec![BallType::Normal, BallType::Normal],
+            ..Default::default()
+        };
+        let stage_info = StageFile {
+            goals: vec![GoalFile {
+                pos: stage_file.launcher.pos + Vec2::new(300.0, 0.0),
+                ..Default::default()
+            }],
+            ..stage_file
+        }
+        .to_stage_info();
+        let mut app = headless_app(&stage_info);
+        app.add_plugin(RewindPlugin);
+        start_game(&mut app);
+        let mut queue = CommandQueue::default();
+        let ball = {
+            let mut commands = Commands::new(&mut queue, &app.world);
+            let ball = spawn_ball_entity(&mut commands, BallType::Normal, Vec2::ZERO);
+            commands.entity(ball).remove::<BallNocking>();
+            ball
+        };
+        queue.apply(&mut app.world);
+        for _ in 0..5 {
+            app.update();
+        }
+
+        // 止まっているボールがゴールに吸い込まれている途中で巻き戻したことにする
+        let mut ball_mut = app.world.entity_mut(ball);
+        ball_mut.insert(GoalinBall(1));
+        ball_mut.get_mut::<Transform>().unwrap().scale = Vec3::splat(0.5);
+        let mut buffer = app.world.remove_resource::<RewindBuffer>().unwrap();
+        let snapshot = buffer.rewind(1).unwrap();
+        let mut state = SystemState::<SnapshotTargets>::new(&mut app.world);
+        state
+            .get_mut(&mut app.world)
+            .apply(snapshot, &mut buffer, false);
+        state.apply(&mut app.world);
+        app.world.insert_resource(buffer);
+        assert!(app.world.get::<GoalinBall>(ball).is_none());
+        assert_eq!(app.world.get::<Transform>(ball).unwrap().scale, Vec3::ONE);
+
+        for _ in 0..60 {
+            app.update();
+        }
+        assert!(app.world.get_entity(ball).is_some());
+        assert_eq!(app.world.resource::<GoaledBall>().0, 0);
+        assert_eq!(app.world.resource::<Score>().0, 0);
+    }
+}
@@ -0,0 +1,142 @@
+//! ウィンドウの大きさに合わせて論理的な画面を拡大縮小し, 余った部分を黒帯で隠す
+use bevy::{prelude::*, ui::UiSystem};
+
+use crate::{
+    components::{
+        camera::hud_layer,
+        input::{ActionInput, InputAction},
+        screen::{LetterboxBar, ScreenLayout, ScreenPosition},
+        settings::GameSettings,
+    },
+    SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/// 黒帯はどんな縦横比のウィンドウでも隠しきれるように十分大きくする
+const BAR_LENGTH: f32 = 100000.0;
+
+/// 論理的な画面の上下左右に黒帯を置く（カメラは論理的な画面が必ず収まるように映す）
+fn setup_screen(mut commands: Commands) {
+    let half_screen = Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0;
+    let half_bar = BAR_LENGTH / 2.0;
+    for (pos, size) in [
+        (
+            Vec2::new(-half_screen.x - half_bar, 0.0),
+            Vec2::splat(BAR_LENGTH),
+        ),
+        (
+            Vec2::new(half_screen.x + half_bar, 0.0),
+            Vec2::splat(BAR_LENGTH),
+        ),
+        (
+            Vec2::new(0.0, -half_screen.y - half_bar),
+            Vec2::splat(BAR_LENGTH),
+        ),
+        (
+            Vec2::new(0.0, half_screen.y + half_bar),
+            Vec2::splat(BAR_LENGTH),
+        ),
+    ] {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::BLACK,
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(pos.extend(999.0)),
+                ..Default::default()
+            })
+            .insert(LetterboxBar)
+            .insert(hud_layer());
+    }
+}
+
+/// ウィンドウの大きさから論理的な画面の表示位置と倍率を計算する
+fn update_screen_layout(windows: Res<Windows>, mut layout: ResMut<ScreenLayout>) {
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    if window_size.min_element() <= 0.0 {
+        // 最小化されている
+        return;
+    }
+    let scale = (window_size.x / SCREEN_WIDTH).min(window_size.y / SCREEN_HEIGHT);
+    let new_layout = ScreenLayout {
+        scale,
+        offset: (window_size - Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT) * scale) / 2.0,
+    };
+    // 変化があったときだけ書き換えてUIの再配置を起こす
+    if *layout != new_layout {
+        *layout = new_layout;
+    }
+}
+
+/// 絶対位置で置かれたUIを論理的な画面に合わせて配置し, 文字の大きさも合わせる.
+/// 初めて見たUIはそのときの位置と文字の大きさを論理的な画面でのものとして記録する.
+#[allow(clippy::type_complexity)]
+fn place_ui_in_screen(
+    mut commands: Commands,
+    layout: Res<ScreenLayout>,
+    mut new_query: Query<
+        (Entity, &mut Style, Option<&mut Text>),
+        (With<Node>, Without<Parent>, Without<ScreenPosition>),
+    >,
+    mut placed_query: Query<(&ScreenPosition, &mut Style, Option<&mut Text>)>,
+) {
+    let fit = |screen_pos: &ScreenPosition, style: &mut Style, text: Option<Mut<Text>>| {
+        style.position = layout.window_position(screen_pos.position);
+        if let Some(mut text) = text {
+            for (section, size) in text.sections.iter_mut().zip(&screen_pos.font_sizes) {
+                section.style.font_size = size * layout.scale;
+            }
+        }
+    };
+    if layout.is_changed() {
+        for (screen_pos, mut style, text) in placed_query.iter_mut() {
+            fit(screen_pos, &mut style, text);
+        }
+    }
+    for (ent, mut style, text) in new_query.iter_mut() {
+        if style.position_type != PositionType::Absolute {
+            continue;
+        }
+        let screen_pos = ScreenPosition {
+            position: style.position,
+            font_sizes: text.as_ref().map_or(vec![], |text| {
+                text.sections
+                    .iter()
+                    .map(|section| section.style.font_size)
+                    .collect()
+            }),
+        };
+        fit(&screen_pos, &mut style, text);
+        commands.entity(ent).insert(screen_pos);
+    }
+}
+
+/// F11キーでフルスクリーンを切り替える（設定に保存される）
+fn toggle_fullscreen(action_in: Res<ActionInput>, mut settings: ResMut<GameSettings>) {
+    if action_in.just_pressed(InputAction::ToggleFullscreen) {
+        settings.fullscreen = !settings.fullscreen;
+    }
+}
+
+pub struct ScreenPlugin;
+impl Plugin for ScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenLayout>();
+        app.add_startup_system(setup_screen.after("global_setup"));
+        app.add_system(toggle_fullscreen);
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_screen_layout.before(place_ui_in_screen),
+        );
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            place_ui_in_screen.before(UiSystem::Flex),
+        );
+    }
+}
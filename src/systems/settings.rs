@@ -0,0 +1,93 @@
+//! 設定の読み込み, 保存, 反映と設定画面での値の変更
+use bevy::{prelude::*, window::WindowMode};
+
+use crate::{
+    components::{
+        input::{ActionInput, InputAction},
+        main_menu::menu::CurrentOption,
+        settings::{GameSettings, SettingKey},
+    },
+    AppState,
+};
+
+use super::utils::{load_ron_file, save_ron_file};
+
+/// 設定を保存するファイル
+const SETTINGS_FILE: &str = "save/settings.ron";
+
+fn load_settings(mut commands: Commands) {
+    commands.insert_resource(load_ron_file::<GameSettings>(SETTINGS_FILE).sanitized());
+}
+
+/// 設定が変わったら保存する
+fn save_settings(settings: Res<GameSettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        save_ron_file(SETTINGS_FILE, &*settings);
+    }
+}
+
+/// ウィンドウに関わる設定を反映する（起動時にも反映される）. ほかの設定が変わっただけなら何もしない
+fn apply_window_settings(
+    settings: Res<GameSettings>,
+    mut windows: ResMut<Windows>,
+    mut applied: Local<Option<(u32, bool)>>,
+) {
+    let window_settings = (settings.window_scale, settings.fullscreen);
+    if *applied == Some(window_settings) {
+        return;
+    }
+    *applied = Some(window_settings);
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_scale_factor_override(Some(settings.window_scale as f64 / 100.0));
+        window.set_mode(if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        });
+    }
+}
+
+/// 選択中の設定項目の値を上下キーで変更する. トグルはZキーでも切り替えられる
+fn adjust_setting(
+    action_in: Res<ActionInput>,
+    current_query: Query<&SettingKey, With<CurrentOption>>,
+    mut settings: ResMut<GameSettings>,
+) {
+    if let Ok(key) = current_query.get_single() {
+        if action_in.any_just_pressed([InputAction::Up, InputAction::Action]) {
+            key.adjust(&mut settings, true);
+        } else if action_in.just_pressed(InputAction::Down) {
+            key.adjust(&mut settings, false);
+        }
+    }
+}
+
+/// 設定項目の表示を現在の値に合わせる
+fn update_setting_texts(
+    settings: Res<GameSettings>,
+    mut text_query: Query<(&mut Text, &SettingKey)>,
+    added_query: Query<(), Added<SettingKey>>,
+) {
+    if !settings.is_changed() && added_query.is_empty() {
+        return;
+    }
+    for (mut text, key) in text_query.iter_mut() {
+        text.sections[0].value = format!("{}: {}", key.label(), key.value_text(&settings));
+    }
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(load_settings);
+        app.add_system(save_settings);
+        app.add_system(apply_window_settings);
+        for state in [AppState::Menu, AppState::Paused] {
+            app.add_system_set(
+                SystemSet::on_update(state)
+                    .with_system(adjust_setting)
+                    .with_system(update_setting_texts),
+            );
+        }
+    }
+}
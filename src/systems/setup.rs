@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
+use super::camera::spawn_cameras;
+
 pub fn global_setup(mut commands: Commands) {
     // カメラのセット
-    commands.spawn_bundle(Camera2dBundle::default());
+    spawn_cameras(&mut commands);
 }
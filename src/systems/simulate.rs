@@ -0,0 +1,311 @@
+//! 画面を出さずにステージを動かす. ソルバーの探索, リプレイの検証, 軌跡の描画で使う.
+//! ゲームと同じシステムを使い, 記録した操作を1フレームずつ与えて結果を調べる.
+use bevy::{
+    asset::AssetPlugin,
+    ecs::schedule::SingleThreadedExecutor,
+    hierarchy::HierarchyPlugin,
+    input::{gamepad::gamepad_connection_system, InputSystem},
+    prelude::*,
+    transform::{TransformPlugin, TransformSystem},
+};
+
+use crate::{
+    components::{
+        ball::{Ball, BallNocking, BallType, MetalBall},
+        game::{
+            GameRule, GoaledBall, OperationAmount, PassedTime, ResultInfoStorage, StrokeCount,
+            WinConditionMet,
+        },
+        goal::GoalHole,
+        input::{ActionInput, InputBindings},
+        launcher::LauncherState,
+        replay::{InputScript, RecordedOutcome, ScriptPlayback},
+        settings::GameSettings,
+    },
+    events::add_events,
+    stages::structs::StageInfo,
+    AppState,
+};
+
+use super::{
+    ball::BallPlugin, block::BlockPlugin, block_attach::BlockAttachmentPlugin,
+    collision::CollisionPlugin, control::ControlGroupPlugin, field::FieldPlugin,
+    game::GameManagePlugin, goal::GoalPlugin, hazard::HazardPlugin, info_board::InfoBoardPlugin,
+    input::update_action_input, item::ItemPlugin, launcher::LauncherPlugin, load::LoadStagePlugin,
+    physics::PhysicsPlugin, time_scale::TimeScalePlugin, timer::TimersPlugin,
+};
+
+/// 記録を再生し終えた後, 何も起きなくなってから打ち切るまでのフレーム数
+const IDLE_FRAMES: u32 = 60;
+
+/// シミュレーションの結果
+#[derive(Clone, Copy, Debug)]
+pub struct SimulationOutcome {
+    /// ボールをゴールに入れた（クリア条件を満たした）か
+    pub cleared: bool,
+    /// 経過時間（フレーム）
+    pub frames: u32,
+    /// 操作量
+    pub operations: u32,
+    /// 発射したボールの数
+    pub strokes: u32,
+    /// ボールがゴールの縁に最も近づいたときの距離. 入ったなら0
+    pub closest: f32,
+}
+impl SimulationOutcome {
+    /// リプレイに保存して再生時に比べる値
+    pub fn recorded(&self) -> RecordedOutcome {
+        RecordedOutcome {
+            frames: self.frames,
+            operations: self.operations,
+            strokes: self.strokes,
+        }
+    }
+}
+
+/// ボールがゴールの縁に最も近づいた距離
+struct ClosestApproach(f32);
+
+fn track_closest_approach(
+    ball_query: Query<&GlobalTransform, With<Ball>>,
+    goal_query: Query<(&GlobalTransform, &GoalHole)>,
+    mut closest: ResMut<ClosestApproach>,
+) {
+    for ball_trans in ball_query.iter() {
+        for (goal_trans, goal) in goal_query.iter() {
+            let distance = ball_trans
+                .translation()
+                .truncate()
+                .distance(goal_trans.translation().truncate())
+                - goal.radius;
+            closest.0 = closest.0.min(distance.max(0.0));
+        }
+    }
+}
+
+/// 画面や音を使わず, ゲームの進行に必要なプラグインだけを入れたアプリを作る.
+/// 同じ操作から同じ結果になるのは, ゲームのシステムの実行順をすべてラベルで決めてあるから.
+/// 順番の決まっていない組があれば`ReportExecutionOrderAmbiguities`を入れると表示される.
+/// コマンドを反映する順番まで揃うように, すべて1スレッドで順に動かす.
+pub(super) fn headless_app(stage_info: &StageInfo) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugin(TransformPlugin);
+    app.add_plugin(HierarchyPlugin);
+    app.add_plugin(bevy::input::InputPlugin);
+    app.add_plugin(AssetPlugin);
+    add_events(&mut app);
+    app.add_state(AppState::Loading);
+    app.insert_resource(stage_info.clone());
+    app.insert_resource(stage_info.rule.unwrap_or(GameRule::Strokes));
+    app.insert_resource(GameSettings::default());
+    // 操作の割り当てのファイルに関わらず同じ結果になるように既定の割り当てを使う
+    app.insert_resource(InputBindings::default());
+    app.init_resource::<ActionInput>();
+    app.insert_resource(ClosestApproach(f32::INFINITY));
+    app.add_system_to_stage(
+        CoreStage::PreUpdate,
+        update_action_input
+            .after(InputSystem)
+            .after(gamepad_connection_system),
+    );
+    app.add_system_to_stage(
+        CoreStage::PostUpdate,
+        track_closest_approach.after(TransformSystem::TransformPropagate),
+    );
+    app.add_plugin(LoadStagePlugin);
+    app.add_plugin(FieldPlugin);
+    app.add_plugin(GoalPlugin);
+    app.add_plugin(ItemPlugin);
+    app.add_plugin(HazardPlugin);
+    app.add_plugin(BallPlugin);
+    app.add_plugin(BlockPlugin);
+    app.add_plugin(BlockAttachmentPlugin);
+    app.add_plugin(CollisionPlugin);
+    app.add_plugin(LauncherPlugin);
+    app.add_plugin(PhysicsPlugin);
+    app.add_plugin(InfoBoardPlugin);
+    app.add_plugin(TimersPlugin);
+    app.add_plugin(TimeScalePlugin);
+    app.add_plugin(GameManagePlugin);
+    app.add_plugin(ControlGroupPlugin);
+    for stage in [
+        CoreStage::First,
+        CoreStage::PreUpdate,
+        CoreStage::Update,
+        CoreStage::PostUpdate,
+        CoreStage::Last,
+    ] {
+        if let Some(stage) = app.schedule.get_stage_mut::<SystemStage>(&stage) {
+            stage.set_executor(Box::new(SingleThreadedExecutor));
+        }
+    }
+    app
+}
+
+/// ロードを終えてゲームが始まるまで動かす. テストでゲーム中の状態を作るのに使う
+#[cfg(test)]
+pub(super) fn start_game(app: &mut App) {
+    for _ in 0..60 {
+        app.update();
+        if *app.world.resource::<State<AppState>>().current() == AppState::Game {
+            return;
+        }
+    }
+    panic!("the game did not start");
+}
+
+/// ボール一つが通った位置
+#[derive(Clone, Debug)]
+pub struct BallTrajectory {
+    pub ball_type: BallType,
+    pub points: Vec<Vec2>,
+}
+
+/// 場に出たボールごとの軌跡
+#[derive(Default)]
+struct BallTrajectories(Vec<(Entity, BallTrajectory)>);
+
+/// 番えている間はランチャーの位置にいるので, 発射された後の位置を記録する
+#[allow(clippy::type_complexity)]
+fn record_trajectories(
+    ball_query: Query<(Entity, &Transform, Option<&MetalBall>), (With<Ball>, Without<BallNocking>)>,
+    mut trajectories: ResMut<BallTrajectories>,
+) {
+    for (ent, trans, metal) in ball_query.iter() {
+        let pos = trans.translation.truncate();
+        match trajectories.0.iter_mut().find(|(e, _)| *e == ent) {
+            Some((_, trajectory)) => trajectory.points.push(pos),
+            None => {
+                let ball_type = if metal.is_some() {
+                    BallType::Metal
+                } else {
+                    BallType::Normal
+                };
+                trajectories.0.push((
+                    ent,
+                    BallTrajectory {
+                        ball_type,
+                        points: vec![pos],
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// 記録した操作でステージを遊び, 結果を返す. ルールはステージで決められていなければ打数ルールを使う.
+pub fn simulate(stage_info: &StageInfo, script: &InputScript) -> SimulationOutcome {
+    play_script(&mut headless_app(stage_info), stage_info, script)
+}
+
+/// 記録した操作でステージを遊び, 結果と場に出たボールの軌跡を返す
+pub fn simulate_trajectories(
+    stage_info: &StageInfo,
+    script: &InputScript,
+) -> (SimulationOutcome, Vec<BallTrajectory>) {
+    let mut app = headless_app(stage_info);
+    app.init_resource::<BallTrajectories>();
+    app.add_system_to_stage(CoreStage::PostUpdate, record_trajectories);
+    let outcome = play_script(&mut app, stage_info, script);
+    let trajectories = app
+        .world
+        .remove_resource::<BallTrajectories>()
+        .map(|trajectories| {
+            trajectories
+                .0
+                .into_iter()
+                .map(|(_, trajectory)| trajectory)
+                .collect()
+        })
+        .unwrap_or_default();
+    (outcome, trajectories)
+}
+
+/// ボールをゴールに入れた（ステージ固有のクリア条件があればそれを満たした）か
+fn is_cleared(world: &World, stage_info: &StageInfo) -> bool {
+    if stage_info.win_conditions.is_empty() {
+        world
+            .get_resource::<GoaledBall>()
+            .map_or(0, |goaled| goaled.0)
+            != 0
+    } else {
+        world
+            .get_resource::<WinConditionMet>()
+            .is_some_and(|met| met.0)
+    }
+}
+
+/// ゲームが始まったら記録の再生を始め, ゲームが終わるまで動かす.
+/// 記録を再生し終えて場にボールがない状態がしばらく続いたら, それ以上は何も起きないので打ち切る.
+/// 経過時間はゲームの終わりではなく, 最初にクリアしたときのものを返す.
+fn play_script(app: &mut App, stage_info: &StageInfo, script: &InputScript) -> SimulationOutcome {
+    // ロード待ちとタイマー切れの後の結果表示待ちの分だけ余裕を持たせる
+    let max_frames = stage_info.time + 120;
+    let mut playing = false;
+    let mut idle_frames = 0;
+    let mut cleared_at = None;
+    for _ in 0..max_frames {
+        app.update();
+        let world = &mut app.world;
+        if !playing {
+            // ゲームが始まってから記録を再生する
+            if *world.resource::<State<AppState>>().current() == AppState::Game {
+                world.insert_resource(ScriptPlayback::new(script.clone()));
+                playing = true;
+            }
+            continue;
+        }
+        if cleared_at.is_none() && is_cleared(world, stage_info) {
+            cleared_at = world.get_resource::<PassedTime>().map(|passed| passed.0);
+        }
+        if world.contains_resource::<ResultInfoStorage>() {
+            break;
+        }
+        let finished = world.resource::<ScriptPlayback>().is_finished();
+        let no_ball = world
+            .query_filtered::<(), With<Ball>>()
+            .iter(world)
+            .next()
+            .is_none();
+        let waiting = world
+            .query::<&LauncherState>()
+            .iter(world)
+            .all(|state| matches!(state, LauncherState::Waiting));
+        // ゴールに吸い込まれている途中のボールは場にないので, 得点が入るまで少し待つ
+        idle_frames = if finished && no_ball && waiting {
+            idle_frames + 1
+        } else {
+            0
+        };
+        if idle_frames > IDLE_FRAMES {
+            break;
+        }
+    }
+    let world = &app.world;
+    let closest = world.resource::<ClosestApproach>().0;
+    match world.get_resource::<ResultInfoStorage>() {
+        Some(result) => SimulationOutcome {
+            cleared: result.stars > 0,
+            frames: cleared_at.unwrap_or(result.passed_time),
+            operations: result.operations,
+            strokes: result.balls_used,
+            closest: if result.stars > 0 { 0.0 } else { closest },
+        },
+        // 打ち切った後は時間切れまで何も起きないので, 時間切れでゲームが終わったものとして扱う
+        None => {
+            let cleared = is_cleared(world, stage_info);
+            SimulationOutcome {
+                cleared,
+                frames: cleared_at.unwrap_or(stage_info.time),
+                operations: world
+                    .get_resource::<OperationAmount>()
+                    .map_or(0, |amount| amount.0),
+                strokes: world
+                    .get_resource::<StrokeCount>()
+                    .map_or(0, |count| count.0),
+                closest: if cleared { 0.0 } else { closest },
+            }
+        }
+    }
+}
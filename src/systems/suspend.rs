@@ -0,0 +1,561 @@
+//! ゲームの中断と再開. メニューに戻るときに最後に記録した状態をファイルに書き出し,
+//! メニューのContinueでステージを作り直してから書き戻す.
+use bevy::{prelude::*, window::WindowCloseRequested};
+
+use crate::{
+    components::{
+        block_attach::{
+            switch::{SwitchReceiver, SwitchType},
+            updater::{Updater, UpdaterType, UpdaterVec},
+        },
+        control::ActiveControlGroup,
+        game::{
+            BounceCount, CollectedItems, CourseProgress, GameRule, LongestAirborne, MaxBallSpeed,
+            NowGameOver, OperationAmount, PassedTime, WinCondition,
+        },
+        info::RemainingTime,
+        rewind::{
+            BallSnapshot, BlockSnapshot, LauncherSnapshot, RewindBuffer, RewindCount, Snapshot,
+            SwitchSnapshot,
+        },
+        suspend::{
+            SavedBall, SavedBlock, SavedCourse, SavedGame, SavedLauncher, SavedSwitch,
+            SavedUpdater, SavedUpdaterType, StageObjectId,
+        },
+        time_scale::SlowMotion,
+        timer::CountDownTimer,
+    },
+    events::game::SuspendGameEvent,
+    stages::{course_vec, find_stage, structs::StageInfo},
+    AppState,
+};
+
+use super::{rewind::SnapshotTargets, utils::save_ron_file};
+
+#[cfg(not(test))]
+const SUSPEND_FILE: &str = "save/suspended.ron";
+/// テストでは遊んでいるときの保存データを上書きしない
+#[cfg(test)]
+const SUSPEND_FILE: &str = "target/test-save/suspended.ron";
+
+/// 中断したゲームがあれば読み込む
+pub fn load_suspended_game() -> Option<SavedGame> {
+    let text = std::fs::read_to_string(SUSPEND_FILE).ok()?;
+    ron::from_str(&text)
+        .map_err(|err| warn!("failed to parse {}: {}", SUSPEND_FILE, err))
+        .ok()
+}
+
+/// 中断したゲームを再開する. ステージとルールを用意してLoadingに移り, 状態はゲーム開始後に書き戻す.
+/// 書き戻したデータは消すので, 同じ状態から何度も遊ぶことはできない.
+/// 同じフレームで他の画面への切り替えが決まっていれば再開せず, データも残しておく
+pub fn resume_suspended_game(commands: &mut Commands, app_state: &mut State<AppState>) {
+    let saved = if let Some(saved) = load_suspended_game() {
+        saved
+    } else {
+        return;
+    };
+    let mut stage_info = if let Some(stage_info) = find_stage(&saved.stage_title) {
+        stage_info
+    } else {
+        warn!("suspended stage {} is not found", saved.stage_title);
+        return;
+    };
+    stage_info.par = saved.par;
+    if app_state.set(AppState::Loading).is_err() {
+        return;
+    }
+    if let Some(saved_course) = &saved.course {
+        if let Some(course) = course_vec()
+            .into_iter()
+            .find(|course| course.title == saved_course.title)
+        {
+            let mut course = CourseProgress::new(course.title, course.stages);
+            course.current = saved_course.current;
+            course.total_strokes = saved_course.total_strokes;
+            course.total_par = saved_course.total_par;
+            commands.insert_resource(course);
+        }
+    }
+    commands.insert_resource(stage_info);
+    commands.insert_resource(saved.rule);
+    commands.insert_resource(saved);
+}
+
+fn save_updaters(updaters: &UpdaterVec) -> Vec<SavedUpdater> {
+    updaters
+        .0
+        .iter()
+        .filter_map(|updater| {
+            let updater_type = match updater.updater_type {
+                UpdaterType::None => return None,
+                UpdaterType::BlockPos { .. } => SavedUpdaterType::BlockPos,
+                UpdaterType::BlockAngle { .. } => SavedUpdaterType::BlockAngle,
+            };
+            Some(SavedUpdater {
+                current_range: updater.current_range.clone(),
+                range: updater.range.clone(),
+                updater_type,
+            })
+        })
+        .collect()
+}
+
+/// 保存した変化を, ブロックについているスイッチの効果から関数を取り出して作り直す
+fn restore_updater(saved: &SavedUpdater, receiver: Option<&SwitchReceiver>) -> Option<Updater> {
+    let updater_type = match (saved.updater_type, &receiver?.switch_type) {
+        (SavedUpdaterType::BlockPos, SwitchType::MoveBlock { func, .. }) => {
+            UpdaterType::BlockPos { func: *func }
+        }
+        (SavedUpdaterType::BlockAngle, SwitchType::RotateBlock { func, .. }) => {
+            UpdaterType::BlockAngle { func: *func }
+        }
+        _ => return None,
+    };
+    Some(Updater {
+        current_range: saved.current_range.clone(),
+        range: saved.range.clone(),
+        updater_type,
+        carry: 0.0,
+    })
+}
+
+/// ゲーム中にウィンドウを閉じたときやEscで終了したときも中断したことにする
+fn suspend_on_window_close(
+    mut close_events: EventReader<WindowCloseRequested>,
+    key_in: Res<Input<KeyCode>>,
+    is_gameover: Option<Res<NowGameOver>>,
+    mut suspend_event_writer: EventWriter<SuspendGameEvent>,
+) {
+    let closing = close_events.iter().count() > 0 || key_in.just_pressed(KeyCode::Escape);
+    if closing && is_gameover.is_none() {
+        suspend_event_writer.send(SuspendGameEvent);
+    }
+}
+
+/// メニューに戻るときに最後に記録した状態を書き出す. ファイルから作り直せないステージでは保存しない
+#[allow(clippy::too_many_arguments)]
+fn suspend_game(
+    mut event_reader: EventReader<SuspendGameEvent>,
+    buffer: Res<RewindBuffer>,
+    (stage_info, rule, course): (Res<StageInfo>, Res<GameRule>, Option<Res<CourseProgress>>),
+    active_group: Option<Res<ActiveControlGroup>>,
+    id_query: Query<&StageObjectId>,
+    timer_query: Query<&CountDownTimer, With<RemainingTime>>,
+    (passed_time, operation_amount, collected_items, bounce_count): (
+        Res<PassedTime>,
+        Res<OperationAmount>,
+        Res<CollectedItems>,
+        Res<BounceCount>,
+    ),
+    (max_ball_speed, longest_airborne, rewind_count, slow_motion): (
+        Res<MaxBallSpeed>,
+        Res<LongestAirborne>,
+        Res<RewindCount>,
+        Res<SlowMotion>,
+    ),
+) {
+    if event_reader.iter().count() == 0 {
+        return;
+    }
+    let snapshot = if let Some(snapshot) = buffer.latest() {
+        snapshot
+    } else {
+        return;
+    };
+    if find_stage(stage_info.stage_title).is_none() {
+        return;
+    }
+    let id_of = |entity: Entity| id_query.get(entity).ok().copied();
+    let saved = SavedGame {
+        stage_title: stage_info.stage_title.to_string(),
+        par: stage_info.par,
+        rule: *rule,
+        course: course.map(|course| SavedCourse {
+            title: course.title.to_string(),
+            current: course.current,
+            total_strokes: course.total_strokes,
+            total_par: course.total_par,
+        }),
+        remaining_time: timer_query
+            .get_single()
+            .map(|timer| timer.count())
+            .unwrap_or(0),
+        balls: snapshot
+            .balls
+            .iter()
+            .map(|ball| SavedBall {
+                ball_type: ball.ball_type,
+                pos: ball.pos,
+                vel: ball.vel,
+                nocking: ball.nocking,
+                airborne_frames: ball.airborne_frames,
+                checkpoints: ball.checkpoints.clone(),
+            })
+            .collect(),
+        blocks: snapshot
+            .blocks
+            .iter()
+            .filter_map(|block| {
+                Some(SavedBlock {
+                    id: id_of(block.entity)?,
+                    translation: block.transform.translation,
+                    rotation: block.transform.rotation,
+                    block_transform: block.block_transform.clone(),
+                    rotate_strategy: block.rotate_strategy.clone(),
+                    slide_strategy: block.slide_strategy.clone(),
+                    updaters: block.updaters.as_ref().map(save_updaters),
+                })
+            })
+            .collect(),
+        switches: snapshot
+            .switches
+            .iter()
+            .filter_map(|switch| {
+                Some(SavedSwitch {
+                    id: id_of(switch.entity)?,
+                    switch: switch.switch.clone(),
+                    pressed_once: switch.pressed_once,
+                    timer: switch.timer,
+                })
+            })
+            .collect(),
+        launchers: snapshot
+            .launchers
+            .iter()
+            .filter_map(|launcher| {
+                Some(SavedLauncher {
+                    id: id_of(launcher.entity)?,
+                    angle: launcher.angle,
+                    state: launcher.state,
+                })
+            })
+            .collect(),
+        goal_counts: snapshot
+            .goal_counts
+            .iter()
+            .filter_map(|(entity, count)| Some((id_of(*entity)?, *count)))
+            .collect(),
+        magazine: snapshot.magazine.clone(),
+        score: snapshot.score,
+        goaled_balls: snapshot.goaled_balls,
+        lost_balls: snapshot.lost_balls,
+        strokes: snapshot.strokes,
+        next_goal_order: snapshot.next_goal_order,
+        reached_goals: snapshot
+            .reached_goals
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        collected_item_ids: snapshot
+            .items
+            .iter()
+            .filter(|(_, collected)| *collected)
+            .filter_map(|(entity, _)| id_of(*entity))
+            .collect(),
+        passed_checkpoint_ids: snapshot
+            .checkpoints
+            .iter()
+            .filter(|(_, passed)| *passed)
+            .filter_map(|(entity, _)| id_of(*entity))
+            .collect(),
+        control_group: active_group.map(|active| active.current),
+        passed_time: passed_time.0,
+        operation_amount: operation_amount.0,
+        collected_items: collected_items.0,
+        bounce_count: bounce_count.0,
+        max_ball_speed: max_ball_speed.0,
+        longest_airborne: longest_airborne.0,
+        rewinds: rewind_count.0,
+        slow_motion_meter: slow_motion.meter,
+        slow_motion_used: slow_motion.used,
+    };
+    save_ron_file(SUSPEND_FILE, &saved);
+}
+
+/// 再開したゲームの最初のフレームで, 出現したステージのものに保存した状態を書き戻し, 保存データを消す
+#[allow(clippy::too_many_arguments)]
+fn resume_game(
+    mut commands: Commands,
+    saved: Option<Res<SavedGame>>,
+    stage_info: Res<StageInfo>,
+    id_query: Query<(Entity, &StageObjectId, Option<&SwitchReceiver>)>,
+    timer_query: Query<Entity, With<RemainingTime>>,
+    mut buffer: ResMut<RewindBuffer>,
+    mut targets: SnapshotTargets,
+    (mut passed_time, mut operation_amount, mut bounce_count, active_group): (
+        ResMut<PassedTime>,
+        ResMut<OperationAmount>,
+        ResMut<BounceCount>,
+        Option<ResMut<ActiveControlGroup>>,
+    ),
+    (mut max_ball_speed, mut longest_airborne, mut rewind_count, mut slow_motion): (
+        ResMut<MaxBallSpeed>,
+        ResMut<LongestAirborne>,
+        ResMut<RewindCount>,
+        ResMut<SlowMotion>,
+    ),
+) {
+    let saved = if let Some(saved) = saved {
+        saved
+    } else {
+        return;
+    };
+    let entity_of = |id: StageObjectId| {
+        id_query
+            .iter()
+            .find(|(_, object_id, _)| **object_id == id)
+            .map(|(entity, ..)| entity)
+    };
+    let receiver_of = |entity: Entity| {
+        id_query
+            .get(entity)
+            .ok()
+            .and_then(|(.., receiver)| receiver)
+    };
+    // 名前付きゴールの名前はステージ情報のものに置き換える
+    let reached_goals = stage_info
+        .win_conditions
+        .iter()
+        .filter_map(|condition| match condition {
+            WinCondition::ReachGoal(name) if saved.reached_goals.iter().any(|n| n == name) => {
+                Some(*name)
+            }
+            _ => None,
+        })
+        .collect();
+    // 保存データにないアイテムやチェックポイントは, 取得前や通過前に戻す
+    let flags_of = |is_target: fn(&StageObjectId) -> bool, saved_ids: &[StageObjectId]| {
+        id_query
+            .iter()
+            .filter(|(_, id, _)| is_target(id))
+            .map(|(entity, id, _)| (entity, saved_ids.contains(id)))
+            .collect()
+    };
+    let snapshot = Snapshot {
+        balls: saved
+            .balls
+            .iter()
+            .map(|ball| BallSnapshot {
+                entity: None,
+                ball_type: ball.ball_type,
+                pos: ball.pos,
+                vel: ball.vel,
+                nocking: ball.nocking,
+                airborne_frames: ball.airborne_frames,
+                checkpoints: ball.checkpoints.clone(),
+            })
+            .collect(),
+        blocks: saved
+            .blocks
+            .iter()
+            .filter_map(|block| {
+                let entity = entity_of(block.id)?;
+                let updaters = block.updaters.as_ref().map(|updaters| {
+                    UpdaterVec(
+                        updaters
+                            .iter()
+                            .filter_map(|updater| restore_updater(updater, receiver_of(entity)))
+                            .collect(),
+                    )
+                });
+                Some(BlockSnapshot {
+                    entity,
+                    transform: Transform::from_translation(block.translation)
+                        .with_rotation(block.rotation),
+                    block_transform: block.block_transform.clone(),
+                    rotate_strategy: block.rotate_strategy.clone(),
+                    slide_strategy: block.slide_strategy.clone(),
+                    updaters,
+                })
+            })
+            .collect(),
+        switches: saved
+            .switches
+            .iter()
+            .filter_map(|switch| {
+                Some(SwitchSnapshot {
+                    entity: entity_of(switch.id)?,
+                    switch: switch.switch.clone(),
+                    pressed_once: switch.pressed_once,
+                    timer: switch.timer,
+                })
+            })
+            .collect(),
+        launchers: saved
+            .launchers
+            .iter()
+            .filter_map(|launcher| {
+                Some(LauncherSnapshot {
+                    entity: entity_of(launcher.id)?,
+                    angle: launcher.angle,
+                    state: launcher.state,
+                })
+            })
+            .collect(),
+        goal_counts: saved
+            .goal_counts
+            .iter()
+            .filter_map(|(id, count)| Some((entity_of(*id)?, *count)))
+            .collect(),
+        magazine: saved.magazine.clone(),
+        score: saved.score,
+        goaled_balls: saved.goaled_balls,
+        lost_balls: saved.lost_balls,
+        strokes: saved.strokes,
+        next_goal_order: saved.next_goal_order,
+        reached_goals,
+        items: flags_of(
+            |id| matches!(id, StageObjectId::Item(_)),
+            &saved.collected_item_ids,
+        ),
+        checkpoints: flags_of(
+            |id| matches!(id, StageObjectId::Checkpoint(_)),
+            &saved.passed_checkpoint_ids,
+        ),
+        collected_items: saved.collected_items,
+    };
+    targets.apply(snapshot, &mut buffer, false);
+
+    for ent in timer_query.iter() {
+        commands
+            .entity(ent)
+            .insert(CountDownTimer::new(saved.remaining_time));
+    }
+    passed_time.0 = saved.passed_time;
+    operation_amount.0 = saved.operation_amount;
+    bounce_count.0 = saved.bounce_count;
+    if let (Some(mut active), Some(current)) = (active_group, saved.control_group) {
+        if current < active.groups.len() {
+            active.current = current;
+        }
+    }
+    max_ball_speed.0 = saved.max_ball_speed;
+    longest_airborne.0 = saved.longest_airborne;
+    rewind_count.0 = saved.rewinds;
+    *slow_motion = SlowMotion {
+        active: false,
+        meter: saved.slow_motion_meter,
+        used: saved.slow_motion_used,
+    };
+    commands.remove_resource::<SavedGame>();
+    // 書き戻し終えてから消す. それまでに終了しても次の起動で再開できる
+    if let Err(err) = std::fs::remove_file(SUSPEND_FILE) {
+        warn!("failed to remove {}: {}", SUSPEND_FILE, err);
+    }
+}
+
+pub struct SuspendPlugin;
+impl Plugin for SuspendPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(suspend_on_window_close.label("suspend:window_close"))
+                .with_system(
+                    suspend_game
+                        .after("return_to_title")
+                        .after("rewind:record")
+                        .after("suspend:window_close"),
+                )
+                // スイッチの状態を書き戻した次のフレームで効果を発動させないようにする
+                .with_system(resume_game.after("switch:execute_change")),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Paused)
+                .with_system(suspend_on_window_close.label("suspend:window_close"))
+                .with_system(
+                    suspend_game
+                        .after("pause:option_processing")
+                        .after("suspend:window_close"),
+                ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+    use crate::{
+        components::{
+            ball::{Ball, BallNocking, BallType, GoalinBall},
+            game::{GoaledBall, Score},
+            goal::GoalHole,
+            physics::{position::Position, velocity::Velocity},
+        },
+        stages::generate::generate_stage,
+        systems::{
+            ball::spawn_ball_entity,
+            rewind::RewindPlugin,
+            simulate::{headless_app, start_game},
+        },
+    };
+
+    fn suspend_app(stage_info: &StageInfo) -> App {
+        let mut app = headless_app(stage_info);
+        // 画面がないのでウィンドウを閉じるイベントだけ用意する
+        app.add_event::<WindowCloseRequested>();
+        app.add_plugin(RewindPlugin);
+        app.add_plugin(SuspendPlugin);
+        app
+    }
+
+    fn spawn_free_ball(app: &mut App, pos: Vec2, vel: Vec2) -> Entity {
+        let mut queue = CommandQueue::default();
+        let ball = {
+            let mut commands = Commands::new(&mut queue, &app.world);
+            let ball = spawn_ball_entity(&mut commands, BallType::Normal, pos);
+            commands
+                .entity(ball)
+                .remove::<BallNocking>()
+                .insert(Velocity(vel));
+            ball
+        };
+        queue.apply(&mut app.world);
+        ball
+    }
+
+    #[test]
+    fn suspended_game_resumes_with_absorbing_ball_counted() {
+        let stage_info = generate_stage(7, 1);
+        let goal_pos = stage_info.goal_pos[0].pos;
+        let mut app = suspend_app(&stage_info);
+        start_game(&mut app);
+
+        // ゴールに入れたボールが吸い込まれている途中と, まだ動いているボールを用意して中断する
+        let goaled = spawn_free_ball(&mut app, goal_pos, Vec2::ZERO);
+        spawn_free_ball(&mut app, Vec2::new(-300.0, 0.0), Vec2::new(0.5, 0.0));
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(app.world.get::<GoalinBall>(goaled).is_some());
+        app.world.send_event(SuspendGameEvent);
+        app.update();
+        let saved = load_suspended_game().unwrap();
+        assert_eq!(saved.score, 1);
+        assert_eq!(saved.goaled_balls, 1);
+        assert_eq!(saved.balls.len(), 1);
+        let (ball_pos, ball_vel) = (saved.balls[0].pos, saved.balls[0].vel);
+
+        let mut app = suspend_app(&stage_info);
+        app.insert_resource(saved);
+        start_game(&mut app);
+        let world = &mut app.world;
+        assert_eq!(world.resource::<Score>().0, 1);
+        assert_eq!(world.resource::<GoaledBall>().0, 1);
+        let goal_counts = world
+            .query::<&GoalHole>()
+            .iter(world)
+            .map(|goal| goal.count)
+            .collect::<Vec<u32>>();
+        assert_eq!(goal_counts, vec![1]);
+        let balls = world
+            .query_filtered::<(&Position, &Velocity), With<Ball>>()
+            .iter(world)
+            .map(|(pos, vel)| (pos.0, vel.0))
+            .collect::<Vec<(Vec2, Vec2)>>();
+        assert_eq!(balls, vec![(ball_pos, ball_vel)]);
+        assert!(load_suspended_game().is_none());
+    }
+}
@@ -0,0 +1,200 @@
+//! スローモーションと時間の速さを変える領域
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use super::collision::rect_contains_point;
+use crate::{
+    components::{
+        ball::Ball,
+        game::NowGameOver,
+        input::{ActionInput, InputAction},
+        physics::position::Position,
+        time_scale::{
+            LocalTimeScale, SlowMotion, SlowMotionMeter, TimeScale, TimeScaleZone,
+            SLOW_MOTION_FRAMES, SLOW_MOTION_SCALE,
+        },
+    },
+    events::time_scale::SpawnTimeScaleZoneEvent,
+    AppState,
+};
+
+fn spawn_time_scale_zone(
+    mut commands: Commands,
+    mut event_listener: EventReader<SpawnTimeScaleZoneEvent>,
+) {
+    for ev in event_listener.iter() {
+        let zone = TimeScaleZone {
+            scale: ev.scale,
+            extents: ev.extents,
+        };
+        let zone_shape = shapes::Rectangle {
+            extents: ev.extents,
+            origin: RectangleOrigin::Center,
+        };
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &zone_shape,
+                DrawMode::Outlined {
+                    fill_mode: FillMode::color(zone.color()),
+                    outline_mode: StrokeMode::new(Color::DARK_GRAY, 2.0),
+                },
+                Transform {
+                    translation: ev.pos.extend(6.0),
+                    ..Default::default()
+                },
+            ))
+            .insert(zone);
+    }
+}
+
+/// ボールの中心が入っている領域の速さをそのボールの速さにする. 重なっている場合は掛け合わせる
+fn apply_time_scale_zones(
+    zone_query: Query<(&TimeScaleZone, &Transform)>,
+    mut ball_query: Query<(&Position, &mut LocalTimeScale), With<Ball>>,
+) {
+    for (ball_pos, mut local) in ball_query.iter_mut() {
+        let scale = zone_query
+            .iter()
+            .filter(|(zone, trans)| {
+                rect_contains_point(trans.translation.truncate(), zone.extents, ball_pos.0)
+            })
+            .map(|(zone, _)| zone.scale)
+            .product::<f32>();
+        // 変わったときだけ書き換える
+        if local.0 != scale {
+            local.0 = scale;
+        }
+    }
+}
+
+/// 入力でスローモーションを切り替える. ゲームオーバー後や残量がないときは始められない
+fn toggle_slow_motion(
+    action_in: Res<ActionInput>,
+    is_gameover: Option<Res<NowGameOver>>,
+    mut slow_motion: ResMut<SlowMotion>,
+) {
+    if !action_in.just_pressed(InputAction::SlowMotion) {
+        return;
+    }
+    if slow_motion.active {
+        slow_motion.active = false;
+    } else if is_gameover.is_none() && slow_motion.meter > 0 {
+        slow_motion.active = true;
+    }
+}
+
+/// スローモーション中は残量を減らし, 全体の時間の速さに反映する
+fn update_slow_motion(
+    is_gameover: Option<Res<NowGameOver>>,
+    mut slow_motion: ResMut<SlowMotion>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    if is_gameover.is_some() {
+        slow_motion.active = false;
+    }
+    if slow_motion.active {
+        slow_motion.meter -= 1;
+        slow_motion.used += 1;
+        if slow_motion.meter == 0 {
+            slow_motion.active = false;
+        }
+    }
+    let scale = if slow_motion.active {
+        SLOW_MOTION_SCALE
+    } else {
+        1.0
+    };
+    if time_scale.0 != scale {
+        time_scale.0 = scale;
+    }
+}
+
+fn init_slow_motion_meter(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(20.0),
+                    right: Val::Px(20.0),
+                    ..default()
+                },
+                ..default()
+            },
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/ume-tgs5.ttf"),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+            ),
+            ..default()
+        })
+        .insert(SlowMotionMeter);
+}
+
+/// 残量を10段階のゲージで表示する
+fn show_slow_motion_meter(
+    slow_motion: Res<SlowMotion>,
+    mut query: Query<&mut Text, With<SlowMotionMeter>>,
+) {
+    if let Ok(mut text) = query.get_single_mut() {
+        let filled = (slow_motion.meter * 10).div_ceil(SLOW_MOTION_FRAMES) as usize;
+        text.sections[0].value = format!("slow {}{}", "|".repeat(filled), ".".repeat(10 - filled));
+        text.sections[0].style.color = if slow_motion.active {
+            Color::CYAN
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+/// ゲーム以外の画面に時間の速さを持ち越さない
+fn reset_time_scale(mut time_scale: ResMut<TimeScale>) {
+    time_scale.0 = 1.0;
+}
+
+pub struct TimeScalePlugin;
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_enter(AppState::Game)
+                .with_system(spawn_time_scale_zone)
+                .after("spawn_stage_entities"),
+        );
+        app.add_system_set(SystemSet::on_enter(AppState::Game).with_system(init_slow_motion_meter));
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                apply_time_scale_zones
+                    .after("goal:sync_goal_pos")
+                    .before("execute_force")
+                    .label("time_scale:apply_zones"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                toggle_slow_motion
+                    .after("count_down_update")
+                    .after("frame_counter_update")
+                    .label("slow_motion:toggle"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game).with_system(
+                update_slow_motion
+                    .after("slow_motion:toggle")
+                    .before("execute_force")
+                    .label("slow_motion:update"),
+            ),
+        );
+        app.add_system_set(
+            SystemSet::on_update(AppState::Game)
+                .with_system(show_slow_motion_meter.after("fan:update_wind_vfx")),
+        );
+        // 時間の進む速さを読むシステムはすべて移動より前に動く
+        app.add_system_set(
+            SystemSet::on_exit(AppState::Game).with_system(reset_time_scale.after("move_pos")),
+        );
+    }
+}
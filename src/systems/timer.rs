@@ -1,10 +1,14 @@
 use bevy::prelude::*;
 
-use crate::components::timer::{CountDownTimer, FrameCounter};
+use crate::components::{
+    time_scale::TimeScale,
+    timer::{CountDownTimer, FrameCounter},
+};
 
-pub fn count_down_update(mut query: Query<&mut CountDownTimer>) {
+/// ゲーム内の時間の進む速さに合わせてカウントダウンする
+pub fn count_down_update(time_scale: Res<TimeScale>, mut query: Query<&mut CountDownTimer>) {
     for mut timer in query.iter_mut() {
-        timer.tick();
+        timer.tick(time_scale.0);
     }
 }
 /// 使い終わったタイマーは自動で削除される.
@@ -31,6 +35,7 @@ fn frame_counter_update(mut query: Query<&mut FrameCounter>) {
 pub struct TimersPlugin;
 impl Plugin for TimersPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<TimeScale>();
         app.add_system(count_down_update.label("count_down_update"));
         app.add_system(frame_counter_update.label("frame_counter_update"));
         app.add_system_to_stage(CoreStage::Last, delete_counter);
@@ -21,3 +21,48 @@ pub fn calc_edge_points_of_rectangle(
         EdgeDirection::Right => [block_orig_pos - refl_half_ext, block_orig_pos + half_ext],
     }
 }
+
+/// RON形式のファイルを読み込む. ファイルがない, または読めない場合はデフォルト値を返す.
+pub fn load_ron_file<T: serde::de::DeserializeOwned + Default>(path: &str) -> T {
+    match std::fs::read_to_string(path) {
+        Ok(text) => ron::from_str::<T>(&text).unwrap_or_else(|err| {
+            warn!("failed to parse {}: {}", path, err);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+/// RON形式でファイルに書き込む. 親ディレクトリがなければ作成する.
+pub fn save_ron_file<T: serde::Serialize>(path: &str, value: &T) {
+    let text = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("failed to serialize {}: {}", path, err);
+            return;
+        }
+    };
+    let dir = std::path::Path::new(path).parent().unwrap();
+    if let Err(err) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(path, text)) {
+        warn!("failed to write {}: {}", path, err);
+    }
+}
+
+/// 種から再現できる乱数（xorshift）
+pub struct Rng(u64);
+impl Rng {
+    /// 種が0だと0しか出ないので1にする
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    /// [min, max]の整数
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        min + (self.next_u64() % (max - min + 1) as u64) as i32
+    }
+}